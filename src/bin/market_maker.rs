@@ -1,81 +1,196 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic)]
 
 use ethers::signers::LocalWallet;
-use hyperliquid_rust_sdk::{Input, MarketMaker};
+use hyperliquid_rust_sdk::{
+    BaseUrl, BinanceFeed, CircuitBreaker, CoinbaseFeed, Config, ExecutionStyle, FeedTask, Input,
+    MarketMaker, MarketMakerError, MidSource, ReconnectLimiter, ReferenceVenue,
+    SharedReferencePrice, SizeMode, StaleOrderPolicy, StatMode, StrategyMode, Subscriptions,
+    TimeInForce, TokenBucket, WindowMode,
+};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::Mutex as AsyncMutex;
-use futures_util::StreamExt;
-use serde::Deserialize;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use url::Url;
-use std::time::{Duration, Instant};
-use log::info;
-
-#[derive(Debug, Deserialize)]
-struct Trade {
-    e: String,  // Event type
-    E: u64,     // Event time
-    s: String,  // Symbol
-    t: u64,     // Trade ID
-    p: String,  // Price
-    q: String,  // Quantity
-    T: u64,     // Transaction time
-    X: String,  // Type
-    m: bool,    // Is buyer market maker
-}
-
-/// Connect to Binance and continuously update latest_binance_price.
-async fn run_binance_feed(latest_binance_price: Arc<Mutex<f64>>) -> Result<(), Box<dyn std::error::Error>> {
-    let url = Url::parse("wss://fstream.binance.com/ws/btcusdt@trade")?;
-    let (ws_stream, _) = connect_async(url).await?;
-    let (_, mut read) = ws_stream.split();
-
-    while let Some(msg) = read.next().await {
-        if let Ok(Message::Text(text)) = msg {
-            if let Ok(trade) = serde_json::from_str::<Trade>(&text) {
-                if let Ok(price) = trade.p.parse::<f64>() {
-                    let mut binance_price = latest_binance_price.lock().unwrap();
-                    *binance_price = price;
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
 
 #[tokio::main]
+// One line per configured field
+#[allow(clippy::too_many_lines)]
 async fn main() {
     env_logger::init();
 
-    // Key was randomly generated for testing and shouldn't be used with any real funds
-    let wallet: LocalWallet = ""
-        .parse()
-        .unwrap();
+    // Strategy comes from a TOML file, see `Config`. The key does too, or else from
+    // HL_PRIVATE_KEY or the file HL_PRIVATE_KEY_FILE names.
+    let Some(config_path) = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("MARKET_MAKER_CONFIG").ok())
+    else {
+        eprintln!("Usage: market_maker <config.toml>, or set MARKET_MAKER_CONFIG");
+        std::process::exit(2);
+    };
+    let config = Config::load(Path::new(&config_path)).unwrap_or_else(|e| exit_with(&e));
+    let wallet: LocalWallet = config.wallet().unwrap_or_else(|e| exit_with(&e));
+
+    // Create a shared variable for the reference price
+    let latest_binance_price = SharedReferencePrice::default();
 
-    // Create a shared variable for the Binance price
-    let latest_binance_price = Arc::new(Mutex::new(0.0));
+    // Shared by every feed so a persistent fault can't turn into a reconnect storm
+    let reconnect_limiter = Arc::new(Mutex::new(ReconnectLimiter::new(
+        5,
+        Duration::from_mins(1),
+        Duration::from_mins(5),
+    )));
 
-    // Spawn the Binance feed in the background
-    {
-        let binance_clone = latest_binance_price.clone();
-        tokio::spawn(async move {
-            if let Err(e) = run_binance_feed(binance_clone).await {
-                eprintln!("Binance feed error: {:?}", e);
+    // Spawn the reference feed in the background, reconnecting when it drops. The
+    // VWAP of the last 20 trades is referenced rather than the last print, unless the
+    // config picks the Binance microprice.
+    let base_url = config.base_url;
+    let testnet = matches!(base_url, BaseUrl::Testnet);
+    let reference_feed = match config.reference_venue {
+        ReferenceVenue::Binance => {
+            let mut feed = BinanceFeed::new(&config.binance_symbol())
+                .unwrap_or_else(|e| exit_with_feed_error("Binance symbol", &e));
+            if testnet {
+                feed = feed
+                    .with_testnet()
+                    .unwrap_or_else(|e| exit_with_feed_error("Binance testnet URL", &e));
             }
-        });
-    }
+            feed.with_price_source(config.binance_price_source)
+                .unwrap_or_else(|e| exit_with_feed_error("Binance stream", &e))
+                .with_vwap_window(20)
+                .with_reconnect_limiter(reconnect_limiter.clone())
+                .spawn(latest_binance_price.clone())
+        }
+        ReferenceVenue::Coinbase => {
+            let mut feed = CoinbaseFeed::new(&config.coinbase_product())
+                .unwrap_or_else(|e| exit_with_feed_error("Coinbase product", &e));
+            if testnet {
+                feed = feed
+                    .with_sandbox()
+                    .unwrap_or_else(|e| exit_with_feed_error("Coinbase sandbox URL", &e));
+            }
+            feed.with_vwap_window(20)
+                .with_reconnect_limiter(reconnect_limiter.clone())
+                .spawn(latest_binance_price.clone())
+        }
+    };
 
-    // Define our single trading configuration (just BTC)
-    let input = Input {
-        asset: "BTC".to_string(),
+    // Defaults for everything the config file leaves out
+    let mut input = Input {
+        asset: config.asset.clone(),
+        base_url,
+        market_kind: config.market_kind,
+        window_size: 100,
+        min_correlation: None,
+        window_mode: WindowMode::Count,
+        stat_mode: StatMode::Window,
+        z_threshold: 2.0,
+        size_mode: SizeMode::Fixed(0.001),
+        balance_refresh: Duration::from_mins(1),
         target_liquidity: 0.0002,
         max_bps_diff: 20,
+        max_bps_diff_band: false,
         half_spread: 5,
         max_absolute_position_size: 0.004,
         decimals: 0,
+        size_decimals: 5,
         wallet,
+        one_sided_book_band_bps: None,
+        cross_buffer_bps: Some(5.0),
+        hedge_delta_threshold: None,
+        hedge_trader: config.hedge_trader().unwrap_or_else(|e| exit_with(&e)),
+        window_prior: None,
+        fill_dedup_window: 1000,
+        reference_confirm_bps: None,
+        shutdown_timeout: Duration::from_secs(10),
+        standby_wallet: None,
+        signal_skew_weight: 0.0,
+        protective_stop_bps: None,
+        protective_take_profit_bps: None,
+        protective_reprice_bps: 10.0,
+        stop_loss_bps: None,
+        take_profit_bps: None,
+        min_hold: Duration::from_secs(30),
+        max_hold_time: Some(Duration::from_hours(4)),
+        post_fill_quiet: Duration::ZERO,
+        long_window_size: None,
+        long_z_threshold: 1.0,
+        returns_window: 100,
+        return_interval: None,
+        stats_interval: Some(Duration::from_mins(15)),
+        exposure: None,
+        z_threshold_schedule: Vec::new(),
+        session_end: None,
+        session_start: None,
+        volatility_scaling: None,
+        loss_penalty: 1.0,
+        loss_penalty_half_life: Duration::from_mins(5),
+        decision_history: 100,
+        reconcile_min_interval: Duration::from_secs(30),
+        entry_band_bps: None,
+        min_stddev_bps: None,
+        min_stddev_mean_fraction: None,
+        reconnect_limiter: Some(reconnect_limiter),
+        confirm_ticks: 1,
+        outlier_k: Some(20.0),
+        outlier_confirm_ticks: 3,
+        message_timeout: Some(Duration::from_secs(30)),
+        heartbeat_interval: Some(Duration::from_mins(1)),
+        log_sample_rate: 1,
+        stale_orders: Some(StaleOrderPolicy::CancelAll),
+        quote_depth_fraction: None,
+        log_position_headroom: false,
+        latency_warn_threshold: Some(Duration::from_millis(200)),
+        fair_value: None,
+        dry_run: None,
+        execution_style: ExecutionStyle::Cross,
+        post_only: false,
+        maker_rebate_bps: 0.0,
+        max_price_age: Duration::from_secs(5),
+        max_size_multiplier: 1.0,
+        inventory_skew: 0.0,
+        paper_trading: false,
+        simulated_latency: Duration::ZERO,
+        simulated_slippage_bps: 0.0,
+        min_trade_interval: Duration::from_secs(1),
+        resting_order_timeout: Some(Duration::from_secs(30)),
+        max_open_orders: Some(10),
+        order_tif: TimeInForce::Ioc,
+        strategy: StrategyMode::Arbitrage,
+        warmup_ticks: 300,
+        max_order_notional: 500.0,
+        max_daily_notional: Some(50_000.0),
+        taker_fee_bps: 4.5,
+        min_edge_bps: 0.0,
+        funding_refresh: Some(Duration::from_mins(5)),
+        expected_holding_time: Duration::from_hours(1),
+        max_order_retries: 2,
+        order_retry_delay: Duration::from_millis(100),
+        order_rate_limit: Some(TokenBucket::new(2.0, 5.0)),
+        order_jitter: Duration::ZERO,
+        jitter_seed: None,
+        circuit_breaker: Some(CircuitBreaker::new(5, Duration::from_mins(5))),
+        mid_source: MidSource::AllMids,
+        subscriptions: if config.signal_only {
+            Subscriptions::signal_only()
+        } else {
+            Subscriptions::default()
+        },
+        reference_venue: config.reference_venue,
+        mark_source: config.mark_source,
+        binance_price_source: config.binance_price_source,
+        recheck_edge: true,
+        imbalance_threshold: None,
+        imbalance_levels: 5,
+        trade_log_path: None,
+        state_path: None,
+        state_max_age: Duration::from_mins(10),
+        observer: None,
+        #[cfg(feature = "metrics")]
+        metrics_port: Some(9184),
+        #[cfg(feature = "admin")]
+        admin_addr: None,
     };
+    config.apply(&mut input);
 
     let wallet_mutex = Arc::new(AsyncMutex::new(input.wallet.clone()));
     let binance_price_clone = latest_binance_price.clone();
@@ -89,6 +204,59 @@ async fn main() {
 
     // Assign the binance price reference to the market maker
     mm.binance_price = binance_price_clone;
+    mm.attach_feed(reference_feed);
+
+    // Pause trading on SIGUSR1, resume it on SIGUSR2 and log the decision history on
+    // SIGHUP
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let control = mm.control_handle();
+        match (
+            signal(SignalKind::user_defined1()),
+            signal(SignalKind::user_defined2()),
+            signal(SignalKind::hangup()),
+        ) {
+            (Ok(mut pause), Ok(mut resume), Ok(mut dump_decisions)) => {
+                // Stopped along with the reference feed, so it can't outlive the bot
+                mm.attach_feed(FeedTask::spawn(move |mut cancellation| async move {
+                    loop {
+                        let sent = tokio::select! {
+                            _ = pause.recv() => control.pause(),
+                            _ = resume.recv() => control.resume(),
+                            _ = dump_decisions.recv() => control.dump_decisions(),
+                            () = cancellation.cancelled() => break,
+                        };
+                        if !sent {
+                            break;
+                        }
+                    }
+                }));
+            }
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                eprintln!("Error listening for control signals: {e}");
+            }
+        }
+    }
+
+    // Cancel resting orders and log the final position on Ctrl-C
+    mm.start_until(async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            // Without a signal handler, run until the feeds end
+            eprintln!("Error listening for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    })
+    .await;
+}
+
+fn exit_with(error: &MarketMakerError) -> ! {
+    eprintln!("{error}");
+    std::process::exit(1);
+}
 
-    mm.start().await;
+fn exit_with_feed_error(what: &str, error: &hyperliquid_rust_sdk::Error) -> ! {
+    exit_with(&MarketMakerError::Config(format!(
+        "invalid {what}: {error}"
+    )))
 }
@@ -0,0 +1,370 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    prelude::*, run_with_reconnect, Backoff, BinancePriceSource, Error, FeedCancellation, FeedTask,
+    ReconnectLimiter, SharedReferencePrice, TradeVwap,
+};
+
+/// A trade from the Binance futures `<symbol>@trade` stream.
+#[derive(Debug, Clone, Deserialize)]
+#[allow(non_snake_case)]
+pub struct BinanceTrade {
+    pub e: String, // Event type
+    pub E: u64,    // Event time
+    pub s: String, // Symbol
+    pub t: u64,    // Trade ID
+    pub p: String, // Price
+    pub q: String, // Quantity
+    pub T: u64,    // Transaction time
+    pub X: String, // Type
+    pub m: bool,   // Is buyer market maker
+}
+
+/// A snapshot from the Binance futures `<symbol>@depth<levels>@100ms` partial book
+/// stream, best levels first.
+#[derive(Debug, Clone, Deserialize)]
+#[allow(non_snake_case)]
+pub struct BinanceDepth {
+    pub E: u64,                   // Event time
+    pub s: String,                // Symbol
+    pub U: u64,                   // First update ID in event
+    pub u: u64,                   // Final update ID in event
+    pub pu: u64,                  // Final update ID of the previous event
+    pub b: Vec<(String, String)>, // Bids, price and quantity
+    pub a: Vec<(String, String)>, // Asks, price and quantity
+}
+
+/// Top of the Binance book as last seen, so snapshots arriving out of order can't
+/// roll it back.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TopOfBook {
+    last_update_id: u64,
+    bid: (f64, f64),
+    ask: (f64, f64),
+}
+
+impl TopOfBook {
+    /// Take the best levels of a depth snapshot, unless it is older than the one
+    /// already applied or its book is crossed or empty. Returns whether it was taken.
+    fn apply(&mut self, depth: &BinanceDepth) -> bool {
+        if depth.u <= self.last_update_id {
+            return false;
+        }
+        let level = |levels: &[(String, String)]| {
+            let (px, qty) = levels.first()?;
+            let px = px
+                .parse::<f64>()
+                .ok()
+                .filter(|px| px.is_finite() && *px > 0.0)?;
+            let qty = qty
+                .parse::<f64>()
+                .ok()
+                .filter(|qty| qty.is_finite() && *qty > 0.0)?;
+            Some((px, qty))
+        };
+        let (Some(bid), Some(ask)) = (level(&depth.b), level(&depth.a)) else {
+            return false;
+        };
+        if bid.0 >= ask.0 {
+            return false;
+        }
+        *self = Self {
+            last_update_id: depth.u,
+            bid,
+            ask,
+        };
+        true
+    }
+
+    /// Mid weighted towards the side with less size, where the price is more likely
+    /// to move next.
+    fn microprice(&self) -> f64 {
+        let ((bid_px, bid_qty), (ask_px, ask_qty)) = (self.bid, self.ask);
+        bid_px.mul_add(ask_qty, ask_px * bid_qty) / (bid_qty + ask_qty)
+    }
+}
+
+/// Reference price feed from the Binance futures trade or depth stream of one
+/// symbol.
+#[derive(Debug, Clone)]
+pub struct BinanceFeed {
+    symbol: String,
+    host: &'static str,
+    price_source: BinancePriceSource,
+    url: Url,
+    vwap_window: Option<usize>,
+    backoff: Backoff,
+    reconnect_limiter: Option<Arc<Mutex<ReconnectLimiter>>>,
+}
+
+impl BinanceFeed {
+    /// Feed for a symbol such as `BTCUSDT` or `ethusdt`.
+    #[allow(clippy::result_large_err)]
+    pub fn new(symbol: &str) -> Result<Self> {
+        let symbol = symbol.trim().to_lowercase();
+        if symbol.is_empty() {
+            return Err(Error::GenericParse("Binance symbol is empty".to_string()));
+        }
+        let price_source = BinancePriceSource::default();
+        let url = stream_url(MAINNET_HOST, &symbol, price_source)?;
+        Ok(Self {
+            symbol,
+            host: MAINNET_HOST,
+            price_source,
+            url,
+            vwap_window: None,
+            backoff: Backoff::new(Duration::from_secs(1), Duration::from_secs(30)),
+            reconnect_limiter: None,
+        })
+    }
+
+    /// Connect to the Binance futures testnet instead, to pair with the Hyperliquid
+    /// testnet.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the testnet URL can't be built for the symbol.
+    #[allow(clippy::result_large_err)]
+    pub fn with_testnet(mut self) -> Result<Self> {
+        self.url = stream_url(TESTNET_HOST, &self.symbol, self.price_source)?;
+        self.host = TESTNET_HOST;
+        Ok(self)
+    }
+
+    /// Take the price from the stream `price_source` names.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the stream URL can't be built for the symbol.
+    #[allow(clippy::result_large_err)]
+    pub fn with_price_source(mut self, price_source: BinancePriceSource) -> Result<Self> {
+        self.url = stream_url(self.host, &self.symbol, price_source)?;
+        self.price_source = price_source;
+        Ok(self)
+    }
+
+    /// Publish the VWAP of the last `window` trades rather than the last print. Only
+    /// applies to the trade stream.
+    pub fn with_vwap_window(mut self, window: usize) -> Self {
+        self.vwap_window = Some(window);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Check every reconnect against a limiter shared with other feeds.
+    pub fn with_reconnect_limiter(mut self, limiter: Arc<Mutex<ReconnectLimiter>>) -> Self {
+        self.reconnect_limiter = Some(limiter);
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Keep `out` updated in the background, reconnecting when the stream drops,
+    /// until the returned task is stopped.
+    pub fn spawn(self, out: SharedReferencePrice) -> FeedTask {
+        FeedTask::spawn(|cancellation| async move {
+            run_with_reconnect(
+                "Binance",
+                cancellation,
+                self.backoff.clone(),
+                self.reconnect_limiter.clone(),
+                |cancellation| self.run(out.clone(), cancellation),
+            )
+            .await;
+        })
+    }
+
+    /// Run one connection until it drops or is cancelled, closing the socket cleanly
+    /// on cancellation.
+    async fn run(
+        &self,
+        out: SharedReferencePrice,
+        mut cancellation: FeedCancellation,
+    ) -> Result<()> {
+        let mut vwap = self.vwap_window.map(TradeVwap::new);
+        // Fresh for every connection, update IDs aren't comparable across them
+        let mut book = TopOfBook::default();
+        let (ws_stream, _) = connect_async(self.url.clone())
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?;
+        let (mut write, mut read) = ws_stream.split();
+
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => msg,
+                () = cancellation.cancelled() => {
+                    write
+                        .send(Message::Close(None))
+                        .await
+                        .map_err(|e| Error::Websocket(e.to_string()))?;
+                    write
+                        .close()
+                        .await
+                        .map_err(|e| Error::Websocket(e.to_string()))?;
+                    break;
+                }
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            if let Ok(Message::Text(text)) = msg {
+                let price = match self.price_source {
+                    BinancePriceSource::Trade => parse_trade_price(&text, vwap.as_mut()),
+                    BinancePriceSource::Microprice => parse_microprice(&text, &mut book),
+                };
+                if let Some(price) = price {
+                    out.set(price);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const MAINNET_HOST: &str = "wss://fstream.binance.com";
+const TESTNET_HOST: &str = "wss://stream.binancefuture.com";
+
+/// Levels per side of the partial depth stream, only the best is used.
+const DEPTH_LEVELS: usize = 5;
+
+#[allow(clippy::result_large_err)]
+fn stream_url(host: &str, symbol: &str, price_source: BinancePriceSource) -> Result<Url> {
+    let stream = match price_source {
+        BinancePriceSource::Trade => "trade".to_string(),
+        BinancePriceSource::Microprice => format!("depth{DEPTH_LEVELS}@100ms"),
+    };
+    Url::parse(&format!("{host}/ws/{symbol}@{stream}"))
+        .map_err(|e| Error::GenericParse(e.to_string()))
+}
+
+/// Microprice after a depth snapshot, `None` for a malformed, stale or crossed one.
+fn parse_microprice(text: &str, book: &mut TopOfBook) -> Option<f64> {
+    let depth = serde_json::from_str::<BinanceDepth>(text).ok()?;
+    if depth.pu != book.last_update_id && book.last_update_id != 0 {
+        // Partial depth events are whole snapshots, so a gap loses nothing but is
+        // worth knowing about
+        debug!(
+            "Binance depth skipped from update {} to {}",
+            book.last_update_id, depth.pu
+        );
+    }
+    book.apply(&depth).then(|| book.microprice())
+}
+
+/// Reference price after a trade message, skipping malformed prints rather than
+/// feeding them to the signal.
+fn parse_trade_price(text: &str, vwap: Option<&mut TradeVwap>) -> Option<f64> {
+    let trade = serde_json::from_str::<BinanceTrade>(text).ok()?;
+    let price = trade
+        .p
+        .parse::<f64>()
+        .ok()
+        .filter(|price| price.is_finite() && *price > 0.0)?;
+    Some(match vwap {
+        Some(vwap) => vwap
+            .push(price, trade.q.parse().unwrap_or(0.0))
+            .unwrap_or(price),
+        None => price,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(px: &str, qty: &str) -> String {
+        format!(
+            r#"{{"e":"trade","E":1,"s":"BTCUSDT","t":1,"p":"{px}","q":"{qty}","T":1,"X":"MARKET","m":true}}"#
+        )
+    }
+
+    #[test]
+    fn symbol_url_test() {
+        let feed = BinanceFeed::new(" ETHUSDT ").unwrap();
+        assert_eq!(
+            feed.url().as_str(),
+            "wss://fstream.binance.com/ws/ethusdt@trade"
+        );
+        assert!(BinanceFeed::new("  ").is_err());
+        assert_eq!(
+            feed.with_testnet().unwrap().url().as_str(),
+            "wss://stream.binancefuture.com/ws/ethusdt@trade"
+        );
+    }
+
+    fn depth(pu: u64, u: u64, bid: (&str, &str), ask: (&str, &str)) -> String {
+        format!(
+            r#"{{"e":"depthUpdate","E":1,"T":1,"s":"BTCUSDT","U":{pu},"u":{u},"pu":{pu},"b":[["{}","{}"],["99","5"]],"a":[["{}","{}"],["102","5"]]}}"#,
+            bid.0, bid.1, ask.0, ask.1
+        )
+    }
+
+    #[test]
+    fn price_source_url_test() {
+        let feed = BinanceFeed::new("BTCUSDT")
+            .unwrap()
+            .with_price_source(BinancePriceSource::Microprice)
+            .unwrap();
+        assert_eq!(
+            feed.url().as_str(),
+            "wss://fstream.binance.com/ws/btcusdt@depth5@100ms"
+        );
+        assert_eq!(
+            feed.with_testnet().unwrap().url().as_str(),
+            "wss://stream.binancefuture.com/ws/btcusdt@depth5@100ms"
+        );
+    }
+
+    #[test]
+    fn parse_microprice_test() {
+        let mut book = TopOfBook::default();
+        // Three on the bid against one on the ask leans the price towards the ask
+        let price = parse_microprice(&depth(0, 10, ("100", "3"), ("101", "1")), &mut book);
+        assert!((price.unwrap() - 100.75).abs() < 1e-9);
+        assert_eq!(book.last_update_id, 10);
+
+        // An older snapshot, a crossed book and a malformed level leave the book as is
+        assert_eq!(
+            parse_microprice(&depth(5, 9, ("90", "1"), ("91", "1")), &mut book),
+            None
+        );
+        assert_eq!(
+            parse_microprice(&depth(10, 11, ("101", "1"), ("100", "1")), &mut book),
+            None
+        );
+        assert_eq!(
+            parse_microprice(&depth(10, 11, ("100", "0"), ("101", "1")), &mut book),
+            None
+        );
+        assert_eq!(parse_microprice(&trade("100", "1"), &mut book), None);
+        assert_eq!(book.last_update_id, 10);
+
+        let price = parse_microprice(&depth(10, 12, ("100", "1"), ("101", "1")), &mut book);
+        assert!((price.unwrap() - 100.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parse_trade_price_test() {
+        assert_eq!(parse_trade_price(&trade("100.5", "1"), None), Some(100.5));
+        assert_eq!(parse_trade_price(&trade("NaN", "1"), None), None);
+        assert_eq!(parse_trade_price("{}", None), None);
+
+        let mut vwap = TradeVwap::new(2);
+        parse_trade_price(&trade("100", "1"), Some(&mut vwap));
+        let price = parse_trade_price(&trade("103", "2"), Some(&mut vwap)).unwrap();
+        assert!((price - 102.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fmt;
+
+use crate::{prelude::*, req::parse_response, Error};
+
+const MAINNET_URL: &str = "https://fapi.binance.com";
+const TESTNET_URL: &str = "https://testnet.binancefuture.com";
+
+/// API key and secret of a Binance futures account. Printing them shows the key only.
+#[derive(Clone)]
+pub struct BinanceCredentials {
+    pub api_key: String,
+    pub secret_key: String,
+}
+
+impl fmt::Debug for BinanceCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinanceCredentials")
+            .field("api_key", &self.api_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Result of a Binance futures order, as returned by `POST /fapi/v1/order`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceOrder {
+    pub order_id: u64,
+    pub status: String,
+    pub executed_qty: String,
+    pub avg_price: String,
+}
+
+impl BinanceOrder {
+    /// Size and average price filled, zero if nothing was.
+    #[must_use]
+    pub fn filled(&self) -> (f64, f64) {
+        (
+            self.executed_qty.parse().unwrap_or(0.0),
+            self.avg_price.parse().unwrap_or(0.0),
+        )
+    }
+}
+
+/// Places market orders on one Binance futures symbol, to hedge Hyperliquid fills on
+/// the reference venue.
+#[derive(Debug, Clone)]
+pub struct BinanceTrader {
+    client: Client,
+    base_url: String,
+    symbol: String,
+    quantity_decimals: u32,
+    credentials: BinanceCredentials,
+}
+
+impl BinanceTrader {
+    /// Trader for a symbol such as `BTCUSDT`, with quantities floored to 3 decimals.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::GenericParse`] if the symbol is empty.
+    #[allow(clippy::result_large_err)]
+    pub fn new(symbol: &str, credentials: BinanceCredentials) -> Result<Self> {
+        let symbol = symbol.trim().to_uppercase();
+        if symbol.is_empty() {
+            return Err(Error::GenericParse("Binance symbol is empty".to_string()));
+        }
+        Ok(Self {
+            client: Client::new(),
+            base_url: MAINNET_URL.to_string(),
+            symbol,
+            quantity_decimals: 3,
+            credentials,
+        })
+    }
+
+    /// Trade on the Binance futures testnet instead.
+    #[must_use]
+    pub fn with_testnet(mut self) -> Self {
+        self.base_url = TESTNET_URL.to_string();
+        self
+    }
+
+    /// Decimals of the symbol's quantity step size.
+    #[must_use]
+    pub const fn with_quantity_decimals(mut self, quantity_decimals: u32) -> Self {
+        self.quantity_decimals = quantity_decimals;
+        self
+    }
+
+    #[must_use]
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// `quantity` floored to the symbol's step size.
+    #[must_use]
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        let factor = 10f64.powi(self.quantity_decimals.try_into().unwrap_or(i32::MAX));
+        (quantity.abs() * factor).floor() / factor
+    }
+
+    /// Send a market order for `quantity`, floored to the step size.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::GenericParse`] if the quantity rounds to zero, or the request
+    /// or response error.
+    pub async fn market_order(&self, is_buy: bool, quantity: f64) -> Result<BinanceOrder> {
+        let quantity = self.round_quantity(quantity);
+        if quantity <= 0.0 {
+            return Err(Error::GenericParse(format!(
+                "Hedge quantity rounds to zero at {} decimals",
+                self.quantity_decimals
+            )));
+        }
+        let side = if is_buy { "BUY" } else { "SELL" };
+        let query = sign(
+            &format!(
+                "symbol={}&side={side}&type=MARKET&quantity={quantity}&newOrderRespType=RESULT&timestamp={}",
+                self.symbol,
+                Utc::now().timestamp_millis()
+            ),
+            &self.credentials.secret_key,
+        );
+        let response = self
+            .client
+            .post(format!("{}/fapi/v1/order?{query}", self.base_url))
+            .header("X-MBX-APIKEY", &self.credentials.api_key)
+            .send()
+            .await
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        let text = parse_response(response).await?;
+        serde_json::from_str(&text).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+}
+
+/// `query` with its HMAC-SHA256 signature appended, as Binance's signed endpoints
+/// require.
+fn sign(query: &str, secret_key: &str) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret_key.as_bytes()).unwrap();
+    mac.update(query.as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+    format!("{query}&signature={signature}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trader() -> BinanceTrader {
+        BinanceTrader::new(
+            " btcusdt ",
+            BinanceCredentials {
+                api_key: "key".to_string(),
+                secret_key: "secret".to_string(),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sign_test() {
+        // Example from the Binance API documentation
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(
+            sign(
+                query,
+                "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j"
+            ),
+            format!(
+                "{query}&signature=c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+            )
+        );
+    }
+
+    #[test]
+    fn trader_test() {
+        let trader = trader();
+        assert_eq!(trader.symbol(), "BTCUSDT");
+        assert!((trader.round_quantity(-0.00199) - 0.001).abs() < 1e-12);
+        assert!(!format!("{:?}", trader).contains("secret"));
+
+        let order: BinanceOrder = serde_json::from_str(
+            r#"{"orderId":7,"status":"FILLED","executedQty":"0.002","avgPrice":"100.5","symbol":"BTCUSDT"}"#,
+        )
+        .unwrap();
+        assert_eq!(order.filled(), (0.002, 100.5));
+    }
+
+    #[tokio::test]
+    async fn zero_quantity_test() {
+        assert!(trader().market_order(true, 0.0004).await.is_err());
+    }
+}
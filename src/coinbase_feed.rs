@@ -0,0 +1,234 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use url::Url;
+
+use crate::{
+    prelude::*, run_with_reconnect, Backoff, Error, FeedCancellation, FeedTask, ReconnectLimiter,
+    SharedReferencePrice, TradeVwap,
+};
+
+/// A message from the Coinbase Exchange `ticker` channel, sent on every trade.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CoinbaseTicker {
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub product_id: String,
+    pub price: String,
+    /// Size of the trade that moved the ticker.
+    #[serde(default)]
+    pub last_size: String,
+    #[serde(default)]
+    pub time: String,
+}
+
+/// Reference price feed from the Coinbase Exchange ticker of one product.
+#[derive(Debug, Clone)]
+pub struct CoinbaseFeed {
+    product_id: String,
+    url: Url,
+    vwap_window: Option<usize>,
+    backoff: Backoff,
+    reconnect_limiter: Option<Arc<Mutex<ReconnectLimiter>>>,
+}
+
+impl CoinbaseFeed {
+    /// Feed for a product such as `BTC-USD` or `eth-usd`.
+    #[allow(clippy::result_large_err)]
+    pub fn new(product_id: &str) -> Result<Self> {
+        let product_id = product_id.trim().to_uppercase();
+        if product_id.is_empty() {
+            return Err(Error::GenericParse("Coinbase product is empty".to_string()));
+        }
+        Ok(Self {
+            product_id,
+            url: feed_url(MAINNET_URL)?,
+            vwap_window: None,
+            backoff: Backoff::new(Duration::from_secs(1), Duration::from_secs(30)),
+            reconnect_limiter: None,
+        })
+    }
+
+    /// Connect to the Coinbase Exchange sandbox instead, to pair with the Hyperliquid
+    /// testnet.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the sandbox URL can't be built.
+    #[allow(clippy::result_large_err)]
+    pub fn with_sandbox(mut self) -> Result<Self> {
+        self.url = feed_url(SANDBOX_URL)?;
+        Ok(self)
+    }
+
+    /// Publish the VWAP of the last `window` trades rather than the last print.
+    pub fn with_vwap_window(mut self, window: usize) -> Self {
+        self.vwap_window = Some(window);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Check every reconnect against a limiter shared with other feeds.
+    pub fn with_reconnect_limiter(mut self, limiter: Arc<Mutex<ReconnectLimiter>>) -> Self {
+        self.reconnect_limiter = Some(limiter);
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn product_id(&self) -> &str {
+        &self.product_id
+    }
+
+    /// Keep `out` updated in the background, reconnecting when the stream drops,
+    /// until the returned task is stopped.
+    pub fn spawn(self, out: SharedReferencePrice) -> FeedTask {
+        FeedTask::spawn(|cancellation| async move {
+            run_with_reconnect(
+                "Coinbase",
+                cancellation,
+                self.backoff.clone(),
+                self.reconnect_limiter.clone(),
+                |cancellation| self.run(out.clone(), cancellation),
+            )
+            .await;
+        })
+    }
+
+    /// Run one connection until it drops or is cancelled, closing the socket cleanly
+    /// on cancellation.
+    async fn run(
+        &self,
+        out: SharedReferencePrice,
+        mut cancellation: FeedCancellation,
+    ) -> Result<()> {
+        let mut vwap = self.vwap_window.map(TradeVwap::new);
+        let (ws_stream, _) = connect_async(self.url.clone())
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?;
+        let (mut write, mut read) = ws_stream.split();
+        // Unlike Binance's per-symbol streams, the product is chosen by subscribing
+        write
+            .send(Message::Text(subscribe_message(&self.product_id)))
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?;
+
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => msg,
+                () = cancellation.cancelled() => {
+                    write
+                        .send(Message::Close(None))
+                        .await
+                        .map_err(|e| Error::Websocket(e.to_string()))?;
+                    write
+                        .close()
+                        .await
+                        .map_err(|e| Error::Websocket(e.to_string()))?;
+                    break;
+                }
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            if let Ok(Message::Text(text)) = msg {
+                if let Some(price) = parse_ticker_price(&text, vwap.as_mut()) {
+                    out.set(price);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const MAINNET_URL: &str = "wss://ws-feed.exchange.coinbase.com";
+const SANDBOX_URL: &str = "wss://ws-feed-public.sandbox.exchange.coinbase.com";
+
+#[allow(clippy::result_large_err)]
+fn feed_url(url: &str) -> Result<Url> {
+    Url::parse(url).map_err(|e| Error::GenericParse(e.to_string()))
+}
+
+fn subscribe_message(product_id: &str) -> String {
+    serde_json::json!({
+        "type": "subscribe",
+        "product_ids": [product_id],
+        "channels": ["ticker"],
+    })
+    .to_string()
+}
+
+/// Reference price after a ticker message, skipping subscription acknowledgements
+/// and malformed prints rather than feeding them to the signal.
+fn parse_ticker_price(text: &str, vwap: Option<&mut TradeVwap>) -> Option<f64> {
+    let ticker = serde_json::from_str::<CoinbaseTicker>(text)
+        .ok()
+        .filter(|ticker| ticker.message_type == "ticker")?;
+    let price = ticker
+        .price
+        .parse::<f64>()
+        .ok()
+        .filter(|price| price.is_finite() && *price > 0.0)?;
+    Some(match vwap {
+        Some(vwap) => vwap
+            .push(price, ticker.last_size.parse().unwrap_or(0.0))
+            .unwrap_or(price),
+        None => price,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticker(px: &str, size: &str) -> String {
+        format!(
+            r#"{{"type":"ticker","sequence":1,"product_id":"BTC-USD","price":"{px}","best_bid":"{px}","best_ask":"{px}","side":"buy","time":"2024-01-01T00:00:00.000000Z","trade_id":1,"last_size":"{size}"}}"#
+        )
+    }
+
+    #[test]
+    fn product_url_test() {
+        let feed = CoinbaseFeed::new(" eth-usd ").unwrap();
+        assert_eq!(feed.product_id(), "ETH-USD");
+        assert_eq!(feed.url().as_str(), "wss://ws-feed.exchange.coinbase.com/");
+        assert!(CoinbaseFeed::new("  ").is_err());
+        assert_eq!(
+            feed.with_sandbox().unwrap().url().as_str(),
+            "wss://ws-feed-public.sandbox.exchange.coinbase.com/"
+        );
+        let subscribe: serde_json::Value =
+            serde_json::from_str(&subscribe_message("ETH-USD")).unwrap();
+        assert_eq!(
+            subscribe,
+            serde_json::json!({"type": "subscribe", "product_ids": ["ETH-USD"], "channels": ["ticker"]})
+        );
+    }
+
+    #[test]
+    fn parse_ticker_price_test() {
+        assert_eq!(parse_ticker_price(&ticker("100.5", "1"), None), Some(100.5));
+        assert_eq!(parse_ticker_price(&ticker("NaN", "1"), None), None);
+        assert_eq!(
+            parse_ticker_price(
+                r#"{"type":"subscriptions","channels":[{"name":"ticker","product_ids":["BTC-USD"]}]}"#,
+                None
+            ),
+            None
+        );
+
+        let mut vwap = TradeVwap::new(2);
+        parse_ticker_price(&ticker("100", "1"), Some(&mut vwap));
+        let price = parse_ticker_price(&ticker("103", "2"), Some(&mut vwap)).unwrap();
+        assert!((price - 102.0).abs() < 1e-9);
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
     },
     helpers::{generate_random_key, next_nonce, uuid_to_hex_string},
     info::info_client::InfoClient,
-    meta::Meta,
+    meta::{Meta, SpotMeta},
     prelude::*,
     req::HttpClient,
     signature::sign_l1_action,
@@ -59,6 +59,7 @@ pub enum Actions {
 }
 
 impl Actions {
+    #[allow(clippy::result_large_err)]
     fn hash(&self, timestamp: u64, vault_address: Option<H160>) -> Result<H256> {
         let mut bytes =
             rmp_serde::to_vec_named(self).map_err(|e| Error::RmpParse(e.to_string()))?;
@@ -108,6 +109,15 @@ impl ExchangeClient {
         })
     }
 
+    /// Make the spot pairs of `spot_meta` orderable by their names, such as `@107`.
+    pub fn add_spot_meta(&mut self, spot_meta: &SpotMeta) {
+        for pair in &spot_meta.universe {
+            // Spot assets are numbered after the perps
+            self.coin_to_asset
+                .insert(pair.name.clone(), 10_000 + pair.index as u32);
+        }
+    }
+
     async fn post(
         &self,
         action: serde_json::Value,
@@ -360,6 +370,7 @@ impl ExchangeClient {
 }
 
 #[cfg(test)]
+#[allow(clippy::result_large_err)]
 mod tests {
     use std::str::FromStr;
 
@@ -71,6 +71,7 @@ pub struct ClientOrderRequest {
 }
 
 impl ClientOrderRequest {
+    #[allow(clippy::result_large_err)]
     pub(crate) fn convert(self, coin_to_asset: &HashMap<String, u32>) -> Result<OrderRequest> {
         let order_type = match self.order_type {
             ClientOrder::Limit(limit) => Order::Limit(Limit { tif: limit.tif }),
@@ -3,6 +3,7 @@ use chrono::prelude::Utc;
 use lazy_static::lazy_static;
 use log::info;
 use rand::{thread_rng, Rng};
+use serde::Deserialize;
 use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
 
@@ -51,6 +52,7 @@ pub(crate) fn uuid_to_hex_string(uuid: Uuid) -> String {
     format!("0x{}", hex_string)
 }
 
+#[allow(clippy::result_large_err)]
 pub(crate) fn generate_random_key() -> Result<[u8; 32]> {
     let mut arr = [0u8; 32];
     thread_rng()
@@ -76,7 +78,8 @@ pub fn bps_diff(x: f64, y: f64) -> u16 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BaseUrl {
     Localhost,
     Testnet,
@@ -1,9 +1,11 @@
 use crate::{
+    helpers::uuid_to_hex_string,
     info::{
         CandlesSnapshotResponse, FundingHistoryResponse, L2SnapshotResponse, OpenOrdersResponse,
-        RecentTradesResponse, UserFillsResponse, UserStateResponse,
+        OrderStatusResponse, RecentTradesResponse, UserFillsResponse, UserStateResponse,
+        UserTokenBalanceResponse,
     },
-    meta::Meta,
+    meta::{Meta, SpotMeta},
     prelude::*,
     req::HttpClient,
     ws::{Subscription, WsManager},
@@ -15,6 +17,7 @@ use gxhash::HashMap;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -40,7 +43,12 @@ pub enum InfoRequest {
     OpenOrders {
         user: H160,
     },
+    #[serde(rename = "spotClearinghouseState")]
+    UserTokenBalances {
+        user: H160,
+    },
     Meta,
+    SpotMeta,
     AllMids,
     UserFills {
         user: H160,
@@ -61,6 +69,18 @@ pub enum InfoRequest {
     CandleSnapshot {
         req: CandleSnapshotRequest,
     },
+    OrderStatus {
+        user: H160,
+        oid: OrderId,
+    },
+}
+
+/// An order, by the id the exchange gave it or the client order id it was sent with.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OrderId {
+    Oid(u64),
+    Cloid(String),
 }
 
 pub struct InfoClient {
@@ -146,6 +166,22 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    pub async fn spot_meta(&self) -> Result<SpotMeta> {
+        let input = InfoRequest::SpotMeta;
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    pub async fn user_token_balances(&self, address: H160) -> Result<UserTokenBalanceResponse> {
+        let input = InfoRequest::UserTokenBalances { user: address };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
     pub async fn all_mids(&self) -> Result<HashMap<String, String>> {
         let input = InfoRequest::AllMids;
         let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
@@ -195,6 +231,27 @@ impl InfoClient {
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
+    pub async fn query_order_by_oid(&self, address: H160, oid: u64) -> Result<OrderStatusResponse> {
+        self.order_status(address, OrderId::Oid(oid)).await
+    }
+
+    pub async fn query_order_by_cloid(
+        &self,
+        address: H160,
+        cloid: Uuid,
+    ) -> Result<OrderStatusResponse> {
+        self.order_status(address, OrderId::Cloid(uuid_to_hex_string(cloid)))
+            .await
+    }
+
+    async fn order_status(&self, address: H160, oid: OrderId) -> Result<OrderStatusResponse> {
+        let input = InfoRequest::OrderStatus { user: address, oid };
+        let data = serde_json::to_string(&input).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let return_data = self.http_client.post("/info", data).await?;
+        serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
     pub async fn candles_snapshot(
         &self,
         coin: String,
@@ -1,4 +1,4 @@
-use crate::info::{AssetPosition, Level, MarginSummary};
+use crate::info::{AssetPosition, Level, MarginSummary, OrderInfo, UserTokenBalance};
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -88,3 +88,16 @@ pub struct CandlesSnapshotResponse {
     #[serde(rename = "n")]
     pub num_trades: u64,
 }
+
+/// Status of one order. `status` is `order` when the exchange knows the order and
+/// `unknownOid` when it doesn't.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct OrderStatusResponse {
+    pub status: String,
+    pub order: Option<OrderInfo>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct UserTokenBalanceResponse {
+    pub balances: Vec<UserTokenBalance>,
+}
@@ -46,3 +46,33 @@ pub struct Level {
     pub px: String,
     pub sz: String,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BasicOrderInfo {
+    pub coin: String,
+    pub side: String,
+    pub limit_px: String,
+    pub sz: String,
+    pub oid: u64,
+    pub timestamp: u64,
+    pub orig_sz: String,
+    pub cloid: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderInfo {
+    pub order: BasicOrderInfo,
+    pub status: String,
+    pub status_timestamp: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTokenBalance {
+    pub coin: String,
+    pub hold: String,
+    pub total: String,
+    pub entry_ntl: String,
+}
@@ -1,4 +1,7 @@
 #![deny(unreachable_pub)]
+mod binance_feed;
+mod binance_trader;
+mod coinbase_feed;
 mod consts;
 mod errors;
 mod exchange;
@@ -11,11 +14,27 @@ mod proxy_digest;
 mod req;
 mod signature;
 mod ws;
+pub use binance_feed::{BinanceDepth, BinanceFeed, BinanceTrade};
+pub use binance_trader::{BinanceCredentials, BinanceOrder, BinanceTrader};
+pub use coinbase_feed::{CoinbaseFeed, CoinbaseTicker};
 pub use consts::{EPSILON, LOCAL_API_URL, MAINNET_API_URL, TESTNET_API_URL};
 pub use errors::Error;
 pub use exchange::*;
 pub use helpers::{bps_diff, truncate_float, BaseUrl};
 pub use info::{info_client::*, *};
-pub use market_maker::{Input, MarketMaker};
-pub use meta::{AssetMeta, Meta};
+pub use market_maker::{
+    load_wallet, load_wallet_file, parse_private_key, run_with_reconnect, AssetConfig, Backoff,
+    Backtest, BacktestReport, BacktestRow, BacktestTrade, BinancePriceSource, BlendedFairValue,
+    CircuitBreaker, Config, ControlCommand, ControlHandle, Decision, DecisionOutcome, EntrySignal,
+    ExecutionStyle, Exposure, ExposureTracker, FairValueSource, FeedCancellation, FeedTask,
+    FillModel, Input, InputBuilder, MarkSource, MarketKind, MarketMaker, MarketMakerError,
+    MarketMakerObserver, MarketMakerState, MedianReferencePrice, Metrics, MidSource,
+    MultiAssetMarketMaker, OrderOutcome, PositionHeadroom, ReconnectLimiter, ReferencePrice,
+    ReferenceVenue, RejectCategory, ReplayFeed, ReplayFrame, ReplaySource, SharedReferencePrice,
+    SizeMode, SpreadCapture, StaleOrderPolicy, StatMode, StrategyMode, StrategyStats,
+    Subscriptions, TimeInForce, TokenBucket, TradeIntent, TradeVwap, VolatilityScaling, WindowMode,
+    WindowPrior, ZThresholdWindow, FLATTEN_ATTEMPTS, PRIVATE_KEY_FILE_VAR, PRIVATE_KEY_VAR,
+    SUBSCRIBE_ATTEMPTS,
+};
+pub use meta::{AssetMeta, Meta, SpotAssetMeta, SpotMeta, TokenInfo};
 pub use ws::*;
@@ -0,0 +1,143 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{error, info, warn};
+
+use super::metrics::MetricValues;
+use super::{ControlCommand, ControlHandle, FeedTask, Metrics};
+
+/// Serve the admin endpoint on `addr` until the returned task is stopped: `GET
+/// /state` reads `metrics`, and `POST /pause`, `/resume`, `/flatten` and
+/// `/dump-decisions` are sent down `control`.
+pub(super) fn serve(addr: SocketAddr, metrics: Metrics, control: ControlHandle) -> FeedTask {
+    FeedTask::spawn(move |mut cancellation| async move {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Error binding the admin server to {addr}: {e}");
+                return;
+            }
+        };
+        info!("Serving the admin endpoint on {addr}");
+
+        loop {
+            let (mut stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Error accepting an admin connection: {e}");
+                        continue;
+                    }
+                },
+                () = cancellation.cancelled() => break,
+            };
+            let mut request = [0; 1024];
+            let read = tokio::time::timeout(Duration::from_secs(1), stream.read(&mut request));
+            let Ok(Ok(len)) = read.await else {
+                continue;
+            };
+            let response = respond(&request[..len], &metrics, &control, peer);
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                warn!("Error writing admin response: {e}");
+            }
+        }
+    })
+}
+
+fn respond(request: &[u8], metrics: &Metrics, control: &ControlHandle, peer: SocketAddr) -> String {
+    let request = String::from_utf8_lossy(request);
+    let mut request_line = request.split_whitespace();
+    let command = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/state")) => {
+            return response("200 OK", &state(&metrics.values(), Instant::now()));
+        }
+        (Some("POST"), Some("/pause")) => ControlCommand::Pause,
+        (Some("POST"), Some("/resume")) => ControlCommand::Resume,
+        (Some("POST"), Some("/flatten")) => ControlCommand::Flatten,
+        (Some("POST"), Some("/dump-decisions")) => ControlCommand::DumpDecisions,
+        _ => return response("404 Not Found", ""),
+    };
+    info!("Admin {command:?} from {peer}");
+    let accepted = control.send(command);
+    let status = if accepted {
+        "202 Accepted"
+    } else {
+        "503 Service Unavailable"
+    };
+    response(
+        status,
+        &serde_json::json!({ "accepted": accepted }).to_string(),
+    )
+}
+
+fn response(status: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Live state as JSON, with feed ages in seconds and `null` for a feed not yet heard
+/// from.
+fn state(values: &MetricValues, now: Instant) -> String {
+    serde_json::json!({
+        "position": values.position,
+        "realized_pnl": values.realized_pnl,
+        "unrealized_pnl": values.unrealized_pnl,
+        "z_score": values.z_score,
+        "reference_price_age_seconds": values.reference_age.map(|age| age.as_secs_f64()),
+        "paused": values.paused,
+        "halted": values.halted_until.is_some_and(|until| now < until),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[test]
+    fn respond_test() {
+        let metrics = Metrics::new("BTC");
+        let now = Instant::now();
+        metrics.record(|values| {
+            values.position = 0.002;
+            values.paused = true;
+            values.halted_until = Some(now + Duration::from_secs(30));
+        });
+        let (sender, mut receiver) = unbounded_channel();
+        let control = ControlHandle(sender);
+        let peer = SocketAddr::from(([127, 0, 0, 1], 40_000));
+
+        let response = respond(b"GET /state HTTP/1.1\r\n\r\n", &metrics, &control, peer);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let body: serde_json::Value =
+            serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["position"], 0.002);
+        assert_eq!(body["paused"], true);
+        assert_eq!(body["halted"], true);
+        assert!(body["reference_price_age_seconds"].is_null());
+
+        let response = respond(b"POST /flatten HTTP/1.1\r\n\r\n", &metrics, &control, peer);
+        assert!(response.starts_with("HTTP/1.1 202 Accepted\r\n"));
+        assert_eq!(receiver.try_recv(), Ok(ControlCommand::Flatten));
+        let response = respond(
+            b"POST /dump-decisions HTTP/1.1\r\n\r\n",
+            &metrics,
+            &control,
+            peer,
+        );
+        assert!(response.starts_with("HTTP/1.1 202 Accepted\r\n"));
+        assert_eq!(receiver.try_recv(), Ok(ControlCommand::DumpDecisions));
+        // Commands are POST only, so a crawler or prefetch can't pause the bot
+        let response = respond(b"GET /pause HTTP/1.1\r\n\r\n", &metrics, &control, peer);
+        assert!(response.starts_with("HTTP/1.1 404"));
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        let response = respond(b"POST /resume HTTP/1.1\r\n\r\n", &metrics, &control, peer);
+        assert!(response.starts_with("HTTP/1.1 503"));
+    }
+}
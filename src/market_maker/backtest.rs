@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use crate::Error;
+
+/// Hyperliquid mid and Binance price observed together, with a timestamp in
+/// milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacktestRow {
+    pub time: u64,
+    pub hl_mid: f64,
+    pub binance_price: f64,
+}
+
+/// Historical prices to run the strategy over offline, in timestamp order.
+#[derive(Debug, Clone)]
+pub struct Backtest {
+    pub(super) rows: Vec<BacktestRow>,
+}
+
+impl Backtest {
+    #[must_use]
+    pub fn new(mut rows: Vec<BacktestRow>) -> Self {
+        rows.sort_by_key(|row| row.time);
+        Self { rows }
+    }
+
+    /// Parse CSV rows of `time,hyperliquid_mid,binance_price`. A header line is
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::GenericParse`] naming the first malformed line.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(csv: &str) -> Result<Self, Error> {
+        let mut rows = Vec::new();
+        for (i, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || (i == 0 && line.starts_with(|c: char| c.is_alphabetic())) {
+                continue;
+            }
+            let malformed =
+                || Error::GenericParse(format!("Malformed backtest line {}: {line}", i + 1));
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [time, hl_mid, binance_price] = fields[..] else {
+                return Err(malformed());
+            };
+            rows.push(BacktestRow {
+                time: time.parse().map_err(|_| malformed())?,
+                hl_mid: hl_mid.parse().map_err(|_| malformed())?,
+                binance_price: binance_price.parse().map_err(|_| malformed())?,
+            });
+        }
+        Ok(Self::new(rows))
+    }
+
+    /// Read and parse a CSV file, see [`Backtest::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::GenericReader`] if the file can't be read, or the parse error.
+    #[allow(clippy::result_large_err)]
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let csv = std::fs::read_to_string(path)
+            .map_err(|e| Error::GenericReader(format!("{}: {e}", path.display())))?;
+        Self::parse(&csv)
+    }
+}
+
+/// A simulated trade made during a backtest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacktestTrade {
+    pub time: u64,
+    pub is_buy: bool,
+    pub size: f64,
+    pub price: f64,
+}
+
+/// Trades a backtest made and the profit after every row.
+#[derive(Debug, Clone, Default)]
+pub struct BacktestReport {
+    pub trades: Vec<BacktestTrade>,
+    /// Realized plus mark-to-market profit after each row, by row time.
+    pub pnl_curve: Vec<(u64, f64)>,
+}
+
+impl BacktestReport {
+    /// Profit at the end of the run.
+    #[must_use]
+    pub fn final_pnl(&self) -> f64 {
+        self.pnl_curve.last().map_or(0.0, |(_, pnl)| *pnl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let backtest =
+            Backtest::parse("time,hl_mid,binance_price\n2,100.5,100\n\n1, 99, 100.0\n").unwrap();
+        assert_eq!(
+            backtest.rows,
+            [
+                BacktestRow {
+                    time: 1,
+                    hl_mid: 99.0,
+                    binance_price: 100.0
+                },
+                BacktestRow {
+                    time: 2,
+                    hl_mid: 100.5,
+                    binance_price: 100.0
+                },
+            ]
+        );
+        assert!(Backtest::parse("1,100").is_err());
+        assert!(Backtest::parse("1,100,abc").is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::BookLevel;
+
+/// Where the strategy takes the Hyperliquid mid from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MidSource {
+    /// The `AllMids` feed.
+    #[default]
+    AllMids,
+    /// Midpoint of the best bid and ask of the `L2Book` feed.
+    BookMid,
+    /// Best bid and ask weighted by the size resting on the opposite side, which
+    /// leans toward the side the book is likely to trade through.
+    Microprice,
+}
+
+impl MidSource {
+    /// Price from a book snapshot, or `None` for `AllMids` or a book missing a side.
+    pub(super) fn price(self, levels: &[Vec<BookLevel>]) -> Option<f64> {
+        let best = |side: usize| best_level(levels, side);
+        match self {
+            Self::AllMids => None,
+            Self::BookMid => {
+                let ((bid_px, _), (ask_px, _)) = (best(0)?, best(1)?);
+                Some(f64::midpoint(bid_px, ask_px))
+            }
+            Self::Microprice => {
+                let ((bid_px, bid_sz), (ask_px, ask_sz)) = (best(0)?, best(1)?);
+                Some(bid_px.mul_add(ask_sz, ask_px * bid_sz) / (bid_sz + ask_sz))
+            }
+        }
+    }
+}
+
+/// Hyperliquid feeds the bot subscribes to. `L2Book` is added whenever the mid
+/// source or a book check needs it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subscriptions {
+    /// Fills, which move the position and profit. Without them the bot only watches
+    /// the signal and never sends an order, unless it simulates them.
+    pub user_events: bool,
+    /// Mids of every asset, not needed when the mid comes from the book.
+    pub all_mids: bool,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self {
+            user_events: true,
+            all_mids: true,
+        }
+    }
+}
+
+impl Subscriptions {
+    /// Watch the signal and log what would be traded without placing an order, e.g.
+    /// to validate a new asset before going live.
+    #[must_use]
+    pub const fn signal_only() -> Self {
+        Self {
+            user_events: false,
+            all_mids: true,
+        }
+    }
+}
+
+/// Price and size of the best level of `side`, 0 for bids and 1 for asks, if it is
+/// well-formed.
+fn best_level(levels: &[Vec<BookLevel>], side: usize) -> Option<(f64, f64)> {
+    let level = levels.get(side)?.first()?;
+    let px = level.px.parse::<f64>().ok()?;
+    let sz = level.sz.parse::<f64>().ok()?;
+    (px.is_finite() && px > 0.0 && sz.is_finite() && sz > 0.0).then_some((px, sz))
+}
+
+/// Best price an order on `is_buy` side would trade against: the best ask for buys
+/// and the best bid for sells.
+pub(super) fn touch_price(levels: &[Vec<BookLevel>], is_buy: bool) -> Option<f64> {
+    best_level(levels, usize::from(is_buy)).map(|(px, _)| px)
+}
+
+/// Returns true if the book has no resting size within `band_bps` of `mid` on
+/// at least one side. `levels` follows the Hyperliquid layout: bids first, then asks.
+pub(super) fn is_one_sided(levels: &[Vec<BookLevel>], mid: f64, band_bps: f64) -> bool {
+    let band = super::bps_to_price(mid, band_bps);
+    let has_liquidity = |side: Option<&Vec<BookLevel>>| {
+        side.is_some_and(|side| {
+            side.iter().any(|level| {
+                let px = level.px.parse::<f64>().unwrap_or(0.0);
+                let sz = level.sz.parse::<f64>().unwrap_or(0.0);
+                sz > 0.0 && (px - mid).abs() <= band
+            })
+        })
+    };
+
+    !has_liquidity(levels.first()) || !has_liquidity(levels.get(1))
+}
+
+/// Resting size imbalance of the top `depth` levels, from -1 when only asks rest to
+/// 1 when only bids do. `None` if the book is empty.
+pub(super) fn imbalance(levels: &[Vec<BookLevel>], depth: usize) -> Option<f64> {
+    let size = |side: usize| -> f64 {
+        levels.get(side).map_or(0.0, |side| {
+            side.iter()
+                .take(depth)
+                .filter_map(|level| level.sz.parse::<f64>().ok())
+                .filter(|sz| sz.is_finite() && *sz > 0.0)
+                .sum()
+        })
+    };
+    let (bids, asks) = (size(0), size(1));
+    (bids + asks > 0.0).then(|| (bids - asks) / (bids + asks))
+}
+
+#[cfg(test)]
+pub(super) fn level(px: f64, sz: f64) -> BookLevel {
+    BookLevel {
+        px: px.to_string(),
+        sz: sz.to_string(),
+        n: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_sided_book_test() {
+        let two_sided = vec![vec![level(99.9, 1.0)], vec![level(100.1, 1.0)]];
+        assert!(!is_one_sided(&two_sided, 100.0, 50.0));
+
+        let no_asks = vec![vec![level(99.9, 1.0)], vec![]];
+        assert!(is_one_sided(&no_asks, 100.0, 50.0));
+
+        let no_bids = vec![vec![], vec![level(100.1, 1.0)]];
+        assert!(is_one_sided(&no_bids, 100.0, 50.0));
+
+        // Asks exist but only far outside the band.
+        let far_asks = vec![vec![level(99.9, 1.0)], vec![level(110.0, 1.0)]];
+        assert!(is_one_sided(&far_asks, 100.0, 50.0));
+
+        assert!(is_one_sided(&[], 100.0, 50.0));
+    }
+
+    #[test]
+    fn imbalance_test() {
+        let book = vec![
+            vec![level(99.9, 3.0), level(99.8, 2.0)],
+            vec![level(100.1, 1.0), level(100.2, 4.0)],
+        ];
+        assert_eq!(imbalance(&book, 1), Some(0.5));
+        assert_eq!(imbalance(&book, 2), Some(0.0));
+        assert_eq!(imbalance(&[vec![], vec![level(100.1, 1.0)]], 5), Some(-1.0));
+        assert_eq!(imbalance(&[], 5), None);
+    }
+
+    #[test]
+    fn mid_source_test() {
+        let book = vec![vec![level(99.0, 3.0)], vec![level(101.0, 1.0)]];
+        assert_eq!(MidSource::AllMids.price(&book), None);
+        assert_eq!(MidSource::BookMid.price(&book), Some(100.0));
+        // Bids outweigh asks, so the microprice sits closer to the ask
+        assert_eq!(MidSource::Microprice.price(&book), Some(100.5));
+
+        let no_asks = vec![vec![level(99.0, 3.0)], vec![]];
+        assert_eq!(MidSource::BookMid.price(&no_asks), None);
+        assert_eq!(MidSource::Microprice.price(&no_asks), None);
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+/// Stops sending orders after `max_failures` consecutive order errors, for
+/// `cooloff`, so a structural fault such as revoked auth or a halted market doesn't
+/// turn into a tight error loop against the API.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    max_failures: u32,
+    cooloff: Duration,
+    consecutive_failures: u32,
+    halted_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    #[must_use]
+    pub const fn new(max_failures: u32, cooloff: Duration) -> Self {
+        Self {
+            max_failures,
+            cooloff,
+            consecutive_failures: 0,
+            halted_until: None,
+        }
+    }
+
+    /// Returns true while halted at `now`, resetting once the cool-off has passed.
+    pub fn is_halted(&mut self, now: Instant) -> bool {
+        match self.halted_until {
+            Some(until) if now < until => true,
+            Some(_) => {
+                info!("Order circuit breaker reset after {:?}", self.cooloff);
+                self.halted_until = None;
+                self.consecutive_failures = 0;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// End of the current cool-off, `None` when not halted.
+    #[must_use]
+    pub const fn halted_until(&self) -> Option<Instant> {
+        self.halted_until
+    }
+
+    pub const fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    /// Counts a failed order at `now`. Returns true if this failure tripped the
+    /// breaker.
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.halted_until.is_some() || self.consecutive_failures < self.max_failures {
+            return false;
+        }
+        error!(
+            "{} consecutive order errors, halting orders for {:?}",
+            self.consecutive_failures, self.cooloff
+        );
+        self.halted_until = Some(now + self.cooloff);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_breaker_test() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        assert!(!breaker.record_failure(start));
+        breaker.record_success();
+        assert!(!breaker.record_failure(start));
+        assert!(!breaker.record_failure(start));
+        assert!(!breaker.is_halted(start));
+        assert!(breaker.record_failure(start));
+        assert!(breaker.is_halted(start + Duration::from_secs(29)));
+
+        assert!(!breaker.is_halted(start + Duration::from_secs(30)));
+        // Counting starts over after the reset
+        assert!(!breaker.record_failure(start + Duration::from_secs(31)));
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDate;
+use tracing::{info, warn};
+
+/// Caps the notional opened per UTC day, however many signals fire, so a runaway
+/// loop can't trade the whole day. Closing fills don't count against it.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct DailyNotionalBudget {
+    max: f64,
+    day: Option<NaiveDate>,
+    traded: f64,
+    exhausted: bool,
+}
+
+impl DailyNotionalBudget {
+    pub(super) const fn new(max: f64) -> Self {
+        Self {
+            max,
+            day: None,
+            traded: 0.0,
+            exhausted: false,
+        }
+    }
+
+    /// Whether an order of `notional` fits in what is left of `today`'s budget. Logs
+    /// once a day when it doesn't.
+    pub(super) fn allows(&mut self, notional: f64, today: NaiveDate) -> bool {
+        self.roll(today);
+        if self.traded + notional <= self.max {
+            return true;
+        }
+        if !self.exhausted {
+            warn!(
+                "Daily notional budget of {:.2} exhausted with {:.2} traded, no new orders until UTC midnight",
+                self.max, self.traded
+            );
+            self.exhausted = true;
+        }
+        false
+    }
+
+    /// Counts a fill of `notional` that opened or added to the position.
+    pub(super) fn record(&mut self, notional: f64, today: NaiveDate) {
+        self.roll(today);
+        self.traded += notional;
+    }
+
+    /// Notional counted against `today`'s budget so far.
+    #[cfg(test)]
+    pub(super) fn traded(&mut self, today: NaiveDate) -> f64 {
+        self.roll(today);
+        self.traded
+    }
+
+    fn roll(&mut self, today: NaiveDate) {
+        match self.day {
+            Some(day) if day == today => {}
+            Some(day) => {
+                info!(
+                    "Daily notional budget reset for {today}, {:.2} of {:.2} traded on {day}",
+                    self.traded, self.max
+                );
+                *self = Self {
+                    day: Some(today),
+                    ..Self::new(self.max)
+                };
+            }
+            None => self.day = Some(today),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_budget_test() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut budget = DailyNotionalBudget::new(1000.0);
+        assert!(budget.allows(1000.0, day));
+        budget.record(600.0, day);
+        assert!(budget.allows(400.0, day));
+        assert!(!budget.allows(400.01, day));
+        assert!(budget.exhausted);
+
+        // A new UTC day starts from zero
+        let next = day.succ_opt().unwrap();
+        assert!(budget.allows(1000.0, next));
+        assert!(!budget.exhausted);
+        assert!(budget.traded(next).abs() < 1e-9);
+    }
+}
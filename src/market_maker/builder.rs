@@ -0,0 +1,325 @@
+use chrono::NaiveTime;
+use ethers::signers::LocalWallet;
+use std::time::Duration;
+
+use crate::BaseUrl;
+
+use super::{
+    BinancePriceSource, ExecutionStyle, FillModel, Input, MarkSource, MarketKind, MarketMakerError,
+    MidSource, ReferenceVenue, SizeMode, StatMode, StrategyMode, Subscriptions, TimeInForce,
+    WindowMode,
+};
+
+/// Builds an [`Input`] from the parameters that matter for a first run, with every
+/// optional feature off.
+///
+/// ```ignore
+/// let input = InputBuilder::new("ETH", wallet)
+///     .trade_size(0.01)
+///     .decimals(1)
+///     .size_decimals(4)
+///     .build()?;
+/// ```
+pub struct InputBuilder {
+    input: Input,
+}
+
+impl InputBuilder {
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn new(asset: impl Into<String>, wallet: LocalWallet) -> Self {
+        Self {
+            input: Input {
+                asset: asset.into(),
+                base_url: BaseUrl::Mainnet,
+                market_kind: MarketKind::Perp,
+                window_size: 100,
+                min_correlation: None,
+                window_mode: WindowMode::Count,
+                stat_mode: StatMode::Window,
+                z_threshold: 2.0,
+                size_mode: SizeMode::Fixed(0.001),
+                balance_refresh: Duration::from_mins(1),
+                target_liquidity: 0.0002,
+                half_spread: 5,
+                max_bps_diff: 20,
+                max_bps_diff_band: false,
+                max_absolute_position_size: 0.004,
+                decimals: 0,
+                size_decimals: 5,
+                wallet,
+                one_sided_book_band_bps: None,
+                cross_buffer_bps: None,
+                hedge_delta_threshold: None,
+                hedge_trader: None,
+                window_prior: None,
+                fill_dedup_window: 1000,
+                reference_confirm_bps: None,
+                shutdown_timeout: Duration::from_secs(10),
+                standby_wallet: None,
+                signal_skew_weight: 0.0,
+                protective_stop_bps: None,
+                protective_take_profit_bps: None,
+                protective_reprice_bps: 10.0,
+                stop_loss_bps: None,
+                take_profit_bps: None,
+                min_hold: Duration::ZERO,
+                max_hold_time: None,
+                post_fill_quiet: Duration::ZERO,
+                long_window_size: None,
+                long_z_threshold: 1.0,
+                returns_window: 100,
+                return_interval: None,
+                stats_interval: None,
+                exposure: None,
+                z_threshold_schedule: Vec::new(),
+                session_end: None,
+                session_start: None,
+                volatility_scaling: None,
+                loss_penalty: 1.0,
+                loss_penalty_half_life: Duration::from_mins(5),
+                decision_history: 100,
+                reconcile_min_interval: Duration::from_secs(30),
+                entry_band_bps: None,
+                min_stddev_bps: None,
+                min_stddev_mean_fraction: None,
+                reconnect_limiter: None,
+                confirm_ticks: 1,
+                outlier_k: None,
+                outlier_confirm_ticks: 3,
+                message_timeout: None,
+                heartbeat_interval: None,
+                log_sample_rate: 1,
+                stale_orders: None,
+                quote_depth_fraction: None,
+                log_position_headroom: true,
+                latency_warn_threshold: None,
+                fair_value: None,
+                dry_run: None,
+                execution_style: ExecutionStyle::Cross,
+                post_only: false,
+                maker_rebate_bps: 0.0,
+                max_price_age: Duration::from_secs(5),
+                max_size_multiplier: 1.0,
+                inventory_skew: 0.0,
+                paper_trading: false,
+                simulated_latency: Duration::ZERO,
+                simulated_slippage_bps: 0.0,
+                min_trade_interval: Duration::ZERO,
+                resting_order_timeout: None,
+                max_open_orders: None,
+                order_tif: TimeInForce::Ioc,
+                strategy: StrategyMode::Arbitrage,
+                warmup_ticks: 0,
+                max_order_notional: f64::INFINITY,
+                max_daily_notional: None,
+                taker_fee_bps: 0.0,
+                min_edge_bps: 0.0,
+                funding_refresh: None,
+                expected_holding_time: Duration::from_hours(1),
+                max_order_retries: 0,
+                order_retry_delay: Duration::ZERO,
+                order_rate_limit: None,
+                order_jitter: Duration::ZERO,
+                jitter_seed: None,
+                circuit_breaker: None,
+                mid_source: MidSource::AllMids,
+                subscriptions: Subscriptions::default(),
+                reference_venue: ReferenceVenue::Binance,
+                mark_source: MarkSource::HlMid,
+                binance_price_source: BinancePriceSource::Trade,
+                recheck_edge: false,
+                imbalance_threshold: None,
+                imbalance_levels: 5,
+                trade_log_path: None,
+                state_path: None,
+                state_max_age: Duration::from_mins(10),
+                observer: None,
+                #[cfg(feature = "metrics")]
+                metrics_port: None,
+                #[cfg(feature = "admin")]
+                admin_addr: None,
+            },
+        }
+    }
+
+    /// Hyperliquid API to trade on, mainnet by default.
+    #[must_use]
+    pub const fn base_url(mut self, base_url: BaseUrl) -> Self {
+        self.input.base_url = base_url;
+        self
+    }
+
+    /// Price to mark the position at, the Hyperliquid mid by default.
+    #[must_use]
+    pub const fn mark_source(mut self, mark_source: MarkSource) -> Self {
+        self.input.mark_source = mark_source;
+        self
+    }
+
+    /// Trade a spot pair rather than a perp, see [`Input::market_kind`].
+    #[must_use]
+    pub const fn market_kind(mut self, market_kind: MarketKind) -> Self {
+        self.input.market_kind = market_kind;
+        self
+    }
+
+    #[must_use]
+    pub const fn window_size(mut self, window_size: usize) -> Self {
+        self.input.window_size = window_size;
+        self
+    }
+
+    #[must_use]
+    pub const fn z_threshold(mut self, z_threshold: f64) -> Self {
+        self.input.z_threshold = z_threshold;
+        self
+    }
+
+    /// Stop trading while the price returns decouple, see [`Input::min_correlation`].
+    #[must_use]
+    pub const fn min_correlation(mut self, min_correlation: f64) -> Self {
+        self.input.min_correlation = Some(min_correlation);
+        self
+    }
+
+    #[must_use]
+    pub const fn trade_size(mut self, trade_size: f64) -> Self {
+        self.input.size_mode = SizeMode::Fixed(trade_size);
+        self
+    }
+
+    #[must_use]
+    pub const fn target_liquidity(mut self, target_liquidity: f64) -> Self {
+        self.input.target_liquidity = target_liquidity;
+        self
+    }
+
+    #[must_use]
+    pub const fn half_spread(mut self, half_spread: u16) -> Self {
+        self.input.half_spread = half_spread;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_bps_diff(mut self, max_bps_diff: u16) -> Self {
+        self.input.max_bps_diff = max_bps_diff;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_absolute_position_size(mut self, max_absolute_position_size: f64) -> Self {
+        self.input.max_absolute_position_size = max_absolute_position_size;
+        self
+    }
+
+    /// Decimals of the asset's prices.
+    #[must_use]
+    pub const fn decimals(mut self, decimals: u32) -> Self {
+        self.input.decimals = decimals;
+        self
+    }
+
+    /// Decimals of the asset's order sizes.
+    #[must_use]
+    pub const fn size_decimals(mut self, size_decimals: u32) -> Self {
+        self.input.size_decimals = size_decimals;
+        self
+    }
+
+    /// Simulate every order and fill instead of trading.
+    #[must_use]
+    pub const fn paper_trading(mut self, paper_trading: bool) -> Self {
+        self.input.paper_trading = paper_trading;
+        self
+    }
+
+    /// Hyperliquid feeds to open, all but the book by default.
+    #[must_use]
+    pub const fn subscriptions(mut self, subscriptions: Subscriptions) -> Self {
+        self.input.subscriptions = subscriptions;
+        self
+    }
+
+    /// Delay and slippage of paper fills, none by default.
+    #[must_use]
+    pub const fn simulated_friction(mut self, latency: Duration, slippage_bps: f64) -> Self {
+        self.input.simulated_latency = latency;
+        self.input.simulated_slippage_bps = slippage_bps;
+        self
+    }
+
+    /// Enter only with post-only orders at the touch, cancelling them unfilled after
+    /// `wait`, and credit `maker_rebate_bps` on maker fills.
+    #[must_use]
+    pub const fn post_only(mut self, wait: Duration, maker_rebate_bps: f64) -> Self {
+        self.input.execution_style = ExecutionStyle::PostThenCross { wait };
+        self.input.post_only = true;
+        self.input.maker_rebate_bps = maker_rebate_bps;
+        self
+    }
+
+    /// Flatten and stop opening positions at `end` UTC each day, resuming at `start`
+    /// if set.
+    #[must_use]
+    pub const fn session(mut self, start: Option<NaiveTime>, end: NaiveTime) -> Self {
+        self.input.session_start = start;
+        self.input.session_end = Some(end);
+        self
+    }
+
+    #[must_use]
+    pub fn dry_run(mut self, fill_model: FillModel) -> Self {
+        self.input.dry_run = Some(fill_model);
+        self
+    }
+
+    /// Set any of the remaining options, which start out off.
+    #[must_use]
+    pub fn configure(mut self, configure: impl FnOnce(&mut Input)) -> Self {
+        configure(&mut self.input);
+        self
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`MarketMakerError::InvalidInput`] if the parameters are inconsistent,
+    /// e.g. an empty asset, a non-positive trade size or a half spread wider than
+    /// `max_bps_diff`.
+    #[allow(clippy::result_large_err)]
+    pub fn build(self) -> Result<Input, MarketMakerError> {
+        self.input.validate()?;
+        Ok(self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> InputBuilder {
+        InputBuilder::new("ETH", LocalWallet::new(&mut rand::thread_rng()))
+    }
+
+    #[test]
+    fn build_test() {
+        let input = builder()
+            .trade_size(0.01)
+            .decimals(1)
+            .configure(|input| input.warmup_ticks = 10)
+            .build()
+            .unwrap();
+        assert_eq!(input.asset, "ETH");
+        assert_eq!(input.size_mode, SizeMode::Fixed(0.01));
+        assert_eq!((input.decimals, input.warmup_ticks), (1, 10));
+
+        assert!(builder().trade_size(-1.0).build().is_err());
+        assert!(builder().window_size(0).build().is_err());
+        assert!(builder().half_spread(30).max_bps_diff(20).build().is_err());
+        assert!(
+            InputBuilder::new("", LocalWallet::new(&mut rand::thread_rng()))
+                .build()
+                .is_err()
+        );
+    }
+}
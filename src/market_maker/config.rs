@@ -0,0 +1,382 @@
+use ethers::signers::LocalWallet;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{BaseUrl, BinanceCredentials, BinanceTrader};
+
+use super::{
+    load_wallet, load_wallet_file, parse_private_key, BinancePriceSource, Input, MarkSource,
+    MarketKind, MarketMakerError, ReferenceVenue, SizeMode, StaleOrderPolicy,
+};
+
+/// Strategy parameters read from a TOML file, so one binary can run different
+/// strategies without keys in the source.
+///
+/// ```toml
+/// asset = "ETH"
+/// base_url = "testnet"
+/// window_size = 200
+/// z_threshold = 2.5
+/// trade_size = 0.01
+/// private_key_env = "HL_PRIVATE_KEY"
+/// ```
+///
+/// Options left out keep the value they already have in the [`Input`] the file is
+/// applied to. The wallet key is given inline, as a path to a file holding it, or
+/// as the name of an environment variable holding it, at most one of the three. With
+/// none it is loaded by [`load_wallet`](super::load_wallet).
+/// There is deliberately no `Debug`, which would print an inline key.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub asset: String,
+    #[serde(default = "default_base_url")]
+    pub base_url: BaseUrl,
+    /// Market `asset` names, `perp` or `spot`.
+    #[serde(default)]
+    pub market_kind: MarketKind,
+    /// Venue to take the reference price from, `binance` or `coinbase`.
+    #[serde(default)]
+    pub reference_venue: ReferenceVenue,
+    /// Price to mark the position at, `hl_mid`, `reference` or `mid_of_both`.
+    #[serde(default)]
+    pub mark_source: MarkSource,
+    /// Binance stream to take the price from, `trade` or `microprice`.
+    #[serde(default)]
+    pub binance_price_source: BinancePriceSource,
+    /// Binance futures symbol to reference, `<asset>USDT` if left out.
+    pub binance_symbol: Option<String>,
+    /// Coinbase product to reference, `<asset>-USD` if left out.
+    pub coinbase_product: Option<String>,
+    pub window_size: Option<usize>,
+    pub z_threshold: Option<f64>,
+    /// See [`Input::min_correlation`].
+    pub min_correlation: Option<f64>,
+    pub trade_size: Option<f64>,
+    pub target_liquidity: Option<f64>,
+    pub half_spread: Option<u16>,
+    pub max_bps_diff: Option<u16>,
+    /// Skip deviations wider than `max_bps_diff`, see [`Input::max_bps_diff_band`].
+    pub max_bps_diff_band: Option<bool>,
+    pub max_absolute_position_size: Option<f64>,
+    pub paper_trading: Option<bool>,
+    /// Delay before a paper fill is booked, see [`Input::simulated_latency`].
+    pub simulated_latency_ms: Option<u64>,
+    /// Slippage of paper fills, see [`Input::simulated_slippage_bps`].
+    pub simulated_slippage_bps: Option<f64>,
+    /// Most to wait before each order, see [`Input::order_jitter`].
+    pub order_jitter_ms: Option<u64>,
+    pub jitter_seed: Option<u64>,
+    /// Notional that may be opened per UTC day, see [`Input::max_daily_notional`].
+    pub max_daily_notional: Option<f64>,
+    pub stop_loss_bps: Option<f64>,
+    pub take_profit_bps: Option<f64>,
+    /// File the rolling window and position are saved to on shutdown.
+    pub state_path: Option<PathBuf>,
+    /// Save state on shutdown, to `state_path` or [`Config::default_state_path`] if
+    /// left out. `false` turns saving off, even with a `state_path` set.
+    pub save_state: Option<bool>,
+    /// Cancel every order of the asset left resting by a previous run on startup,
+    /// see [`StaleOrderPolicy::CancelAll`]. `false` only turns cancelling off, an
+    /// [`StaleOrderPolicy::Adopt`] set on the input is kept.
+    pub cancel_on_start: Option<bool>,
+    /// Log one in this many quiet ticks, see [`Input::log_sample_rate`].
+    pub log_sample_rate: Option<usize>,
+    /// Hedge fills on Binance with the key and secret in `BINANCE_API_KEY` and
+    /// `BINANCE_SECRET_KEY`.
+    #[serde(default)]
+    pub hedge: bool,
+    /// Watch the signal without subscribing to fills or placing an order, see
+    /// [`Subscriptions::signal_only`](super::Subscriptions::signal_only).
+    #[serde(default)]
+    pub signal_only: bool,
+    /// Address such as `127.0.0.1:9185` to serve the admin endpoint on, see
+    /// [`Input::admin_addr`]. Off if left out.
+    #[cfg(feature = "admin")]
+    pub admin_addr: Option<std::net::SocketAddr>,
+    pub private_key: Option<String>,
+    pub private_key_file: Option<PathBuf>,
+    pub private_key_env: Option<String>,
+}
+
+const fn default_base_url() -> BaseUrl {
+    BaseUrl::Mainnet
+}
+
+impl Config {
+    /// # Errors
+    ///
+    /// Returns [`MarketMakerError::Config`] if the TOML is malformed or doesn't match
+    /// the options.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(toml: &str) -> Result<Self, MarketMakerError> {
+        toml::from_str(toml).map_err(|e| MarketMakerError::Config(e.to_string()))
+    }
+
+    /// Read and parse a config file, see [`Config::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MarketMakerError::Config`] naming the file if it can't be read or
+    /// parsed.
+    #[allow(clippy::result_large_err)]
+    pub fn load(path: &Path) -> Result<Self, MarketMakerError> {
+        let toml = std::fs::read_to_string(path)
+            .map_err(|e| MarketMakerError::Config(format!("{}: {e}", path.display())))?;
+        toml::from_str(&toml)
+            .map_err(|e| MarketMakerError::Config(format!("{}: {e}", path.display())))
+    }
+
+    #[must_use]
+    pub fn binance_symbol(&self) -> String {
+        self.binance_symbol
+            .clone()
+            .unwrap_or_else(|| format!("{}USDT", self.asset))
+    }
+
+    /// `market_maker_<asset>.json`, with characters that can't go in a file name,
+    /// like the `/` of a spot pair, replaced by `_`.
+    #[must_use]
+    pub fn default_state_path(&self) -> PathBuf {
+        let asset: String = self
+            .asset
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        PathBuf::from(format!("market_maker_{asset}.json"))
+    }
+
+    #[must_use]
+    pub fn coinbase_product(&self) -> String {
+        self.coinbase_product
+            .clone()
+            .unwrap_or_else(|| format!("{}-USD", self.asset))
+    }
+
+    /// Wallet from whichever key source the file names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MarketMakerError::Config`] if more than one key source is given, or
+    /// [`MarketMakerError::Wallet`] if the key can't be read or is malformed.
+    #[allow(clippy::result_large_err)]
+    pub fn wallet(&self) -> Result<LocalWallet, MarketMakerError> {
+        let sources = (
+            &self.private_key,
+            &self.private_key_file,
+            &self.private_key_env,
+        );
+        match sources {
+            (None, None, None) => load_wallet(),
+            (Some(key), None, None) => parse_private_key(key),
+            (None, Some(path), None) => load_wallet_file(path),
+            (None, None, Some(var)) => {
+                let key = std::env::var(var)
+                    .map_err(|e| MarketMakerError::Wallet(format!("{var}: {e}")))?;
+                parse_private_key(&key)
+            }
+            _ => Err(MarketMakerError::Config(
+                "set at most one of private_key, private_key_file and private_key_env".to_string(),
+            )),
+        }
+    }
+
+    /// Binance trader for the hedge leg, if the file turns hedging on.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MarketMakerError::Config`] if hedging is on but the credentials
+    /// aren't set.
+    #[allow(clippy::result_large_err)]
+    pub fn hedge_trader(&self) -> Result<Option<BinanceTrader>, MarketMakerError> {
+        if !self.hedge {
+            return Ok(None);
+        }
+        let var = |name: &str| {
+            std::env::var(name)
+                .map_err(|e| MarketMakerError::Config(format!("hedging needs {name}: {e}")))
+        };
+        let credentials = BinanceCredentials {
+            api_key: var("BINANCE_API_KEY")?,
+            secret_key: var("BINANCE_SECRET_KEY")?,
+        };
+        let trader = BinanceTrader::new(&self.binance_symbol(), credentials)?;
+        Ok(Some(if matches!(self.base_url, BaseUrl::Testnet) {
+            trader.with_testnet()
+        } else {
+            trader
+        }))
+    }
+
+    /// Override the options of `input` the file sets, including the asset, base URL
+    /// and reference venue but not the wallet.
+    pub fn apply(&self, input: &mut Input) {
+        input.asset.clone_from(&self.asset);
+        input.base_url = self.base_url;
+        input.market_kind = self.market_kind;
+        input.reference_venue = self.reference_venue;
+        input.binance_price_source = self.binance_price_source;
+        input.mark_source = self.mark_source;
+        if let Some(window_size) = self.window_size {
+            input.window_size = window_size;
+        }
+        if let Some(z_threshold) = self.z_threshold {
+            input.z_threshold = z_threshold;
+        }
+        if self.min_correlation.is_some() {
+            input.min_correlation = self.min_correlation;
+        }
+        if let Some(trade_size) = self.trade_size {
+            input.size_mode = SizeMode::Fixed(trade_size);
+        }
+        if let Some(target_liquidity) = self.target_liquidity {
+            input.target_liquidity = target_liquidity;
+        }
+        if let Some(half_spread) = self.half_spread {
+            input.half_spread = half_spread;
+        }
+        if let Some(max_bps_diff) = self.max_bps_diff {
+            input.max_bps_diff = max_bps_diff;
+        }
+        if let Some(max_bps_diff_band) = self.max_bps_diff_band {
+            input.max_bps_diff_band = max_bps_diff_band;
+        }
+        if let Some(max_absolute_position_size) = self.max_absolute_position_size {
+            input.max_absolute_position_size = max_absolute_position_size;
+        }
+        if let Some(paper_trading) = self.paper_trading {
+            input.paper_trading = paper_trading;
+        }
+        if let Some(latency_ms) = self.simulated_latency_ms {
+            input.simulated_latency = Duration::from_millis(latency_ms);
+        }
+        if let Some(slippage_bps) = self.simulated_slippage_bps {
+            input.simulated_slippage_bps = slippage_bps;
+        }
+        if let Some(jitter_ms) = self.order_jitter_ms {
+            input.order_jitter = Duration::from_millis(jitter_ms);
+        }
+        if self.jitter_seed.is_some() {
+            input.jitter_seed = self.jitter_seed;
+        }
+        if self.max_daily_notional.is_some() {
+            input.max_daily_notional = self.max_daily_notional;
+        }
+        if self.stop_loss_bps.is_some() {
+            input.stop_loss_bps = self.stop_loss_bps;
+        }
+        if self.take_profit_bps.is_some() {
+            input.take_profit_bps = self.take_profit_bps;
+        }
+        if self.state_path.is_some() {
+            input.state_path.clone_from(&self.state_path);
+        }
+        match self.save_state {
+            Some(true) if input.state_path.is_none() => {
+                input.state_path = Some(self.default_state_path());
+            }
+            Some(false) => input.state_path = None,
+            _ => {}
+        }
+        match self.cancel_on_start {
+            Some(true) => input.stale_orders = Some(StaleOrderPolicy::CancelAll),
+            Some(false) if input.stale_orders == Some(StaleOrderPolicy::CancelAll) => {
+                input.stale_orders = None;
+            }
+            _ => {}
+        }
+        if let Some(log_sample_rate) = self.log_sample_rate {
+            input.log_sample_rate = log_sample_rate;
+        }
+        #[cfg(feature = "admin")]
+        if self.admin_addr.is_some() {
+            input.admin_addr = self.admin_addr;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InputBuilder;
+
+    const KEY: &str = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+
+    #[test]
+    fn parse_test() {
+        let config = Config::parse(&format!(
+            "asset = \"ETH\"\nbase_url = \"testnet\"\nwindow_size = 200\nz_threshold = 2.5\nprivate_key = \"{KEY}\"\n"
+        ))
+        .unwrap();
+        assert_eq!(config.binance_symbol(), "ETHUSDT");
+        assert_eq!(config.coinbase_product(), "ETH-USD");
+        assert_eq!(config.reference_venue, ReferenceVenue::Binance);
+        assert!(config.wallet().is_ok());
+
+        let mut input = InputBuilder::new("BTC", config.wallet().unwrap())
+            .trade_size(0.01)
+            .build()
+            .unwrap();
+        config.apply(&mut input);
+        assert_eq!((input.asset.as_str(), input.window_size), ("ETH", 200));
+        assert!(matches!(input.base_url, BaseUrl::Testnet));
+        assert!((input.z_threshold - 2.5).abs() < 1e-12);
+        // Options the file leaves out keep their value
+        assert_eq!(input.size_mode, SizeMode::Fixed(0.01));
+
+        let config = Config::parse(
+            "asset = \"ETH\"\ncancel_on_start = true\nreference_venue = \"coinbase\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.reference_venue, ReferenceVenue::Coinbase);
+        config.apply(&mut input);
+        assert_eq!(input.binance_price_source, BinancePriceSource::Trade);
+        Config::parse("asset = \"ETH\"\nbinance_price_source = \"microprice\"\n")
+            .unwrap()
+            .apply(&mut input);
+        assert_eq!(input.binance_price_source, BinancePriceSource::Microprice);
+        assert_eq!(input.market_kind, MarketKind::Perp);
+        Config::parse("asset = \"PURR/USDC\"\nmarket_kind = \"spot\"\n")
+            .unwrap()
+            .apply(&mut input);
+        assert_eq!(input.market_kind, MarketKind::Spot);
+        assert_eq!(input.stale_orders, Some(StaleOrderPolicy::CancelAll));
+        assert_eq!(input.state_path, None);
+        Config::parse("asset = \"PURR/USDC\"\nsave_state = true\n")
+            .unwrap()
+            .apply(&mut input);
+        assert_eq!(
+            input.state_path,
+            Some(PathBuf::from("market_maker_PURR_USDC.json"))
+        );
+        Config::parse("asset = \"ETH\"\nsave_state = false\n")
+            .unwrap()
+            .apply(&mut input);
+        assert_eq!(input.state_path, None);
+
+        // Leaving cancelling off doesn't stop orders from being adopted
+        let keep = Config::parse("asset = \"ETH\"\ncancel_on_start = false\n").unwrap();
+        keep.apply(&mut input);
+        assert_eq!(input.stale_orders, None);
+        input.stale_orders = Some(StaleOrderPolicy::Adopt);
+        keep.apply(&mut input);
+        assert_eq!(input.stale_orders, Some(StaleOrderPolicy::Adopt));
+    }
+
+    #[test]
+    fn config_error_test() {
+        assert!(Config::parse("asset = \"ETH\"\nwindow = 200\n").is_err());
+        assert!(Config::parse("window_size = 200\n").is_err());
+        assert!(Config::load(Path::new("/nonexistent/market_maker.toml")).is_err());
+
+        let two_keys = Config::parse(&format!(
+            "asset = \"ETH\"\nprivate_key = \"{KEY}\"\nprivate_key_env = \"HL_PRIVATE_KEY\"\n"
+        ))
+        .unwrap();
+        assert!(two_keys.wallet().is_err());
+        let bad_key = Config::parse("asset = \"ETH\"\nprivate_key = \"0x12\"\n").unwrap();
+        let error = bad_key.wallet().unwrap_err().to_string();
+        assert!(!error.contains("0x12"));
+    }
+}
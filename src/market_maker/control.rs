@@ -0,0 +1,51 @@
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Command for a running market maker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Stop sending orders. Prices are still ingested, so the rolling statistics and
+    /// warmup stay current.
+    Pause,
+    /// Trade again after a pause.
+    Resume,
+    /// Close the position with marketable orders, see
+    /// [`MarketMaker::flatten`](super::MarketMaker::flatten). Trading carries on
+    /// unless also paused.
+    Flatten,
+    /// Log the retained decision history, see
+    /// [`MarketMaker::dump_decisions`](super::MarketMaker::dump_decisions).
+    DumpDecisions,
+}
+
+/// Sends commands to a market maker from another task, e.g. to pause it around a news
+/// event without losing the warmed-up window.
+#[derive(Debug, Clone)]
+pub struct ControlHandle(pub(super) UnboundedSender<ControlCommand>);
+
+impl ControlHandle {
+    /// Returns false if the market maker is gone.
+    #[must_use]
+    pub fn send(&self, command: ControlCommand) -> bool {
+        self.0.send(command).is_ok()
+    }
+
+    #[must_use]
+    pub fn pause(&self) -> bool {
+        self.send(ControlCommand::Pause)
+    }
+
+    #[must_use]
+    pub fn resume(&self) -> bool {
+        self.send(ControlCommand::Resume)
+    }
+
+    #[must_use]
+    pub fn flatten(&self) -> bool {
+        self.send(ControlCommand::Flatten)
+    }
+
+    #[must_use]
+    pub fn dump_decisions(&self) -> bool {
+        self.send(ControlCommand::DumpDecisions)
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::EntrySignal;
+
+/// Position limit headroom on the side of an intended trade, and the size the trade
+/// was clamped to so it stays within the cap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionHeadroom {
+    pub position: f64,
+    pub cap: f64,
+    pub headroom: f64,
+    pub requested_size: f64,
+    pub clamped_size: f64,
+}
+
+impl PositionHeadroom {
+    /// Headroom for trading `requested_size` from `position` under an absolute `cap`.
+    pub(super) fn new(position: f64, cap: f64, is_buy: bool, requested_size: f64) -> Self {
+        let headroom = if is_buy {
+            cap - position
+        } else {
+            cap + position
+        }
+        .max(0.0);
+        Self {
+            position,
+            cap,
+            headroom,
+            requested_size,
+            clamped_size: requested_size.min(headroom),
+        }
+    }
+}
+
+/// How the base size of each immediate trade is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeMode {
+    /// Always this size.
+    Fixed(f64),
+    /// This fraction of the account's withdrawable collateral, converted to a size at
+    /// the current price and capped by `max_order_notional`.
+    FractionOfEquity(f64),
+}
+
+/// Size worth `fraction` of `withdrawable` collateral at `price`, capped at
+/// `max_notional`. Zero without a sane price.
+pub(super) fn equity_size(fraction: f64, withdrawable: f64, price: f64, max_notional: f64) -> f64 {
+    if !(price.is_finite() && price > 0.0) {
+        return 0.0;
+    }
+    (fraction * withdrawable).clamp(0.0, max_notional) / price
+}
+
+/// Trade a signal calls for, before any of the risk and execution checks. Deciding
+/// on it has no side effects, only acting on it sends orders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeIntent {
+    pub is_buy: bool,
+    pub size: f64,
+    /// Signal the trade is taken on.
+    pub signal: EntrySignal,
+}
+
+/// Sells Hyperliquid when it's rich against the reference and buys when it's cheap,
+/// leaning the size up to `max_size_multiplier` times `trade_size` for stronger
+/// mispricings. `None` while the signal is within its threshold.
+///
+/// Pure, so the live loop and offline backtests decide alike.
+pub(super) fn trade_intent(
+    signal: EntrySignal,
+    trade_size: f64,
+    max_size_multiplier: f64,
+) -> Option<TradeIntent> {
+    let (level, threshold) = signal.level();
+    let is_buy = if level > threshold {
+        false
+    } else if level < -threshold {
+        true
+    } else {
+        return None;
+    };
+    let size = if threshold <= 0.0 {
+        trade_size
+    } else {
+        trade_size * (level.abs() / threshold).clamp(1.0, max_size_multiplier.max(1.0))
+    };
+    Some(TradeIntent {
+        is_buy,
+        size,
+        signal,
+    })
+}
+
+/// Threshold for trading on the `is_buy` side, skewed toward flat inventory.
+///
+/// With `inventory` the position as a fraction of the cap, clamped to [-1, 1], the
+/// threshold is `z_threshold * (1 + coefficient * inventory)` for buys and
+/// `z_threshold * (1 - coefficient * inventory)` for sells, never below zero: a long
+/// position makes selling easier and buying harder.
+pub(super) fn skewed_threshold(
+    z_threshold: f64,
+    is_buy: bool,
+    inventory: f64,
+    coefficient: f64,
+) -> f64 {
+    let skew = coefficient * inventory.clamp(-1.0, 1.0);
+    let skew = if is_buy { skew } else { -skew };
+    z_threshold * (1.0 + skew).max(0.0)
+}
+
+/// Funding in bps a position on the `is_buy` side pays over `holding_time` at the
+/// hourly `funding_rate`, negative if it earns funding. Longs pay a positive rate.
+pub(super) fn funding_cost_bps(funding_rate: f64, is_buy: bool, holding_time: Duration) -> f64 {
+    let cost = super::fraction_to_bps(funding_rate * holding_time.as_secs_f64() / 3600.0);
+    if is_buy {
+        cost
+    } else {
+        -cost
+    }
+}
+
+/// What the bot did with a computed signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecisionOutcome {
+    /// The z-score stayed within the threshold.
+    NoSignal,
+    /// A signal fired but the named check held the trade back.
+    Blocked(&'static str),
+    /// A passive order was posted, to be escalated to a cross if it doesn't fill.
+    Posted {
+        is_buy: bool,
+        headroom: PositionHeadroom,
+    },
+    /// An order was sent; `filled` tells whether any of it executed.
+    Traded {
+        is_buy: bool,
+        filled: bool,
+        headroom: PositionHeadroom,
+    },
+}
+
+/// Context and outcome of a single signal evaluation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    /// Milliseconds since the Unix epoch.
+    pub time: i64,
+    pub hl_mid: f64,
+    pub reference_price: f64,
+    /// Time since the Binance price was received, `None` before the first.
+    pub reference_age: Option<Duration>,
+    pub diff: f64,
+    pub signal: EntrySignal,
+    /// Distance of the diff from its rolling mean in bps, the edge expected from
+    /// reversion.
+    pub edge_bps: f64,
+    /// Round-trip taker fees the edge has to cover, in bps.
+    pub fee_bps: f64,
+    pub outcome: DecisionOutcome,
+}
+
+/// Bounded in-memory history of the most recent decisions.
+pub(super) struct DecisionHistory {
+    capacity: usize,
+    decisions: VecDeque<Decision>,
+}
+
+impl DecisionHistory {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            decisions: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(super) fn push(&mut self, decision: Decision) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.decisions.len() == self.capacity {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(decision);
+    }
+
+    /// Oldest first.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Decision> {
+        self.decisions.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_maker::signal::z_score;
+
+    #[test]
+    fn equity_size_test() {
+        // 10% of 5000 at a price of 100
+        assert!((equity_size(0.1, 5_000.0, 100.0, f64::INFINITY) - 5.0).abs() < 1e-12);
+        // Capped by the maximum order notional
+        assert!((equity_size(0.5, 5_000.0, 100.0, 1_000.0) - 10.0).abs() < 1e-12);
+        assert!(equity_size(0.1, -10.0, 100.0, 1_000.0).abs() < 1e-12);
+        assert!(equity_size(0.1, 5_000.0, 0.0, 1_000.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn trade_intent_test() {
+        assert_eq!(trade_intent(z_score(1.5, 2.0), 0.001, 1.0), None);
+        assert_eq!(trade_intent(z_score(-2.0, 2.0), 0.001, 1.0), None);
+        assert!(!trade_intent(z_score(2.5, 2.0), 0.001, 1.0).unwrap().is_buy);
+        assert_eq!(
+            trade_intent(z_score(-2.5, 2.0), 0.001, 1.0),
+            Some(TradeIntent {
+                is_buy: true,
+                size: 0.001,
+                signal: z_score(-2.5, 2.0)
+            })
+        );
+        // A band signal sizes up the same way, in bps
+        let band = EntrySignal::Band {
+            bps: 12.0,
+            threshold_bps: 6.0,
+        };
+        assert!((trade_intent(band, 0.001, 3.0).unwrap().size - 0.002).abs() < 1e-12);
+    }
+
+    #[test]
+    fn skewed_threshold_test() {
+        // Long half the cap: buying needs more, selling less
+        assert!((skewed_threshold(2.0, true, 0.5, 0.5) - 2.5).abs() < 1e-12);
+        assert!((skewed_threshold(2.0, false, 0.5, 0.5) - 1.5).abs() < 1e-12);
+        assert!((skewed_threshold(2.0, true, -0.5, 0.5) - 1.5).abs() < 1e-12);
+        // Flat or no coefficient leaves the threshold alone
+        assert!((skewed_threshold(2.0, true, 0.0, 0.5) - 2.0).abs() < 1e-12);
+        assert!((skewed_threshold(2.0, false, 0.5, 0.0) - 2.0).abs() < 1e-12);
+        // Never negative
+        assert!(skewed_threshold(2.0, false, 3.0, 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn funding_cost_test() {
+        let half_hour = Duration::from_mins(30);
+        // 0.01% an hour for half an hour
+        assert!((funding_cost_bps(0.0001, true, half_hour) - 0.5).abs() < 1e-12);
+        assert!((funding_cost_bps(0.0001, false, half_hour) + 0.5).abs() < 1e-12);
+        assert!((funding_cost_bps(-0.0001, false, half_hour) - 0.5).abs() < 1e-12);
+    }
+
+    fn decision(time: i64) -> Decision {
+        Decision {
+            time,
+            hl_mid: 100.0,
+            reference_price: 100.0,
+            reference_age: None,
+            diff: 0.0,
+            signal: z_score(0.0, 2.0),
+            edge_bps: 0.0,
+            fee_bps: 0.0,
+            outcome: DecisionOutcome::NoSignal,
+        }
+    }
+
+    #[test]
+    fn headroom_test() {
+        let headroom = PositionHeadroom::new(0.0035, 0.004, true, 0.001);
+        assert!((headroom.headroom - 0.0005).abs() < 1e-12);
+        assert!((headroom.clamped_size - 0.0005).abs() < 1e-12);
+
+        let headroom = PositionHeadroom::new(0.0035, 0.004, false, 0.001);
+        assert!((headroom.headroom - 0.0075).abs() < 1e-12);
+        assert!((headroom.clamped_size - 0.001).abs() < 1e-12);
+
+        // Already past the cap
+        let headroom = PositionHeadroom::new(-0.005, 0.004, false, 0.001);
+        assert!(headroom.clamped_size.abs() < 1e-12);
+    }
+
+    #[test]
+    fn keeps_last_decisions_test() {
+        let mut history = DecisionHistory::new(3);
+        for time in 0..5 {
+            history.push(decision(time));
+        }
+        let times: Vec<_> = history.iter().map(|decision| decision.time).collect();
+        assert_eq!(times, vec![2, 3, 4]);
+
+        let mut disabled = DecisionHistory::new(0);
+        disabled.push(decision(0));
+        assert_eq!(disabled.iter().count(), 0);
+    }
+}
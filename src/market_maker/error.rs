@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+/// Why a [`MarketMaker`](super::MarketMaker) couldn't be created.
+#[derive(Error, Debug)]
+pub enum MarketMakerError {
+    #[error("Info client init error: {0}")]
+    InfoClientInit(crate::Error),
+    #[error("Exchange client init error: {0}")]
+    ExchangeClientInit(crate::Error),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Asset {0} is not in the exchange metadata")]
+    UnknownAsset(String),
+    #[error("Config error: {0}")]
+    Config(String),
+    #[error("Wallet error: {0}")]
+    Wallet(String),
+    #[error("Trade log error: {0}")]
+    TradeLog(std::io::Error),
+    #[error(transparent)]
+    Sdk(#[from] crate::Error),
+}
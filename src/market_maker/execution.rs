@@ -0,0 +1,103 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// How a signal is executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionStyle {
+    /// Cross the spread immediately with an IOC order.
+    #[default]
+    Cross,
+    /// Post a passive order at the touch and cross with whatever hasn't filled after
+    /// `wait`.
+    PostThenCross { wait: Duration },
+}
+
+/// Time in force of the orders `place_order` sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeInForce {
+    /// Immediate or cancel, anything that doesn't fill right away is cancelled.
+    #[default]
+    Ioc,
+    /// Good til cancelled.
+    Gtc,
+    /// Add liquidity only, rejected instead of crossing.
+    Alo,
+}
+
+impl TimeInForce {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ioc => "Ioc",
+            Self::Gtc => "Gtc",
+            Self::Alo => "Alo",
+        }
+    }
+}
+
+impl FromStr for TimeInForce {
+    type Err = Error;
+
+    fn from_str(tif: &str) -> Result<Self, Self::Err> {
+        match tif {
+            "Ioc" => Ok(Self::Ioc),
+            "Gtc" => Ok(Self::Gtc),
+            "Alo" => Ok(Self::Alo),
+            _ => Err(Error::GenericParse(format!("Unknown time in force: {tif}"))),
+        }
+    }
+}
+
+/// A post-only order waiting to fill before it is escalated to a cross.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PassiveOrder {
+    pub(super) oid: u64,
+    pub(super) is_buy: bool,
+    pub(super) remaining: f64,
+    pub(super) placed_at: Instant,
+}
+
+impl PassiveOrder {
+    /// Reduces the remaining size by a fill and returns true once it's fully filled.
+    pub(super) fn on_fill(&mut self, sz: f64) -> bool {
+        self.remaining -= sz;
+        self.remaining <= f64::EPSILON
+    }
+
+    pub(super) fn should_escalate(&self, now: Instant, wait: Duration) -> bool {
+        now.duration_since(self.placed_at) >= wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passive_order_test() {
+        let placed_at = Instant::now();
+        let mut order = PassiveOrder {
+            oid: 1,
+            is_buy: true,
+            remaining: 1.0,
+            placed_at,
+        };
+        let wait = Duration::from_millis(500);
+        assert!(!order.should_escalate(placed_at + Duration::from_millis(499), wait));
+        assert!(order.should_escalate(placed_at + wait, wait));
+
+        assert!(!order.on_fill(0.4));
+        assert!(order.on_fill(0.6));
+    }
+
+    #[test]
+    fn parse_time_in_force_test() {
+        for tif in [TimeInForce::Ioc, TimeInForce::Gtc, TimeInForce::Alo] {
+            assert_eq!(tif.as_str().parse::<TimeInForce>().unwrap(), tif);
+        }
+        assert!("ioc".parse::<TimeInForce>().is_err());
+        assert!("Fok".parse::<TimeInForce>().is_err());
+    }
+}
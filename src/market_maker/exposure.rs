@@ -0,0 +1,163 @@
+use gxhash::{HashMap, HashMapExt};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct AssetExposure {
+    position: f64,
+    mark: f64,
+}
+
+impl AssetExposure {
+    fn notional(self) -> f64 {
+        self.position.abs() * self.mark
+    }
+}
+
+/// Portfolio level risk budget shared by the market makers of a pool. Notionals are
+/// taken at the latest mark of each asset, and optional per-asset caps sub-allocate
+/// the aggregate budget.
+#[derive(Debug, Clone)]
+pub struct ExposureTracker {
+    max_aggregate_notional: f64,
+    asset_caps: HashMap<String, f64>,
+    max_active_assets: Option<usize>,
+    assets: HashMap<String, AssetExposure>,
+}
+
+impl ExposureTracker {
+    #[must_use]
+    pub fn new(max_aggregate_notional: f64) -> Self {
+        Self {
+            max_aggregate_notional,
+            asset_caps: HashMap::new(),
+            max_active_assets: None,
+            assets: HashMap::new(),
+        }
+    }
+
+    /// Cap the notional of `asset` on its own, within the aggregate budget.
+    #[must_use]
+    pub fn with_asset_cap(mut self, asset: &str, max_notional: f64) -> Self {
+        self.asset_caps.insert(asset.to_string(), max_notional);
+        self
+    }
+
+    /// Limit how many assets may hold a nonzero position at the same time.
+    #[must_use]
+    pub const fn with_max_active_assets(mut self, max_active_assets: usize) -> Self {
+        self.max_active_assets = Some(max_active_assets);
+        self
+    }
+
+    pub fn update_position(&mut self, asset: &str, position: f64) {
+        self.assets.entry(asset.to_string()).or_default().position = position;
+    }
+
+    pub fn update_mark(&mut self, asset: &str, mark: f64) {
+        self.assets.entry(asset.to_string()).or_default().mark = mark;
+    }
+
+    #[must_use]
+    pub fn aggregate_notional(&self) -> f64 {
+        self.assets
+            .values()
+            .map(|exposure| exposure.notional())
+            .sum()
+    }
+
+    /// Number of assets currently holding a nonzero position.
+    #[must_use]
+    pub fn active_assets(&self) -> usize {
+        self.assets
+            .values()
+            .filter(|exposure| exposure.position.abs() > f64::EPSILON)
+            .count()
+    }
+
+    /// Returns false if `asset` is flat and the maximum number of assets already hold
+    /// a position, so it may not open a new one.
+    #[must_use]
+    pub fn allows_entry(&self, asset: &str) -> bool {
+        let Some(max_active_assets) = self.max_active_assets else {
+            return true;
+        };
+        let is_active = self
+            .assets
+            .get(asset)
+            .is_some_and(|exposure| exposure.position.abs() > f64::EPSILON);
+        is_active || self.active_assets() < max_active_assets
+    }
+
+    /// Returns true if trading `signed_sz` of `asset` at `mark` keeps both its own cap
+    /// and the aggregate budget. Trades that reduce exposure are always allowed.
+    #[must_use]
+    pub fn allows(&self, asset: &str, signed_sz: f64, mark: f64) -> bool {
+        let current = self.assets.get(asset).copied().unwrap_or_default();
+        let after = AssetExposure {
+            position: current.position + signed_sz,
+            mark,
+        };
+        let current = AssetExposure { mark, ..current };
+        if after.notional() <= current.notional() {
+            return true;
+        }
+
+        if self
+            .asset_caps
+            .get(asset)
+            .is_some_and(|cap| after.notional() > *cap)
+        {
+            return false;
+        }
+
+        let others: f64 = self
+            .assets
+            .iter()
+            .filter(|(name, _)| name.as_str() != asset)
+            .map(|(_, exposure)| exposure.notional())
+            .sum();
+        others + after.notional() <= self.max_aggregate_notional
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposure_limits_test() {
+        let mut tracker = ExposureTracker::new(1_000.0).with_asset_cap("ETH", 600.0);
+        assert!(tracker.allows("BTC", 0.01, 50_000.0));
+        assert!(!tracker.allows("BTC", 0.03, 50_000.0));
+
+        // ETH's own cap binds before the aggregate budget
+        assert!(!tracker.allows("ETH", 0.4, 2_000.0));
+        tracker.update_mark("ETH", 2_000.0);
+        tracker.update_position("ETH", 0.25);
+        assert!((tracker.aggregate_notional() - 500.0).abs() < 1e-9);
+
+        // BTC has no cap of its own but only the remaining budget
+        assert!(tracker.allows("BTC", 0.01, 50_000.0));
+        assert!(!tracker.allows("BTC", 0.011, 50_000.0));
+
+        // Reducing is allowed even over budget
+        tracker.update_position("ETH", 1.0);
+        assert!(tracker.allows("ETH", -0.5, 2_000.0));
+        assert!(!tracker.allows("BTC", -0.001, 50_000.0));
+    }
+
+    #[test]
+    fn max_active_assets_test() {
+        let mut tracker = ExposureTracker::new(f64::MAX).with_max_active_assets(2);
+        tracker.update_position("BTC", 0.01);
+        assert!(tracker.allows_entry("ETH"));
+        tracker.update_position("ETH", -1.0);
+        assert_eq!(tracker.active_assets(), 2);
+
+        // The third asset waits, active ones may keep trading
+        assert!(!tracker.allows_entry("SOL"));
+        assert!(tracker.allows_entry("BTC"));
+
+        tracker.update_position("BTC", 0.0);
+        assert!(tracker.allows_entry("SOL"));
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::warn;
+
+/// Latest price from a venue feed and when it was received.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReferencePrice {
+    pub price: f64,
+    pub updated_at: Option<Instant>,
+}
+
+impl ReferencePrice {
+    pub fn set(&mut self, price: f64) {
+        self.price = price;
+        self.updated_at = Some(Instant::now());
+    }
+
+    /// Returns true if no price was received within `max_age` of `now`.
+    #[must_use]
+    pub fn is_stale(&self, now: Instant, max_age: Duration) -> bool {
+        self.updated_at
+            .is_none_or(|updated_at| now.duration_since(updated_at) > max_age)
+    }
+}
+
+/// Latest reference price shared between a feed and the market maker. Reads never
+/// wait on the writer and there is no lock to poison.
+#[derive(Debug, Clone)]
+pub struct SharedReferencePrice(Arc<watch::Sender<ReferencePrice>>);
+
+impl Default for SharedReferencePrice {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(ReferencePrice::default()).0))
+    }
+}
+
+impl SharedReferencePrice {
+    /// Publish a price received now.
+    pub fn set(&self, price: f64) {
+        self.0.send_modify(|reference| reference.set(price));
+    }
+
+    pub fn store(&self, reference: ReferencePrice) {
+        self.0.send_replace(reference);
+    }
+
+    #[must_use]
+    pub fn get(&self) -> ReferencePrice {
+        *self.0.borrow()
+    }
+
+    /// Receiver that is notified of every new price.
+    #[must_use]
+    pub fn subscribe(&self) -> watch::Receiver<ReferencePrice> {
+        self.0.subscribe()
+    }
+}
+
+/// Source of the fair value the z-score is computed against.
+///
+/// Implemented for the shared price the venue feeds update, for closures wrapping a
+/// user's own pricing model, and for [`BlendedFairValue`] combining several sources.
+pub trait FairValueSource: Send {
+    /// Current fair value, or `None` if the source has none yet.
+    fn fair_value(&self) -> Option<f64>;
+}
+
+/// Price shared with a venue feed. Zero means no price was received yet.
+impl FairValueSource for Arc<Mutex<f64>> {
+    fn fair_value(&self) -> Option<f64> {
+        let price = *self.lock().unwrap();
+        (price.abs() > f64::EPSILON).then_some(price)
+    }
+}
+
+impl FairValueSource for SharedReferencePrice {
+    fn fair_value(&self) -> Option<f64> {
+        let price = self.get().price;
+        (price.abs() > f64::EPSILON).then_some(price)
+    }
+}
+
+impl<F: Fn() -> Option<f64> + Send> FairValueSource for F {
+    fn fair_value(&self) -> Option<f64> {
+        self()
+    }
+}
+
+/// Weighted average of several sources. Sources without a value are left out and
+/// the remaining weights renormalized.
+#[derive(Default)]
+pub struct BlendedFairValue {
+    sources: Vec<(Box<dyn FairValueSource>, f64)>,
+}
+
+impl BlendedFairValue {
+    #[must_use]
+    pub fn with_source(mut self, source: impl FairValueSource + 'static, weight: f64) -> Self {
+        self.sources.push((Box::new(source), weight));
+        self
+    }
+}
+
+impl FairValueSource for BlendedFairValue {
+    fn fair_value(&self) -> Option<f64> {
+        let (weighted, total_weight) = self
+            .sources
+            .iter()
+            .filter_map(|(source, weight)| Some((source.fair_value()?, *weight)))
+            .fold((0.0, 0.0), |(weighted, total), (value, weight)| {
+                (value.mul_add(weight, weighted), total + weight)
+            });
+        (total_weight > f64::EPSILON).then(|| weighted / total_weight)
+    }
+}
+
+/// Median of the fresh prices of several reference venues, so a single venue's
+/// glitch or outage can't move the benchmark. Gives no value, and so no trading,
+/// while fewer than `quorum` venues are fresh.
+#[derive(Debug, Clone)]
+pub struct MedianReferencePrice {
+    venues: Vec<(String, SharedReferencePrice)>,
+    max_age: Duration,
+    quorum: usize,
+}
+
+impl MedianReferencePrice {
+    #[must_use]
+    pub const fn new(max_age: Duration, quorum: usize) -> Self {
+        Self {
+            venues: Vec::new(),
+            max_age,
+            quorum,
+        }
+    }
+
+    /// Add a venue whose feed publishes to `price`.
+    #[must_use]
+    pub fn with_venue(mut self, name: &str, price: SharedReferencePrice) -> Self {
+        self.venues.push((name.to_string(), price));
+        self
+    }
+
+    /// Median of the venues fresh at `now`, if at least `quorum` of them are.
+    #[must_use]
+    pub fn median_at(&self, now: Instant) -> Option<f64> {
+        let mut prices: Vec<f64> = self
+            .venues
+            .iter()
+            .map(|(_, price)| price.get())
+            .filter(|reference| !reference.is_stale(now, self.max_age) && reference.price > 0.0)
+            .map(|reference| reference.price)
+            .collect();
+        if prices.is_empty() || prices.len() < self.quorum {
+            warn!(
+                "Only {} of {} reference venues are fresh, {} needed",
+                prices.len(),
+                self.venues.len(),
+                self.quorum
+            );
+            return None;
+        }
+        prices.sort_by(f64::total_cmp);
+        let mid = prices.len() / 2;
+        Some(if prices.len().is_multiple_of(2) {
+            f64::midpoint(prices[mid - 1], prices[mid])
+        } else {
+            prices[mid]
+        })
+    }
+}
+
+impl FairValueSource for MedianReferencePrice {
+    fn fair_value(&self) -> Option<f64> {
+        self.median_at(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_price_staleness_test() {
+        let mut reference = ReferencePrice::default();
+        let now = Instant::now();
+        assert!(reference.is_stale(now, Duration::from_secs(5)));
+
+        reference.set(100.0);
+        let updated_at = reference.updated_at.unwrap();
+        assert!(!reference.is_stale(updated_at + Duration::from_secs(5), Duration::from_secs(5)));
+        assert!(reference.is_stale(updated_at + Duration::from_secs(6), Duration::from_secs(5)));
+    }
+
+    #[tokio::test]
+    async fn shared_reference_price_test() {
+        let shared = SharedReferencePrice::default();
+        let mut receiver = shared.subscribe();
+        let feed = shared.clone();
+        tokio::spawn(async move { feed.set(101.0) });
+
+        receiver.changed().await.unwrap();
+        assert!((receiver.borrow().price - 101.0).abs() < 1e-9);
+        assert!((shared.get().price - 101.0).abs() < 1e-9);
+        assert!(shared.get().updated_at.is_some());
+    }
+
+    #[test]
+    fn blended_fair_value_test() {
+        let venue = Arc::new(Mutex::new(0.0));
+        let blend = BlendedFairValue::default()
+            .with_source(venue.clone(), 1.0)
+            .with_source(|| Some(104.0), 3.0);
+        // The venue has no price yet, the model alone decides
+        assert_eq!(blend.fair_value(), Some(104.0));
+
+        *venue.lock().unwrap() = 100.0;
+        let value = blend.fair_value().unwrap();
+        assert!((value - 103.0).abs() < 1e-9);
+
+        assert_eq!(BlendedFairValue::default().fair_value(), None);
+    }
+
+    #[test]
+    fn median_reference_price_test() {
+        let [binance, coinbase, okx] = [(); 3].map(|()| SharedReferencePrice::default());
+        let median = MedianReferencePrice::new(Duration::from_secs(5), 2)
+            .with_venue("binance", binance.clone())
+            .with_venue("coinbase", coinbase.clone())
+            .with_venue("okx", okx.clone());
+        binance.set(100.0);
+        // One fresh venue is below the quorum
+        assert_eq!(median.fair_value(), None);
+
+        coinbase.set(102.0);
+        assert_eq!(median.fair_value(), Some(101.0));
+
+        // A glitching venue doesn't move the median of three
+        okx.set(150.0);
+        assert_eq!(median.fair_value(), Some(102.0));
+
+        // Venues that went quiet are left out
+        let later = Instant::now() + Duration::from_secs(6);
+        assert_eq!(median.median_at(later), None);
+    }
+}
@@ -0,0 +1,214 @@
+use serde::Deserialize;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use super::{Backoff, ReconnectLimiter};
+
+/// Exchange the reference price is streamed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceVenue {
+    /// The futures trade stream, see [`BinanceFeed`](crate::BinanceFeed).
+    #[default]
+    Binance,
+    /// The spot ticker, see [`CoinbaseFeed`](crate::CoinbaseFeed).
+    Coinbase,
+}
+
+impl ReferenceVenue {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Binance => "Binance",
+            Self::Coinbase => "Coinbase",
+        }
+    }
+}
+
+/// Binance stream the reference price is taken from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinancePriceSource {
+    /// Last trade price, or the VWAP of recent trades.
+    #[default]
+    Trade,
+    /// Size-weighted mid of the top of the partial depth stream, which doesn't print
+    /// through the spread like a trade can.
+    Microprice,
+}
+
+impl BinancePriceSource {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Trade => "trade",
+            Self::Microprice => "microprice",
+        }
+    }
+}
+
+/// Cancellation signal handed to a spawned feed. The feed should select on
+/// [`FeedCancellation::cancelled`] and flush and close its connection once it fires.
+#[derive(Debug, Clone)]
+pub struct FeedCancellation(watch::Receiver<bool>);
+
+impl FeedCancellation {
+    /// Resolves once the owner of the feed asks it to stop, or is dropped.
+    pub async fn cancelled(&mut self) {
+        while !*self.0.borrow_and_update() {
+            if self.0.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+}
+
+/// Handle to a background feed task that can be cancelled and joined on shutdown,
+/// rather than aborted mid-write when the runtime tears down.
+#[derive(Debug)]
+pub struct FeedTask {
+    cancel: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+impl FeedTask {
+    pub fn spawn<F, Fut>(feed: F) -> Self
+    where
+        F: FnOnce(FeedCancellation) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (cancel, receiver) = watch::channel(false);
+        let handle = tokio::spawn(feed(FeedCancellation(receiver)));
+        Self { cancel, handle }
+    }
+
+    /// Signal the feed to stop and wait for it to finish cleaning up.
+    pub async fn stop(self) {
+        // The feed may already have exited on its own, dropping the receiver
+        let _ = self.cancel.send(true);
+        if let Err(e) = self.handle.await {
+            error!("Feed task failed: {e}");
+        }
+    }
+}
+
+/// Keep a feed connected until cancelled.
+///
+/// `connect` runs one connection until it drops; it is retried with `backoff`, which
+/// restarts once a connection outlives the maximum delay. Every attempt is also
+/// checked against the shared `limiter`.
+///
+/// # Panics
+///
+/// Panics if the limiter mutex is poisoned.
+pub async fn run_with_reconnect<F, Fut, E>(
+    name: &str,
+    mut cancellation: FeedCancellation,
+    mut backoff: Backoff,
+    limiter: Option<Arc<Mutex<ReconnectLimiter>>>,
+    mut connect: F,
+) where
+    F: FnMut(FeedCancellation) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: Debug,
+{
+    while !cancellation.is_cancelled() {
+        let attempt = limiter.as_ref().map_or(Ok(()), |limiter| {
+            limiter.lock().unwrap().try_attempt(Instant::now())
+        });
+        if let Err(retry_at) = attempt {
+            tokio::select! {
+                () = tokio::time::sleep_until(retry_at.into()) => continue,
+                () = cancellation.cancelled() => break,
+            }
+        }
+
+        let connected_at = Instant::now();
+        if let Err(e) = connect(cancellation.clone()).await {
+            warn!("{name} feed error: {e:?}");
+        }
+        if cancellation.is_cancelled() {
+            break;
+        }
+        if connected_at.elapsed() > backoff.max() {
+            backoff.reset();
+        }
+
+        let delay = backoff.next_delay();
+        warn!(
+            "{name} feed disconnected, reconnect attempt {} in {delay:?}",
+            backoff.attempts()
+        );
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            () = cancellation.cancelled() => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn feed_flushes_on_cancel_test() {
+        let flushed = Arc::new(AtomicBool::new(false));
+        let feed_flushed = flushed.clone();
+        let feed = FeedTask::spawn(|mut cancellation| async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(1));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    () = cancellation.cancelled() => break,
+                }
+            }
+            assert!(cancellation.is_cancelled());
+            feed_flushed.store(true, Ordering::SeqCst);
+        });
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(!flushed.load(Ordering::SeqCst));
+        feed.stop().await;
+        assert!(flushed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn reconnects_until_connected_test() {
+        let connects = Arc::new(AtomicUsize::new(0));
+        let feed_connects = connects.clone();
+        let feed = FeedTask::spawn(|cancellation| async move {
+            let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(4));
+            run_with_reconnect("test", cancellation, backoff, None, |mut cancellation| {
+                let attempt = feed_connects.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    if attempt < 3 {
+                        return Err("connection refused");
+                    }
+                    // Stays connected until shutdown
+                    cancellation.cancelled().await;
+                    Ok(())
+                }
+            })
+            .await;
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while connects.load(Ordering::SeqCst) < 3 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        feed.stop().await;
+        assert_eq!(connects.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,566 @@
+use crate::{AssetPosition, TradeInfo, UserTokenBalance};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+type FillKey = (u64, u64, String, String);
+
+/// Bounded set of recently seen fills, used to drop fills that are delivered twice
+/// (e.g. replayed after a reconnect).
+pub(super) struct FillDeduper {
+    capacity: usize,
+    order: VecDeque<FillKey>,
+    seen: HashSet<FillKey>,
+}
+
+impl FillDeduper {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Records the fill and returns false if it has already been seen within the
+    /// window. A zero capacity disables de-duplication.
+    pub(super) fn insert(&mut self, fill: &TradeInfo) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+
+        let key = (fill.oid, fill.time, fill.hash.clone(), fill.sz.clone());
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.order.push_back(key);
+        true
+    }
+}
+
+/// Volume-weighted average price of the fills on one side.
+#[derive(Default)]
+pub(super) struct SideVwap {
+    notional: f64,
+    size: f64,
+}
+
+impl SideVwap {
+    pub(super) fn add(&mut self, px: f64, sz: f64) {
+        self.notional += px * sz;
+        self.size += sz;
+    }
+
+    pub(super) fn average(&self) -> Option<f64> {
+        (self.size > 0.0).then(|| self.notional / self.size)
+    }
+
+    pub(super) const fn size(&self) -> f64 {
+        self.size
+    }
+
+    pub(super) const fn notional(&self) -> f64 {
+        self.notional
+    }
+}
+
+/// Price the open position is marked at for its unrealized profit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkSource {
+    /// The Hyperliquid mid, where the position would be closed.
+    #[default]
+    HlMid,
+    /// The reference price the diff is measured against.
+    Reference,
+    /// Midpoint of the two.
+    MidOfBoth,
+}
+
+impl MarkSource {
+    /// Mark from the Hyperliquid mid and the reference, falling back to the mid
+    /// while there is no reference price.
+    pub(super) fn mark_px(self, hl_mid: f64, reference: f64) -> f64 {
+        if !(reference.is_finite() && reference > 0.0) {
+            return hl_mid;
+        }
+        match self {
+            Self::HlMid => hl_mid,
+            Self::Reference => reference,
+            Self::MidOfBoth => f64::midpoint(hl_mid, reference),
+        }
+    }
+}
+
+/// Gross size and notional filled on each side over the session, next to the net
+/// position they add up to, to show turnover and whether trading leans one way.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Exposure {
+    pub position: f64,
+    pub gross_bought: f64,
+    pub gross_sold: f64,
+    pub bought_notional: f64,
+    pub sold_notional: f64,
+}
+
+impl Exposure {
+    /// Share of the gross size that was bought, 0.5 for a balanced session and
+    /// `None` before the first fill.
+    #[must_use]
+    pub fn buy_share(&self) -> Option<f64> {
+        let gross = self.gross_bought + self.gross_sold;
+        (gross > 0.0).then(|| self.gross_bought / gross)
+    }
+}
+
+/// `PnL` of the round trips closed over the session, split into edge and drift.
+///
+/// The edge is the spread of the fill price to the reference converging, which the
+/// strategy trades on, and drift the reference itself moving while the position was
+/// held. The two add up to the `PnL` before fees.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpreadCapture {
+    pub round_trips: usize,
+    pub edge_pnl: f64,
+    pub drift_pnl: f64,
+}
+
+/// A position from flat back to flat, with the diff to the reference in bps averaged
+/// by size over its entry and its exit fills.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct RoundTrip {
+    pub(super) is_long: bool,
+    pub(super) size: f64,
+    pub(super) entry_diff_bps: f64,
+    pub(super) exit_diff_bps: f64,
+    pub(super) edge_pnl: f64,
+    pub(super) drift_pnl: f64,
+}
+
+/// Reference price and spread of the fill price over it, averaged by size.
+#[derive(Debug, Clone, Copy, Default)]
+struct Leg {
+    size: f64,
+    reference: f64,
+    spread: f64,
+}
+
+impl Leg {
+    fn add(&mut self, size: f64, reference: f64, spread: f64) {
+        let total = self.size + size;
+        self.reference = self.reference.mul_add(self.size, reference * size) / total;
+        self.spread = self.spread.mul_add(self.size, spread * size) / total;
+        self.size = total;
+    }
+
+    fn diff_bps(&self) -> f64 {
+        super::price_to_bps(self.reference, self.reference + self.spread)
+    }
+}
+
+/// Follows the position through fills to attribute each round trip's `PnL` to the
+/// edge and to drift.
+#[derive(Debug, Default)]
+pub(super) struct RoundTripTracker {
+    position: f64,
+    /// `None` for a position taken over without knowing what it was entered at.
+    entry: Option<Leg>,
+    exit: Leg,
+    edge_pnl: f64,
+    drift_pnl: f64,
+    capture: SpreadCapture,
+}
+
+impl RoundTripTracker {
+    /// Books a signed fill at `px` with the reference at `reference`, returning the
+    /// round trip it completed if it closed the position.
+    pub(super) fn apply(&mut self, signed_sz: f64, px: f64, reference: f64) -> Option<RoundTrip> {
+        let spread = px - reference;
+        let closing = if self.position * signed_sz < 0.0 {
+            signed_sz.abs().min(self.position.abs())
+        } else {
+            0.0
+        };
+        let mut completed = None;
+        if closing > f64::EPSILON {
+            let sign = self.position.signum();
+            if let Some(entry) = self.entry {
+                self.edge_pnl += sign * closing * (spread - entry.spread);
+                self.drift_pnl += sign * closing * (reference - entry.reference);
+                self.exit.add(closing, reference, spread);
+            }
+            self.position -= sign * closing;
+            if self.position.abs() < f64::EPSILON {
+                self.position = 0.0;
+                completed = self.finish(sign > 0.0);
+            }
+        }
+        let opening = signed_sz.abs() - closing;
+        if opening > f64::EPSILON {
+            if self.position.abs() < f64::EPSILON {
+                self.entry = Some(Leg::default());
+            }
+            if let Some(entry) = self.entry.as_mut() {
+                entry.add(opening, reference, spread);
+            }
+            self.position += opening * signed_sz.signum();
+        }
+        completed
+    }
+
+    /// Takes over a `position` from elsewhere, whose round trip isn't attributed.
+    pub(super) fn reset(&mut self, position: f64) {
+        *self = Self {
+            position,
+            capture: self.capture,
+            ..Self::default()
+        };
+    }
+
+    pub(super) const fn capture(&self) -> SpreadCapture {
+        self.capture
+    }
+
+    fn finish(&mut self, is_long: bool) -> Option<RoundTrip> {
+        let entry = self.entry.take()?;
+        let round_trip = RoundTrip {
+            is_long,
+            size: entry.size,
+            entry_diff_bps: entry.diff_bps(),
+            exit_diff_bps: self.exit.diff_bps(),
+            edge_pnl: std::mem::take(&mut self.edge_pnl),
+            drift_pnl: std::mem::take(&mut self.drift_pnl),
+        };
+        self.exit = Leg::default();
+        self.capture.round_trips += 1;
+        self.capture.edge_pnl += round_trip.edge_pnl;
+        self.capture.drift_pnl += round_trip.drift_pnl;
+        Some(round_trip)
+    }
+}
+
+/// Fills reported inline in order responses that `UserEvents` hasn't delivered yet.
+/// Positions are only ever updated from `UserEvents`; the inline report is kept as an
+/// acknowledgement and matched off by oid, so the fill is never applied twice.
+#[derive(Default)]
+pub(super) struct InlineFills {
+    pending: HashMap<u64, f64>,
+}
+
+impl InlineFills {
+    pub(super) fn acknowledge(&mut self, oid: u64, sz: f64) {
+        *self.pending.entry(oid).or_default() += sz;
+    }
+
+    /// Matches a `UserEvents` fill against the inline acknowledgements. Returns true if
+    /// it had been reported inline already.
+    pub(super) fn confirm(&mut self, oid: u64, sz: f64) -> bool {
+        let Some(pending) = self.pending.get_mut(&oid) else {
+            return false;
+        };
+        *pending -= sz;
+        if *pending <= f64::EPSILON {
+            self.pending.remove(&oid);
+        }
+        true
+    }
+
+    pub(super) fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Signed net position and its average entry price, maintained from fills.
+#[derive(Default)]
+pub(super) struct Inventory {
+    position: f64,
+    avg_entry_px: f64,
+}
+
+impl Inventory {
+    /// Applies a signed fill (positive for buys). Adding to the position averages the
+    /// entry, reducing it keeps the entry and flipping through flat restarts it at `px`.
+    /// Returns the `PnL` realized by the part of the fill that closed the position.
+    pub(super) fn apply(&mut self, signed_sz: f64, px: f64) -> f64 {
+        let realized =
+            if self.position.abs() > f64::EPSILON && self.position.signum() != signed_sz.signum() {
+                let closed = signed_sz.abs().min(self.position.abs());
+                closed * (px - self.avg_entry_px) * self.position.signum()
+            } else {
+                0.0
+            };
+
+        let new_position = self.position + signed_sz;
+        if new_position.abs() < f64::EPSILON {
+            self.avg_entry_px = 0.0;
+        } else if self.position.abs() < f64::EPSILON || self.position.signum() == signed_sz.signum()
+        {
+            self.avg_entry_px = self
+                .avg_entry_px
+                .mul_add(self.position.abs(), px * signed_sz.abs())
+                / new_position.abs();
+        } else if new_position.signum() != self.position.signum() {
+            self.avg_entry_px = px;
+        }
+        self.position = new_position;
+        realized
+    }
+
+    /// Overwrite the tracked state with the exchange's view of the position.
+    pub(super) const fn reset(&mut self, position: f64, avg_entry_px: f64) {
+        self.position = position;
+        self.avg_entry_px = avg_entry_px;
+    }
+
+    pub(super) const fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub(super) const fn avg_entry_px(&self) -> f64 {
+        self.avg_entry_px
+    }
+}
+
+/// Signed size and entry price of the exchange's position in `asset`, zero if there
+/// is none.
+pub(super) fn exchange_position(asset_positions: &[AssetPosition], asset: &str) -> (f64, f64) {
+    asset_positions
+        .iter()
+        .find(|asset_position| asset_position.position.coin == asset)
+        .map_or((0.0, 0.0), |asset_position| {
+            let position = &asset_position.position;
+            (
+                position.szi.parse().unwrap_or(0.0),
+                position
+                    .entry_px
+                    .as_ref()
+                    .and_then(|px| px.parse().ok())
+                    .unwrap_or(0.0),
+            )
+        })
+}
+
+/// Balance and average entry price of the `token` a spot pair trades, zero when
+/// the wallet holds none.
+pub(super) fn spot_position(balances: &[UserTokenBalance], token: &str) -> (f64, f64) {
+    balances
+        .iter()
+        .find(|balance| balance.coin == token)
+        .map_or((0.0, 0.0), |balance| {
+            let total = balance.total.parse().unwrap_or(0.0);
+            let entry_ntl = balance.entry_ntl.parse().unwrap_or(0.0);
+            if total > 0.0 {
+                (total, entry_ntl / total)
+            } else {
+                (total, 0.0)
+            }
+        })
+}
+
+/// Fee paid on `fill`, negative for a rebate. A maker fill reporting no fee, like a
+/// simulated one, is credited `maker_rebate_bps` of its notional instead.
+pub(super) fn fee(fill: &TradeInfo, maker_rebate_bps: f64) -> f64 {
+    let fee: f64 = fill.fee.parse().unwrap_or(0.0);
+    if fill.crossed || fee.abs() > 0.0 {
+        return fee;
+    }
+    let notional = fill.px.parse::<f64>().unwrap_or(0.0) * fill.sz.parse::<f64>().unwrap_or(0.0);
+    -super::bps_to_price(notional, maker_rebate_bps)
+}
+
+/// Fill for a simulated paper order, shaped like one from `UserEvents`.
+pub(super) fn simulated_fill(coin: &str, is_buy: bool, sz: f64, px: f64, oid: u64) -> TradeInfo {
+    TradeInfo {
+        coin: coin.to_string(),
+        side: if is_buy { "B" } else { "A" }.to_string(),
+        px: px.to_string(),
+        sz: sz.to_string(),
+        time: u64::try_from(chrono::Utc::now().timestamp_millis()).unwrap_or_default(),
+        hash: format!("paper-{oid}"),
+        start_position: String::new(),
+        dir: String::new(),
+        closed_pnl: "0".to_string(),
+        oid,
+        cloid: None,
+        crossed: true,
+        fee: "0".to_string(),
+    }
+}
+
+#[cfg(test)]
+pub(super) fn fill(side: &str, sz: f64, oid: u64, time: u64) -> TradeInfo {
+    TradeInfo {
+        coin: "BTC".to_string(),
+        side: side.to_string(),
+        px: "100".to_string(),
+        sz: sz.to_string(),
+        time,
+        hash: format!("0x{oid:x}"),
+        start_position: "0".to_string(),
+        dir: String::new(),
+        closed_pnl: "0".to_string(),
+        oid,
+        cloid: None,
+        crossed: true,
+        fee: "0".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset_position(coin: &str, szi: &str, entry_px: &str) -> AssetPosition {
+        serde_json::from_value(serde_json::json!({
+            "type": "oneWay",
+            "position": {
+                "coin": coin,
+                "entryPx": entry_px,
+                "leverage": {"type": "cross", "value": 20},
+                "liquidationPx": null,
+                "marginUsed": "0",
+                "positionValue": "0",
+                "returnOnEquity": "0",
+                "szi": szi,
+                "unrealizedPnl": "0"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn exchange_position_test() {
+        let positions = vec![
+            asset_position("ETH", "2.5", "3000"),
+            asset_position("BTC", "-0.002", "100000"),
+        ];
+        assert_eq!(exchange_position(&positions, "BTC"), (-0.002, 100_000.0));
+        // No position in the asset, the ETH position doesn't bleed in
+        assert_eq!(exchange_position(&positions[..1], "BTC"), (0.0, 0.0));
+        assert_eq!(exchange_position(&[], "BTC"), (0.0, 0.0));
+    }
+
+    #[test]
+    fn fee_test() {
+        let taker = fill("B", 0.5, 1, 0);
+        assert!(fee(&taker, 1.5).abs() < f64::EPSILON);
+        let maker = TradeInfo {
+            crossed: false,
+            ..taker
+        };
+        assert!((fee(&maker, 1.5) + 0.0075).abs() < 1e-12);
+        // A fee the exchange reports wins over the configured rebate
+        let reported = TradeInfo {
+            fee: "-0.002".to_string(),
+            ..maker
+        };
+        assert!((fee(&reported, 1.5) + 0.002).abs() < 1e-12);
+    }
+
+    #[test]
+    fn spot_position_test() {
+        let balance = |coin: &str, total: &str, entry_ntl: &str| UserTokenBalance {
+            coin: coin.to_string(),
+            hold: "0.0".to_string(),
+            total: total.to_string(),
+            entry_ntl: entry_ntl.to_string(),
+        };
+        let balances = vec![balance("USDC", "500", "0"), balance("PURR", "40", "10")];
+        assert_eq!(spot_position(&balances, "PURR"), (40.0, 0.25));
+        assert_eq!(spot_position(&balances[..1], "PURR"), (0.0, 0.0));
+    }
+
+    #[test]
+    fn inline_fills_test() {
+        let mut inline = InlineFills::default();
+        inline.acknowledge(7, 1.0);
+        assert!(inline.confirm(7, 0.4));
+        assert_eq!(inline.pending(), 1);
+        assert!(inline.confirm(7, 0.6));
+        assert_eq!(inline.pending(), 0);
+        assert!(!inline.confirm(8, 1.0));
+    }
+
+    #[test]
+    fn dedup_test() {
+        let mut deduper = FillDeduper::new(2);
+        assert!(deduper.insert(&fill("B", 1.0, 1, 10)));
+        assert!(!deduper.insert(&fill("B", 1.0, 1, 10)));
+        assert!(deduper.insert(&fill("B", 1.0, 2, 10)));
+        assert!(deduper.insert(&fill("B", 1.0, 3, 10)));
+
+        // The first fill has been evicted from the window
+        assert!(deduper.insert(&fill("B", 1.0, 1, 10)));
+
+        let mut disabled = FillDeduper::new(0);
+        assert!(disabled.insert(&fill("B", 1.0, 1, 10)));
+        assert!(disabled.insert(&fill("B", 1.0, 1, 10)));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let mut tracker = RoundTripTracker::default();
+        // Bought 99 bps under the reference and sold at it after it rose by 2: 1 of
+        // edge and 2 of drift
+        assert_eq!(tracker.apply(0.5, 100.0, 101.0), None);
+        assert_eq!(tracker.apply(0.5, 100.0, 101.0), None);
+        let round_trip = tracker.apply(-1.0, 103.0, 103.0).unwrap();
+        assert!(round_trip.is_long);
+        assert!((round_trip.size - 1.0).abs() < 1e-12);
+        assert!((round_trip.entry_diff_bps + 99.0099).abs() < 1e-4);
+        assert!(round_trip.exit_diff_bps.abs() < 1e-12);
+        assert!((round_trip.edge_pnl - 1.0).abs() < 1e-12);
+        assert!((round_trip.drift_pnl - 2.0).abs() < 1e-12);
+
+        // Flipping through flat closes the short and opens a long
+        assert_eq!(tracker.apply(-1.0, 100.0, 100.0), None);
+        let round_trip = tracker.apply(2.0, 99.0, 100.0).unwrap();
+        assert!(!round_trip.is_long);
+        assert!((round_trip.edge_pnl - 1.0).abs() < 1e-12);
+        assert!(round_trip.drift_pnl.abs() < 1e-12);
+        let capture = tracker.capture();
+        assert_eq!(capture.round_trips, 2);
+        assert!((capture.edge_pnl - 2.0).abs() < 1e-12);
+
+        // A position taken over has no entry to attribute against
+        tracker.reset(1.0);
+        assert_eq!(tracker.apply(-1.0, 105.0, 100.0), None);
+        assert_eq!(tracker.capture(), capture);
+    }
+
+    #[test]
+    fn inventory_test() {
+        let mut inventory = Inventory::default();
+        assert!(inventory.apply(1.0, 100.0).abs() < 1e-12);
+        assert!(inventory.apply(1.0, 102.0).abs() < 1e-12);
+        assert!((inventory.position() - 2.0).abs() < 1e-12);
+        assert!((inventory.avg_entry_px() - 101.0).abs() < 1e-12);
+
+        // Reducing keeps the entry
+        assert!((inventory.apply(-0.5, 105.0) - 2.0).abs() < 1e-12);
+        assert!((inventory.avg_entry_px() - 101.0).abs() < 1e-12);
+
+        // Flipping realizes the rest and restarts the entry at the fill price
+        assert!((inventory.apply(-2.5, 99.0) + 3.0).abs() < 1e-12);
+        assert!((inventory.position() + 1.0).abs() < 1e-12);
+        assert!((inventory.avg_entry_px() - 99.0).abs() < 1e-12);
+
+        assert!((inventory.apply(1.0, 98.0) - 1.0).abs() < 1e-12);
+        assert!(inventory.position().abs() < 1e-12);
+        assert!(inventory.avg_entry_px().abs() < 1e-12);
+    }
+
+    #[test]
+    fn side_vwap_test() {
+        let mut vwap = SideVwap::default();
+        assert_eq!(vwap.average(), None);
+        vwap.add(100.0, 1.0);
+        vwap.add(103.0, 2.0);
+        assert!((vwap.average().unwrap() - 102.0).abs() < 1e-12);
+    }
+}
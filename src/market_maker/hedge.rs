@@ -0,0 +1,59 @@
+/// Accumulates the delta from fills that hasn't been offset on the hedge venue yet,
+/// and only releases a hedge once it grows past `threshold`. Small fills are thereby
+/// batched into fewer, larger hedge orders.
+pub(super) struct HedgeBatcher {
+    threshold: f64,
+    unhedged_delta: f64,
+}
+
+impl HedgeBatcher {
+    pub(super) const fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            unhedged_delta: 0.0,
+        }
+    }
+
+    /// Adds a signed fill (positive for buys) and returns the signed size of the
+    /// hedge order to send, if the unhedged delta now exceeds the threshold.
+    pub(super) fn on_fill(&mut self, signed_size: f64) -> Option<f64> {
+        self.unhedged_delta += signed_size;
+        if self.unhedged_delta.abs() > self.threshold {
+            let hedge = -self.unhedged_delta;
+            self.unhedged_delta = 0.0;
+            Some(hedge)
+        } else {
+            None
+        }
+    }
+
+    /// Adds delta that a released hedge didn't offset, without releasing it again.
+    pub(super) fn carry(&mut self, signed_size: f64) {
+        self.unhedged_delta += signed_size;
+    }
+
+    pub(super) const fn unhedged_delta(&self) -> f64 {
+        self.unhedged_delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hedge_batching_test() {
+        let mut batcher = HedgeBatcher::new(0.01);
+        assert_eq!(batcher.on_fill(0.004), None);
+        assert_eq!(batcher.on_fill(0.004), None);
+        assert!((batcher.unhedged_delta() - 0.008).abs() < 1e-12);
+
+        let hedge = batcher.on_fill(0.004).unwrap();
+        assert!((hedge + 0.012).abs() < 1e-12);
+        assert!(batcher.unhedged_delta().abs() < 1e-12);
+
+        // Offsetting fills net out without hedging
+        assert_eq!(batcher.on_fill(-0.006), None);
+        assert_eq!(batcher.on_fill(0.006), None);
+    }
+}
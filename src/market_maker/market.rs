@@ -0,0 +1,101 @@
+use serde::Deserialize;
+
+use super::PositionHeadroom;
+use crate::{SpotMeta, TokenInfo};
+
+/// Kind of Hyperliquid market traded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarketKind {
+    /// A perpetual, where the position may go short and pays funding.
+    #[default]
+    Perp,
+    /// A spot pair, where the position is the balance of the base token. It can't go
+    /// short and there is no funding or reduce-only.
+    Spot,
+}
+
+impl MarketKind {
+    /// Most decimals a price may have, of which the size decimals of the asset are
+    /// taken off.
+    #[must_use]
+    pub const fn max_decimals(self) -> u32 {
+        match self {
+            Self::Perp => 6,
+            Self::Spot => 8,
+        }
+    }
+
+    /// Reduce-only flag to send, dropped on spot where the exchange doesn't take it.
+    /// An order meant to reduce still can't add to the balance there, it sells at most
+    /// what is held.
+    pub(super) const fn reduce_only(self, reduce_only: bool) -> bool {
+        matches!(self, Self::Perp) && reduce_only
+    }
+
+    /// Headroom for a trade, with spot sells capped at the balance held since a spot
+    /// position can't go below zero.
+    pub(super) fn headroom(
+        self,
+        position: f64,
+        cap: f64,
+        is_buy: bool,
+        size: f64,
+    ) -> PositionHeadroom {
+        let cap = if self == Self::Spot && !is_buy {
+            0.0
+        } else {
+            cap
+        };
+        PositionHeadroom::new(position, cap, is_buy, size)
+    }
+}
+
+/// Spot pair named `name`, either by its name on the wire such as `@107` or
+/// `PURR/USDC`, or by its tokens as `BASE/QUOTE`. Returns the wire name and the base
+/// token.
+pub(super) fn spot_pair<'a>(
+    spot_meta: &'a SpotMeta,
+    name: &str,
+) -> Option<(&'a str, &'a TokenInfo)> {
+    let token = |index: usize| spot_meta.tokens.iter().find(|token| token.index == index);
+    spot_meta.universe.iter().find_map(|pair| {
+        let base = token(pair.tokens[0])?;
+        let quote = token(pair.tokens[1])?;
+        let matches =
+            pair.name == name || format!("{}/{}", base.name, quote.name).eq_ignore_ascii_case(name);
+        matches.then_some((pair.name.as_str(), base))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spot_pair_test() {
+        let spot_meta: SpotMeta = serde_json::from_str(
+            r#"{"universe":[{"tokens":[1,0],"name":"PURR/USDC","index":0,"isCanonical":true},{"tokens":[150,0],"name":"@107","index":107,"isCanonical":false}],"tokens":[{"name":"USDC","szDecimals":8,"weiDecimals":8,"index":0,"isCanonical":true},{"name":"PURR","szDecimals":0,"weiDecimals":5,"index":1,"isCanonical":true},{"name":"HYPE","szDecimals":2,"weiDecimals":8,"index":150,"isCanonical":false}]}"#,
+        )
+        .unwrap();
+        let (name, base) = spot_pair(&spot_meta, "HYPE/USDC").unwrap();
+        assert_eq!(
+            (name, base.name.as_str(), base.sz_decimals),
+            ("@107", "HYPE", 2)
+        );
+        assert_eq!(spot_pair(&spot_meta, "@107").unwrap().0, "@107");
+        assert_eq!(spot_pair(&spot_meta, "PURR/USDC").unwrap().1.name, "PURR");
+        assert!(spot_pair(&spot_meta, "BTC/USDC").is_none());
+    }
+
+    #[test]
+    fn spot_headroom_test() {
+        // A spot sell can't go below the balance held, a perp one may go short
+        let spot = MarketKind::Spot.headroom(0.5, 2.0, false, 1.0);
+        assert!((spot.clamped_size - 0.5).abs() < 1e-12);
+        let perp = MarketKind::Perp.headroom(0.5, 2.0, false, 1.0);
+        assert!((perp.clamped_size - 1.0).abs() < 1e-12);
+        assert!(!MarketKind::Spot.reduce_only(true));
+        assert!(MarketKind::Perp.reduce_only(true));
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "metrics")]
+use super::FeedTask;
+use super::RejectCategory;
+
+/// Upper bounds in seconds of the order latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 9] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// Cumulative Prometheus histogram of the time from a signal to its order result.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl LatencyHistogram {
+    pub(super) fn observe(&mut self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct MetricValues {
+    pub(super) position: f64,
+    pub(super) realized_pnl: f64,
+    pub(super) unrealized_pnl: f64,
+    pub(super) orders: u64,
+    pub(super) fills: u64,
+    /// Order rejections, indexed by [`RejectCategory`].
+    pub(super) rejects: [u64; RejectCategory::ALL.len()],
+    pub(super) z_score: f64,
+    pub(super) reference_age: Option<Duration>,
+    pub(super) order_rate_utilization: f64,
+    pub(super) order_latency: LatencyHistogram,
+    pub(super) paused: bool,
+    pub(super) halted_until: Option<Instant>,
+}
+
+/// Counters and gauges of one market maker, updated from its event loop and
+/// rendered in the Prometheus text format.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    asset: String,
+    values: Arc<Mutex<MetricValues>>,
+}
+
+impl Metrics {
+    pub(super) fn new(asset: &str) -> Self {
+        Self {
+            asset: asset.to_string(),
+            values: Arc::default(),
+        }
+    }
+
+    pub(super) fn record(&self, update: impl FnOnce(&mut MetricValues)) {
+        update(&mut self.values.lock().unwrap());
+    }
+
+    pub(super) fn values(&self) -> MetricValues {
+        *self.values.lock().unwrap()
+    }
+
+    /// Current values in the Prometheus text exposition format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the metrics mutex is poisoned.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let values = self.values();
+        #[allow(clippy::cast_precision_loss)]
+        let metrics = [
+            ("position", "gauge", "Tracked position", values.position),
+            (
+                "realized_pnl",
+                "gauge",
+                "Realized profit in quote currency",
+                values.realized_pnl,
+            ),
+            (
+                "orders_total",
+                "counter",
+                "Order attempts",
+                values.orders as f64,
+            ),
+            (
+                "fills_total",
+                "counter",
+                "Fills booked",
+                values.fills as f64,
+            ),
+            ("z_score", "gauge", "Latest z-score", values.z_score),
+            (
+                "reference_price_age_seconds",
+                "gauge",
+                "Time since the last Binance price, NaN before the first",
+                values
+                    .reference_age
+                    .map_or(f64::NAN, |age| age.as_secs_f64()),
+            ),
+            (
+                "order_rate_utilization",
+                "gauge",
+                "Share of the order rate limiter burst in use",
+                values.order_rate_utilization,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            // Writing to a String can't fail
+            let _ = writeln!(out, "# HELP market_maker_{name} {help}.");
+            let _ = writeln!(out, "# TYPE market_maker_{name} {kind}");
+            let _ = writeln!(
+                out,
+                "market_maker_{name}{{asset=\"{}\"}} {value}",
+                self.asset
+            );
+        }
+
+        let asset = &self.asset;
+        let name = "market_maker_rejects_total";
+        let _ = writeln!(out, "# HELP {name} Order rejections by category.");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for (count, category) in values.rejects.iter().zip(RejectCategory::ALL) {
+            let _ = writeln!(
+                out,
+                "{name}{{asset=\"{asset}\",category=\"{}\"}} {count}",
+                category.as_str()
+            );
+        }
+
+        let latency = values.order_latency;
+        let name = "market_maker_order_latency_seconds";
+        let _ = writeln!(out, "# HELP {name} Time from a signal to its order result.");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (count, bound) in latency.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{asset=\"{asset}\",le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{asset=\"{asset}\",le=\"+Inf\"}} {}",
+            latency.count
+        );
+        let _ = writeln!(out, "{name}_sum{{asset=\"{asset}\"}} {}", latency.sum);
+        let _ = writeln!(out, "{name}_count{{asset=\"{asset}\"}} {}", latency.count);
+        out
+    }
+}
+
+/// Serve `metrics` at `/metrics` on `port` until the returned task is stopped.
+#[cfg(feature = "metrics")]
+pub(super) fn serve(port: u16, metrics: Metrics) -> FeedTask {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tracing::{error, info, warn};
+
+    FeedTask::spawn(move |mut cancellation| async move {
+        let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Error binding the metrics server to port {port}: {e}");
+                return;
+            }
+        };
+        info!("Serving metrics on port {port}");
+
+        loop {
+            let mut stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Error accepting a metrics connection: {e}");
+                        continue;
+                    }
+                },
+                () = cancellation.cancelled() => break,
+            };
+            let mut request = [0; 1024];
+            let read = tokio::time::timeout(Duration::from_secs(1), stream.read(&mut request));
+            let Ok(Ok(len)) = read.await else {
+                continue;
+            };
+            let response = respond(&request[..len], &metrics);
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                warn!("Error writing metrics response: {e}");
+            }
+        }
+    })
+}
+
+#[cfg(feature = "metrics")]
+fn respond(request: &[u8], metrics: &Metrics) -> String {
+    let (status, body) = if request.starts_with(b"GET /metrics ") {
+        ("200 OK", metrics.render())
+    } else {
+        ("404 Not Found", String::new())
+    };
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let metrics = Metrics::new("BTC");
+        metrics.record(|values| {
+            values.position = 0.002;
+            values.fills = 3;
+            values.rejects[RejectCategory::Margin as usize] = 2;
+        });
+        let rendered = metrics.render();
+        assert!(rendered.contains("# TYPE market_maker_fills_total counter\n"));
+        assert!(rendered.contains("market_maker_position{asset=\"BTC\"} 0.002\n"));
+        assert!(rendered.contains("market_maker_fills_total{asset=\"BTC\"} 3\n"));
+        assert!(
+            rendered.contains("market_maker_rejects_total{asset=\"BTC\",category=\"margin\"} 2\n")
+        );
+        assert!(
+            rendered.contains("market_maker_rejects_total{asset=\"BTC\",category=\"auth\"} 0\n")
+        );
+        assert!(rendered.contains("market_maker_reference_price_age_seconds{asset=\"BTC\"} NaN\n"));
+    }
+
+    #[test]
+    fn latency_histogram_test() {
+        let metrics = Metrics::new("BTC");
+        metrics.record(|values| {
+            values.order_latency.observe(Duration::from_millis(20));
+            values.order_latency.observe(Duration::from_secs(3));
+        });
+        let rendered = metrics.render();
+        let name = "market_maker_order_latency_seconds";
+        assert!(rendered.contains(&format!("# TYPE {name} histogram\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{asset=\"BTC\",le=\"0.01\"}} 0\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{asset=\"BTC\",le=\"0.025\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{asset=\"BTC\",le=\"2.5\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{asset=\"BTC\",le=\"+Inf\"}} 2\n")));
+        assert!(rendered.contains(&format!("{name}_count{{asset=\"BTC\"}} 2\n")));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn respond_test() {
+        let metrics = Metrics::new("BTC");
+        let response = respond(b"GET /metrics HTTP/1.1\r\n\r\n", &metrics);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&metrics.render()));
+        assert!(respond(b"GET / HTTP/1.1\r\n\r\n", &metrics).starts_with("HTTP/1.1 404"));
+    }
+}
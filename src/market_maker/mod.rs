@@ -0,0 +1,7036 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+
+use chrono::{NaiveTime, Utc};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::H160,
+};
+use gxhash::{HashMap, HashMapExt};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, field, info, info_span, warn, Instrument, Span};
+
+use crate::{
+    helpers::uuid_to_hex_string, truncate_float, BaseUrl, BinanceTrader, ClientCancelRequest,
+    ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, ExchangeClient,
+    ExchangeDataStatus, ExchangeResponseStatus, InfoClient, L2BookData, Message, Meta,
+    OpenOrdersResponse, SpotMeta, Subscription, TradeInfo, EPSILON,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use uuid::Uuid;
+
+#[cfg(feature = "admin")]
+mod admin;
+mod backtest;
+mod book;
+mod breaker;
+mod budget;
+mod builder;
+mod config;
+mod control;
+mod decisions;
+mod error;
+mod execution;
+mod exposure;
+mod fair_value;
+mod feed;
+mod fills;
+mod hedge;
+mod market;
+mod metrics;
+mod multi;
+mod observer;
+mod orders;
+mod protective;
+mod quote;
+mod rate_limit;
+mod reconcile;
+mod reconnect;
+mod reject;
+mod replay;
+mod session;
+mod shutdown;
+mod signal;
+mod sim;
+mod state;
+mod stats;
+mod trade_log;
+mod wallet;
+
+pub use backtest::{Backtest, BacktestReport, BacktestRow, BacktestTrade};
+pub use book::{MidSource, Subscriptions};
+pub use breaker::CircuitBreaker;
+pub use builder::InputBuilder;
+pub use config::Config;
+pub use control::{ControlCommand, ControlHandle};
+pub use decisions::{Decision, DecisionOutcome, PositionHeadroom, SizeMode, TradeIntent};
+pub use error::MarketMakerError;
+pub use execution::{ExecutionStyle, TimeInForce};
+pub use exposure::ExposureTracker;
+pub use fair_value::{
+    BlendedFairValue, FairValueSource, MedianReferencePrice, ReferencePrice, SharedReferencePrice,
+};
+pub use feed::{
+    run_with_reconnect, BinancePriceSource, FeedCancellation, FeedTask, ReferenceVenue,
+};
+pub use fills::{Exposure, MarkSource, SpreadCapture};
+pub use market::MarketKind;
+pub use metrics::Metrics;
+pub use multi::{AssetConfig, MultiAssetMarketMaker};
+pub use observer::MarketMakerObserver;
+pub use orders::{OrderOutcome, StaleOrderPolicy};
+pub use quote::StrategyMode;
+pub use rate_limit::TokenBucket;
+pub use reconnect::{Backoff, ReconnectLimiter};
+pub use reject::RejectCategory;
+pub use replay::{ReplayFeed, ReplayFrame, ReplaySource};
+pub use signal::{EntrySignal, VolatilityScaling, ZThresholdWindow};
+pub use sim::FillModel;
+pub use state::MarketMakerState;
+pub use stats::{StatMode, StrategyStats, TradeVwap, WindowMode, WindowPrior};
+pub use wallet::{
+    load_wallet, load_wallet_file, parse_private_key, PRIVATE_KEY_FILE_VAR, PRIVATE_KEY_VAR,
+};
+
+/// Smallest order value the exchange accepts, in quote currency.
+const MIN_ORDER_NOTIONAL: f64 = 10.0;
+
+/// Reduce-only orders [`MarketMaker::flatten`] sends before giving up.
+pub const FLATTEN_ATTEMPTS: usize = 5;
+
+/// Attempts at each Hyperliquid subscription before giving up, on start and on
+/// every resubscription.
+pub const SUBSCRIBE_ATTEMPTS: u32 = 5;
+
+/// Wait between failed subscription attempts.
+const SUBSCRIBE_BACKOFF: Backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(16));
+
+/// How often the event loop checks for the end or start of the trading session.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Prices from a malformed message may parse as NaN, infinite, zero or negative.
+fn is_valid_price(px: f64) -> bool {
+    px.is_finite() && px > 0.0
+}
+
+/// Basis points in one.
+const BPS: f64 = 10_000.0;
+
+/// Signals and thresholds are kept in bps so they compare across assets, and only
+/// turned into a price distance when an order is built: `bps` of `mid` in price.
+fn bps_to_price(mid: f64, bps: f64) -> f64 {
+    mid * bps_to_fraction(bps)
+}
+
+/// Distance from `from` to `to` in bps of `from`.
+fn price_to_bps(from: f64, to: f64) -> f64 {
+    (to - from) / from * BPS
+}
+
+/// A fractional difference, e.g. a log price diff, in bps.
+fn fraction_to_bps(fraction: f64) -> f64 {
+    fraction * BPS
+}
+
+fn bps_to_fraction(bps: f64) -> f64 {
+    bps / BPS
+}
+
+// Independent switches, not states of one machine
+#[allow(clippy::struct_excessive_bools)]
+pub struct Input {
+    pub asset: String,
+    /// Hyperliquid API the info and exchange clients connect to.
+    pub base_url: BaseUrl,
+    /// Trade a perp, or a spot pair with `asset` naming it as `BASE/QUOTE` or as it
+    /// is named on the wire, e.g. `@107`.
+    pub market_kind: MarketKind,
+    /// Number of diffs in the rolling window (previously fixed at 100).
+    pub window_size: usize,
+    /// Bound the window by `window_size` diffs, or by their age.
+    pub window_mode: WindowMode,
+    /// Estimate the mean and stddev over the window or as exponentially weighted
+    /// averages.
+    pub stat_mode: StatMode,
+    /// Z-score beyond which the strategy trades (previously fixed at 2.0).
+    pub z_threshold: f64,
+    /// Don't trade while the correlation of the Hyperliquid and reference price
+    /// returns over the last `window_size` ticks is below this, as a decoupled
+    /// market is diverging rather than mispriced. `None` trades regardless.
+    pub min_correlation: Option<f64>,
+    /// Base size of each immediate trade, fixed or a fraction of the account's
+    /// collateral.
+    pub size_mode: SizeMode,
+    /// Query the account's withdrawable collateral this often in
+    /// [`SizeMode::FractionOfEquity`].
+    pub balance_refresh: Duration,
+    pub target_liquidity: f64,
+    pub half_spread: u16,
+    /// How far past the mid immediate orders are priced to cross, in basis points.
+    pub max_bps_diff: u16,
+    /// Take `max_bps_diff` as the widest sane deviation from the rolling mean too:
+    /// anything wider is skipped as bad data or decoupled markets instead of traded.
+    pub max_bps_diff_band: bool,
+    pub max_absolute_position_size: f64,
+    /// Decimals of the asset's prices. [`MarketMaker::new`] derives them from the
+    /// exchange metadata instead.
+    pub decimals: u32,
+    /// Decimals of the asset's order sizes. [`MarketMaker::new`] takes the exchange's
+    /// `szDecimals` for the asset instead.
+    pub size_decimals: u32,
+    pub wallet: LocalWallet,
+    /// When set, subscribe to the L2 book and refuse to trade while either side has no
+    /// liquidity within this many bps of the mid. Without a book, trading is allowed.
+    pub one_sided_book_band_bps: Option<f64>,
+    /// When set, subscribe to the L2 book and price marketable orders this many bps
+    /// past the best opposite price, rather than `max_bps_diff` past the mid.
+    pub cross_buffer_bps: Option<f64>,
+    /// When set, fills accumulate into an unhedged delta and a hedge is only released
+    /// once its absolute value exceeds this size.
+    pub hedge_delta_threshold: Option<f64>,
+    /// Offset every released hedge with a market order on Binance, so the two legs
+    /// together are delta neutral. Without `hedge_delta_threshold` every fill is
+    /// hedged. Paper trading and dry runs book the hedge at the reference price
+    /// without sending it.
+    pub hedge_trader: Option<BinanceTrader>,
+    /// When set, trade from the first tick using this prior, blended with live samples
+    /// until the window is full, instead of waiting for a full window.
+    pub window_prior: Option<WindowPrior>,
+    /// Number of recent fills remembered to drop redelivered duplicates. Zero disables.
+    pub fill_dedup_window: usize,
+    /// When set, a signal only fires once two consecutive reference updates both produce
+    /// it and differ from each other by at most this many bps.
+    pub reference_confirm_bps: Option<f64>,
+    /// Upper bound on the whole shutdown sequence.
+    pub shutdown_timeout: Duration,
+    /// Wallet to switch order signing to if the primary one fails to authenticate.
+    pub standby_wallet: Option<LocalWallet>,
+    /// How many bps the passive quote center moves per unit of z-score, against the
+    /// predicted drift. Zero quotes symmetrically around the mid.
+    pub signal_skew_weight: f64,
+    /// Keep a reduce-only stop-loss trigger order resting this many bps from the
+    /// position's average entry.
+    pub protective_stop_bps: Option<f64>,
+    /// Keep a reduce-only take-profit trigger order resting this many bps from the
+    /// position's average entry.
+    pub protective_take_profit_bps: Option<f64>,
+    /// Move of the average entry, in bps, after which protective orders are re-placed.
+    pub protective_reprice_bps: f64,
+    /// Flatten the position with reduce-only IOC orders once its unrealized loss
+    /// passes this many bps of its notional. Checked by the bot on every price update,
+    /// unlike the protective orders resting on the exchange. `None` never stops out.
+    pub stop_loss_bps: Option<f64>,
+    /// Close the position with reduce-only IOC orders once its unrealized profit passes
+    /// this many bps of its notional, rather than waiting for an opposite signal. Wins
+    /// over a signal to add to the position on the same tick. `None` never takes
+    /// profit.
+    pub take_profit_bps: Option<f64>,
+    /// Neither `stop_loss_bps` nor `take_profit_bps` fires until the position has been
+    /// held this long since the last fill that opened or added to it, so one noisy
+    /// mark can't flush a position the tick after it was opened. Protective orders on
+    /// the exchange aren't delayed. Zero disables.
+    pub min_hold: Duration,
+    /// Flatten a position held this long regardless of its profit, a time stop for
+    /// when the spread doesn't revert. Held time counts from the last fill that
+    /// opened, added to or flipped the position, so adding to it restarts the clock.
+    /// A position inherited at startup counts from when it is first checked.
+    pub max_hold_time: Option<Duration>,
+    /// After one of our fills, defer new aggressive trades for this long so the signal
+    /// can re-stabilize after our own impact. Zero disables.
+    pub post_fill_quiet: Duration,
+    /// When set, keep a second, longer window of diffs and only trade when its z-score
+    /// also exceeds `long_z_threshold` in the same direction.
+    pub long_window_size: Option<usize>,
+    /// Z-score threshold for the long window.
+    pub long_z_threshold: f64,
+    /// Number of realized returns kept for the rolling Sharpe and Sortino ratios.
+    pub returns_window: usize,
+    /// Aggregate realized `PnL` into returns over this interval. `None` uses one return
+    /// per closing fill.
+    pub return_interval: Option<Duration>,
+    /// Log [`MarketMaker::stats`] this often. `None` never logs them.
+    pub stats_interval: Option<Duration>,
+    /// Risk budget shared with the other assets of a pool, consulted before every trade.
+    pub exposure: Option<Arc<Mutex<ExposureTracker>>>,
+    /// Time of day overrides of the z-score threshold, checked in order.
+    pub z_threshold_schedule: Vec<ZThresholdWindow>,
+    /// UTC time of day to stop opening positions and flatten with reduce-only orders
+    /// each day. `None` trades around the clock.
+    pub session_end: Option<NaiveTime>,
+    /// UTC time of day to resume trading after `session_end`. `None` stays parked
+    /// until restarted.
+    pub session_start: Option<NaiveTime>,
+    /// Scale the z-score threshold with recent diff volatility. `None` keeps it constant.
+    pub volatility_scaling: Option<VolatilityScaling>,
+    /// Multiply the z-score threshold by this right after a losing trade, when the
+    /// model may be temporarily wrong, so the bot doesn't over-trade. One disables.
+    pub loss_penalty: f64,
+    /// Half-life of the decay of `loss_penalty` back to one.
+    pub loss_penalty_half_life: Duration,
+    /// Number of recent decisions kept in memory for inspection.
+    pub decision_history: usize,
+    /// Minimum time between the position and order reconciliations run after reconnects.
+    pub reconcile_min_interval: Duration,
+    /// Enter when the diff is this many bps away from the rolling mean, instead of a
+    /// multiple of the rolling standard deviation. The band is fixed: the z-threshold
+    /// schedule, volatility scaling and stddev floors don't apply to it, the loss
+    /// penalty does.
+    pub entry_band_bps: Option<f64>,
+    /// Don't compute a z-score while the rolling standard deviation is below this many
+    /// bps, where tiny diffs would make extreme scores out of noise.
+    pub min_stddev_bps: Option<f64>,
+    /// Likewise below this fraction of the rolling mean's magnitude.
+    pub min_stddev_mean_fraction: Option<f64>,
+    /// Resubscribe on a new connection if no Hyperliquid message arrives for this long,
+    /// in case the connection went half-open. `None` waits indefinitely.
+    pub message_timeout: Option<Duration>,
+    /// Log that the bot is alive this often, with the time of the last tick, the
+    /// position, the profit and the age of both prices. `None` never does.
+    pub heartbeat_interval: Option<Duration>,
+    /// Log only one in this many ticks that don't signal a trade at debug, so a fast
+    /// mid feed doesn't drown the log. 1 logs every tick; signals, orders and fills
+    /// are logged in full either way.
+    pub log_sample_rate: usize,
+    /// Only act on a signal once it has stayed beyond the threshold on the same side
+    /// for this many consecutive ticks. 1 acts on the first.
+    pub confirm_ticks: usize,
+    /// Drop a diff more than this many rolling standard deviations from the rolling
+    /// mean as bad data, keeping it out of the window and the decision. `None` accepts
+    /// every diff.
+    pub outlier_k: Option<f64>,
+    /// Accept outliers again once this many arrive in a row, as a regime shift rather
+    /// than a garbled tick.
+    pub outlier_confirm_ticks: usize,
+    /// Reconnect rate limit shared by all feeds. Trading pauses during its cooldown.
+    pub reconnect_limiter: Option<Arc<Mutex<ReconnectLimiter>>>,
+    /// Handle orders left resting by a previous run on startup, e.g. cancel them all
+    /// to start from a clean slate. `None` leaves them alone.
+    pub stale_orders: Option<StaleOrderPolicy>,
+    /// Cap each quote to this fraction of the same-side depth at its price or better.
+    /// Quotes use `target_liquidity` while no book has been received.
+    pub quote_depth_fraction: Option<f64>,
+    /// Log the position cap headroom before each trade.
+    pub log_position_headroom: bool,
+    /// Warn about orders whose result arrives more than this long after their signal.
+    pub latency_warn_threshold: Option<Duration>,
+    /// Compute the z-score against this fair value instead of the Binance price, e.g.
+    /// a [`MedianReferencePrice`] over several venues.
+    pub fair_value: Option<Box<dyn FairValueSource>>,
+    /// Simulate immediate orders with this model instead of sending them.
+    pub dry_run: Option<FillModel>,
+    pub execution_style: ExecutionStyle,
+    /// Only add liquidity when entering: with [`ExecutionStyle::PostThenCross`], what
+    /// hasn't filled after `wait` is cancelled instead of crossed, and a post-only
+    /// order that would have crossed is a no-fill. Exits and flattens still cross.
+    pub post_only: bool,
+    /// Maker rebate in bps, credited on maker fills that report no fee of their own
+    /// and counted toward the edge of both legs of a post-only round trip.
+    pub maker_rebate_bps: f64,
+    /// Don't trade on a Binance price older than this.
+    pub max_price_age: Duration,
+    /// Scale the trade size with how far the signal is past its threshold, up to this
+    /// multiple of the base trade size. 1.0 always trades the base size.
+    pub max_size_multiplier: f64,
+    /// Skew the z-score threshold toward flat inventory: with the position at a
+    /// fraction `f` of `max_absolute_position_size`, the threshold on the side growing
+    /// the position is multiplied by `1 + inventory_skew * f` and the other side's by
+    /// `1 - inventory_skew * f`. 0.0 disables the skew.
+    pub inventory_skew: f64,
+    /// Never send orders; simulate full fills at the limit price and book them like
+    /// real fills. Uses `dry_run` for the simulated responses, or a model that never
+    /// rejects if it is unset.
+    pub paper_trading: bool,
+    /// Book paper fills this long after the order, as real execution lags the
+    /// signal. Backtests wait it out too.
+    pub simulated_latency: Duration,
+    /// Book paper fills this many basis points worse than the order price, higher for
+    /// buys and lower for sells. 0.0 fills at the order price.
+    pub simulated_slippage_bps: f64,
+    /// Minimum time between consecutive trades while the signal stays extreme.
+    pub min_trade_interval: Duration,
+    /// Cancel orders that unexpectedly rest on the book instead of filling once they
+    /// are older than this.
+    pub resting_order_timeout: Option<Duration>,
+    /// Refuse new orders while this many are open: quotes, passive orders and orders
+    /// resting after `place_order`. Reduce-only exits are always sent. `None` doesn't
+    /// limit them.
+    pub max_open_orders: Option<usize>,
+    /// Time in force of immediate orders. Anything but IOC may rest, see
+    /// `resting_order_timeout`.
+    pub order_tif: TimeInForce,
+    pub strategy: StrategyMode,
+    /// Price updates to observe after startup before trading, independent of how
+    /// soon the window is full.
+    pub warmup_ticks: usize,
+    /// Refuse any immediate order worth more than this in quote currency.
+    pub max_order_notional: f64,
+    /// Stop placing orders once fills opening or adding to the position are worth
+    /// this much in quote currency since UTC midnight. Exits don't count and are
+    /// always sent.
+    pub max_daily_notional: Option<f64>,
+    /// Taker fee of immediate orders in bps. Signals whose edge doesn't cover the fee
+    /// on both the entry and the exit aren't traded.
+    pub taker_fee_bps: f64,
+    /// Only trade when the diff is at least this many bps from its rolling mean, however
+    /// large the z-score. 0.0 leaves the z-score to decide alone.
+    pub min_edge_bps: f64,
+    /// Query the asset's hourly funding rate this often and only trade when the
+    /// signal's edge covers the funding expected over `expected_holding_time`. `None`
+    /// ignores funding.
+    pub funding_refresh: Option<Duration>,
+    /// How long a position is expected to be held, for the funding check.
+    pub expected_holding_time: Duration,
+    /// Resend an immediate order up to this many times after a failure the exchange
+    /// can't have acted on: a refused connection or a rate limit. Ambiguous failures
+    /// such as timeouts are never retried, a resent IOC order could fill twice.
+    /// Margin and other rejections fail fast.
+    pub max_order_retries: u32,
+    /// Delay before the first retry, doubling up to 16 times this. The event loop
+    /// waits while the order is retried.
+    pub order_retry_delay: Duration,
+    /// Skip orders, including retries, that would exceed this client-side rate.
+    pub order_rate_limit: Option<TokenBucket>,
+    /// Wait a random fraction of this before sending each order, so bots running
+    /// side by side don't send in bursts. Zero sends straight away.
+    pub order_jitter: Duration,
+    /// Seed of the jitter, for reproducible paper runs and backtests. Seeded from
+    /// entropy if unset.
+    pub jitter_seed: Option<u64>,
+    /// Stop sending immediate orders for a while after too many consecutive order
+    /// errors.
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Feed the mid is taken from. Book-derived mids subscribe to `L2Book`.
+    pub mid_source: MidSource,
+    /// Hyperliquid feeds to open, see [`Subscriptions::signal_only`] for watching
+    /// without trading.
+    pub subscriptions: Subscriptions,
+    /// Re-read the prices right before sending and skip the trade if the diff is no
+    /// longer on the signal's side of its mean, so a signal gone stale while the
+    /// checks ran isn't traded into.
+    pub recheck_edge: bool,
+    /// Venue whose feed writes `binance_price`. The caller spawns the feed, this only
+    /// names it in logs.
+    pub reference_venue: ReferenceVenue,
+    /// Price the open position is marked at for [`MarketMaker::unrealized_pnl`].
+    pub mark_source: MarkSource,
+    /// Stream of the Binance feed, which the caller spawns with
+    /// [`BinanceFeed::with_price_source`](crate::BinanceFeed::with_price_source).
+    /// Only named in logs here.
+    pub binance_price_source: BinancePriceSource,
+    /// Veto a trade when the top of the book leans against it by more than this
+    /// imbalance, between 0 and 1: a sell when bids outweigh asks, a buy when asks
+    /// outweigh bids. Subscribes to `L2Book`.
+    pub imbalance_threshold: Option<f64>,
+    /// Book levels per side the imbalance is measured over.
+    pub imbalance_levels: usize,
+    /// Append a CSV row for every order attempt and fill to this file.
+    pub trade_log_path: Option<PathBuf>,
+    /// Save the rolling window, EWMA, position and profit to this JSON file on
+    /// shutdown, and resume from it on start.
+    pub state_path: Option<PathBuf>,
+    /// Start fresh instead when the saved state is older than this.
+    pub state_max_age: Duration,
+    /// Notified of signals, orders, fills and halts.
+    pub observer: Option<Box<dyn MarketMakerObserver + Send>>,
+    /// Serve Prometheus metrics at `/metrics` on this port.
+    #[cfg(feature = "metrics")]
+    pub metrics_port: Option<u16>,
+    /// Serve `GET /state` and `POST /pause`, `/resume` and `/flatten` on this
+    /// address. Anyone who can reach it can control the bot, so bind it to a
+    /// loopback or private address.
+    #[cfg(feature = "admin")]
+    pub admin_addr: Option<std::net::SocketAddr>,
+}
+
+impl Input {
+    /// Rejects parameters the strategy can't run with. [`MarketMaker::new`] and
+    /// [`InputBuilder::build`] call it, so a bad combination fails at start-up rather
+    /// than as confusing behavior or rejected orders later.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MarketMakerError::InvalidInput`] describing the first violated
+    /// invariant.
+    #[allow(clippy::result_large_err)]
+    pub fn validate(&self) -> Result<(), MarketMakerError> {
+        if self.asset.is_empty() {
+            return Err(MarketMakerError::InvalidInput("asset is empty".to_string()));
+        }
+        self.validate_statistics()?;
+        if !(self.loss_penalty.is_finite() && self.loss_penalty >= 1.0) {
+            return Err(MarketMakerError::InvalidInput(format!(
+                "loss_penalty must be at least 1, got {}",
+                self.loss_penalty
+            )));
+        }
+        if self.half_spread > self.max_bps_diff {
+            return Err(MarketMakerError::InvalidInput(format!(
+                "half_spread of {} bps is wider than max_bps_diff of {}",
+                self.half_spread, self.max_bps_diff
+            )));
+        }
+        let (size_name, size) = match self.size_mode {
+            SizeMode::Fixed(size) => ("trade size", size),
+            SizeMode::FractionOfEquity(fraction) => ("equity fraction", fraction),
+        };
+        for (name, value) in [("z_threshold", self.z_threshold), (size_name, size)] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "{name} must be positive, got {value}"
+                )));
+            }
+        }
+        for (name, value) in [
+            (
+                "max_absolute_position_size",
+                self.max_absolute_position_size,
+            ),
+            ("simulated_slippage_bps", self.simulated_slippage_bps),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "{name} must not be negative, got {value}"
+                )));
+            }
+        }
+        for (name, decimals) in [
+            ("decimals", self.decimals),
+            ("size_decimals", self.size_decimals),
+        ] {
+            let max = self.market_kind.max_decimals();
+            if decimals > max {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "{name} must be at most {max}, got {decimals}"
+                )));
+            }
+        }
+        for (name, value) in [
+            ("stop_loss_bps", self.stop_loss_bps),
+            ("take_profit_bps", self.take_profit_bps),
+            ("cross_buffer_bps", self.cross_buffer_bps),
+            ("outlier_k", self.outlier_k),
+            ("max_daily_notional", self.max_daily_notional),
+        ] {
+            if let Some(value) = value.filter(|value| !value.is_finite() || *value <= 0.0) {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "{name} must be positive when set, got {value}"
+                )));
+            }
+        }
+        if !self.subscriptions.all_mids && self.mid_source == MidSource::AllMids {
+            return Err(MarketMakerError::InvalidInput(
+                "mid_source is AllMids but the AllMids subscription is off".to_string(),
+            ));
+        }
+        if self.post_only && !matches!(self.execution_style, ExecutionStyle::PostThenCross { .. }) {
+            return Err(MarketMakerError::InvalidInput(
+                "post_only needs ExecutionStyle::PostThenCross for how long entries rest"
+                    .to_string(),
+            ));
+        }
+        if !self.maker_rebate_bps.is_finite() {
+            return Err(MarketMakerError::InvalidInput(format!(
+                "maker_rebate_bps must be finite, got {}",
+                self.maker_rebate_bps
+            )));
+        }
+        self.validate_session()?;
+        for (name, count) in [
+            ("confirm_ticks", self.confirm_ticks),
+            ("log_sample_rate", self.log_sample_rate),
+        ] {
+            if count == 0 {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "{name} must be at least 1"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks of the daily trading session.
+    #[allow(clippy::result_large_err)]
+    fn validate_session(&self) -> Result<(), MarketMakerError> {
+        match (self.session_start, self.session_end) {
+            (Some(_), None) => Err(MarketMakerError::InvalidInput(
+                "session_start is set without a session_end".to_string(),
+            )),
+            (Some(start), Some(end)) if start == end => Err(MarketMakerError::InvalidInput(
+                format!("session_start and session_end are both {start}, the session is empty"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks of the rolling window and the statistics computed over it.
+    #[allow(clippy::result_large_err)]
+    fn validate_statistics(&self) -> Result<(), MarketMakerError> {
+        if self.window_size < 2 {
+            return Err(MarketMakerError::InvalidInput(format!(
+                "window_size must be at least 2, got {}",
+                self.window_size
+            )));
+        }
+        if let StatMode::Ewma { alpha, .. } = self.stat_mode {
+            if !(alpha > 0.0 && alpha <= 1.0) {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "EWMA alpha must be in (0, 1], got {alpha}"
+                )));
+            }
+        }
+        if let StatMode::Decay { half_life } = self.stat_mode {
+            if !(half_life > 0.0 && half_life.is_finite()) {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "half_life must be positive, got {half_life}"
+                )));
+            }
+        }
+        if let Some(scaling) = self.volatility_scaling {
+            if scaling.recent_window < 2
+                || !(scaling.min_multiplier > 0.0
+                    && scaling.min_multiplier <= scaling.max_multiplier)
+            {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "volatility scaling needs a recent_window of at least 2 and 0 < min_multiplier <= max_multiplier, got {scaling:?}"
+                )));
+            }
+        }
+        for (name, value) in [
+            ("min_stddev_bps", self.min_stddev_bps),
+            ("min_stddev_mean_fraction", self.min_stddev_mean_fraction),
+        ] {
+            if let Some(value) = value.filter(|value| !value.is_finite() || *value <= 0.0) {
+                return Err(MarketMakerError::InvalidInput(format!(
+                    "{name} must be positive when set, got {value}"
+                )));
+            }
+        }
+        if self
+            .min_correlation
+            .is_some_and(|min| !(-1.0..=1.0).contains(&min))
+        {
+            return Err(MarketMakerError::InvalidInput(format!(
+                "min_correlation must be between -1 and 1, got {:?}",
+                self.min_correlation
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Independent switches, not states of one machine
+#[allow(clippy::struct_excessive_bools)]
+pub struct MarketMaker {
+    pub asset: String,
+    pub info_client: InfoClient,
+    pub exchange_client: ExchangeClient,
+    pub user_address: H160,
+    // Shared reference to Binance price
+    pub binance_price: SharedReferencePrice,
+
+    // Rolling buffer of differences
+    diffs: stats::RollingWindow,
+    window_size: usize,
+    window_mode: WindowMode,
+    // When the current run of diffs started filling a time-based window
+    window_started_at: Option<Instant>,
+    ewma: Option<stats::Ewma>,
+    // Half-life in ticks of the window weights in decay mode
+    decay_half_life: Option<f64>,
+    z_threshold: f64,
+    correlation: stats::ReturnCorrelation,
+    min_correlation: Option<f64>,
+    size_mode: SizeMode,
+    balance_refresh: Duration,
+    balance_checked_at: Option<Instant>,
+    withdrawable: Option<f64>,
+    pub latest_mid_price: f64,
+    /// Set once the asset's absence from `AllMids` is logged, so a misnamed asset
+    /// logs one error instead of one per tick.
+    mid_missing: bool,
+    latest_book: Option<L2BookData>,
+    one_sided_book_band_bps: Option<f64>,
+    cross_buffer_bps: Option<f64>,
+    reject_counts: HashMap<RejectCategory, u64>,
+    hedge_batcher: Option<hedge::HedgeBatcher>,
+    hedge_trader: Option<BinanceTrader>,
+    hedge_position: f64,
+    hedge_cash: f64,
+    window_prior: Option<WindowPrior>,
+    fill_deduper: fills::FillDeduper,
+    reference_confirmation: Option<signal::ReferenceConfirmation>,
+    signal_debounce: signal::SignalDebounce,
+    subscription_ids: Vec<u32>,
+    shutdown_timeout: Duration,
+    base_url: BaseUrl,
+    market_kind: MarketKind,
+    // Token whose balance is the position on a spot market
+    spot_base: Option<String>,
+    standby_wallet: Option<LocalWallet>,
+    half_spread: u16,
+    signal_skew_weight: f64,
+    decimals: u32,
+    size_decimals: u32,
+    buy_vwap: fills::SideVwap,
+    sell_vwap: fills::SideVwap,
+    inventory: fills::Inventory,
+    round_trips: fills::RoundTripTracker,
+    protective_stop_bps: Option<f64>,
+    protective_take_profit_bps: Option<f64>,
+    protective_reprice_bps: f64,
+    stop_loss_bps: Option<f64>,
+    take_profit_bps: Option<f64>,
+    min_hold: Duration,
+    max_hold_time: Option<Duration>,
+    // Last fill that opened or added to the position
+    opened_at: Option<Instant>,
+    // Position an exit was sent for, so it isn't resent before its fills arrive
+    exit_sent_for: Option<f64>,
+    protective_orders: Option<protective::ProtectiveOrders>,
+    post_fill_quiet: Duration,
+    last_fill_at: Option<Instant>,
+    long_window_size: Option<usize>,
+    long_z_threshold: f64,
+    long_diffs: stats::RollingWindow,
+    returns: stats::ReturnSeries,
+    return_interval: Option<Duration>,
+    trade_results: stats::ReturnSeries,
+    stats_interval: Option<Duration>,
+    stats_logged_at: Instant,
+    interval_pnl: f64,
+    interval_started_at: Instant,
+    exposure: Option<Arc<Mutex<ExposureTracker>>>,
+    feeds: Vec<FeedTask>,
+    control_sender: UnboundedSender<ControlCommand>,
+    control_receiver: Option<UnboundedReceiver<ControlCommand>>,
+    paused: bool,
+    session: Option<session::TradingSession>,
+    z_threshold_schedule: Vec<ZThresholdWindow>,
+    volatility_scaling: Option<VolatilityScaling>,
+    loss_penalty: f64,
+    loss_penalty_half_life: Duration,
+    last_loss_at: Option<Instant>,
+    recent_diffs: stats::RollingWindow,
+    decisions: decisions::DecisionHistory,
+    reconcile_limiter: reconcile::ReconcileLimiter,
+    entry_band_bps: Option<f64>,
+    min_stddev_bps: Option<f64>,
+    min_stddev_mean_fraction: Option<f64>,
+    reconnect_limiter: Option<Arc<Mutex<ReconnectLimiter>>>,
+    outlier_k: Option<f64>,
+    outlier_confirm_ticks: usize,
+    outlier_streak: usize,
+    message_timeout: Option<Duration>,
+    heartbeat_interval: Option<Duration>,
+    log_sample_rate: usize,
+    /// Ticks that didn't signal a trade, counted to sample their debug logs.
+    quiet_ticks: usize,
+    last_tick_at: Option<Instant>,
+    mid_updated_at: Option<Instant>,
+    stale_orders: Option<StaleOrderPolicy>,
+    quote_depth_fraction: Option<f64>,
+    target_liquidity: f64,
+    max_absolute_position_size: f64,
+    max_bps_diff: u16,
+    max_bps_diff_band: bool,
+    log_position_headroom: bool,
+    fair_value: Option<Box<dyn FairValueSource>>,
+    inline_fills: fills::InlineFills,
+    dry_run: Option<FillModel>,
+    execution_style: ExecutionStyle,
+    post_only: bool,
+    maker_rebate_bps: f64,
+    passive_order: Option<execution::PassiveOrder>,
+    realized_pnl: f64,
+    max_price_age: Duration,
+    max_size_multiplier: f64,
+    inventory_skew: f64,
+    paper_trading: bool,
+    simulated_latency: Duration,
+    simulated_slippage_bps: f64,
+    min_trade_interval: Duration,
+    last_trade_at: Option<Instant>,
+    resting_order_timeout: Option<Duration>,
+    // When each order from `place_order` that rested was placed, and its unfilled size
+    unfilled_orders: HashMap<u64, (Instant, f64)>,
+    cloids: orders::CloidLog,
+    max_open_orders: Option<usize>,
+    order_tif: TimeInForce,
+    strategy: StrategyMode,
+    quotes: quote::TwoSidedQuotes,
+    warmup_ticks: usize,
+    ticks: usize,
+    max_order_notional: f64,
+    daily_budget: Option<budget::DailyNotionalBudget>,
+    // Orders worth less are rejected by the exchange, zero until the metadata is known
+    min_order_notional: f64,
+    taker_fee_bps: f64,
+    min_edge_bps: f64,
+    funding_refresh: Option<Duration>,
+    expected_holding_time: Duration,
+    funding_rate: Option<f64>,
+    funding_checked_at: Option<Instant>,
+    max_order_retries: u32,
+    order_retry_delay: Duration,
+    order_rate_limit: Option<TokenBucket>,
+    order_jitter: Duration,
+    jitter_rng: StdRng,
+    circuit_breaker: Option<CircuitBreaker>,
+    mid_source: MidSource,
+    subscriptions: Subscriptions,
+    reference_venue: ReferenceVenue,
+    mark_source: MarkSource,
+    binance_price_source: BinancePriceSource,
+    recheck_edge: bool,
+    imbalance_threshold: Option<f64>,
+    imbalance_levels: usize,
+    trade_log: Option<trade_log::TradeLog>,
+    state_path: Option<PathBuf>,
+    state_max_age: Duration,
+    last_signal: Option<trade_log::SignalSnapshot>,
+    // When the signal being acted on fired, and the reference price age then
+    signal_at: Option<(Instant, Option<Duration>)>,
+    latency_warn_threshold: Option<Duration>,
+    observer: Option<Box<dyn MarketMakerObserver + Send>>,
+    metrics: Metrics,
+    #[cfg(feature = "metrics")]
+    metrics_port: Option<u16>,
+    #[cfg(feature = "admin")]
+    admin_addr: Option<std::net::SocketAddr>,
+}
+
+impl MarketMaker {
+    /// # Errors
+    ///
+    /// Returns `Err` if the input is invalid, the exchange or info clients can't be
+    /// created, the asset isn't listed in the exchange metadata, or the trade log can't
+    /// be opened.
+    pub async fn new(input: Input) -> Result<Self, MarketMakerError> {
+        input.validate()?;
+        let info_client = InfoClient::new(None, Some(input.base_url))
+            .await
+            .map_err(MarketMakerError::InfoClientInit)?;
+        let mut exchange_client =
+            ExchangeClient::new(None, input.wallet.clone(), Some(input.base_url), None, None)
+                .await
+                .map_err(MarketMakerError::ExchangeClientInit)?;
+
+        let trade_log = input
+            .trade_log_path
+            .as_deref()
+            .map(trade_log::TradeLog::open)
+            .transpose()
+            .map_err(MarketMakerError::TradeLog)?;
+        let meta = exchange_client.meta.clone();
+        let spot_meta = if input.market_kind == MarketKind::Spot {
+            let spot_meta = info_client
+                .spot_meta()
+                .await
+                .map_err(MarketMakerError::InfoClientInit)?;
+            exchange_client.add_spot_meta(&spot_meta);
+            Some(spot_meta)
+        } else {
+            None
+        };
+        let mut market_maker = Self::with_clients(input, info_client, exchange_client);
+        market_maker.trade_log = trade_log;
+        match &spot_meta {
+            Some(spot_meta) => market_maker.apply_spot_meta(spot_meta)?,
+            None => market_maker.apply_asset_meta(&meta)?,
+        }
+        market_maker.restore_state();
+        Ok(market_maker)
+    }
+
+    /// Take the price and size decimals and the minimum order value from the exchange
+    /// metadata, which orders are validated against, over the configured ones.
+    #[allow(clippy::result_large_err)]
+    fn apply_asset_meta(&mut self, meta: &Meta) -> Result<(), MarketMakerError> {
+        let asset_meta = meta
+            .universe
+            .iter()
+            .find(|asset| asset.name == self.asset)
+            .ok_or_else(|| MarketMakerError::UnknownAsset(self.asset.clone()))?;
+        self.apply_sz_decimals(asset_meta.sz_decimals);
+        Ok(())
+    }
+
+    /// Resolve the spot pair to its name on the wire, which mids, books and fills
+    /// use, and take its decimals from the base token.
+    #[allow(clippy::result_large_err)]
+    fn apply_spot_meta(&mut self, spot_meta: &SpotMeta) -> Result<(), MarketMakerError> {
+        let (name, base) = market::spot_pair(spot_meta, &self.asset)
+            .ok_or_else(|| MarketMakerError::UnknownAsset(self.asset.clone()))?;
+        if name != self.asset {
+            info!("Trading spot pair {} as {name}", self.asset);
+            self.asset = name.to_string();
+        }
+        self.spot_base = Some(base.name.clone());
+        self.apply_sz_decimals(base.sz_decimals);
+        Ok(())
+    }
+
+    fn apply_sz_decimals(&mut self, sz_decimals: u32) {
+        let decimals = self.market_kind.max_decimals().saturating_sub(sz_decimals);
+        if (self.decimals, self.size_decimals) != (decimals, sz_decimals) {
+            info!(
+                "Using {decimals} price and {sz_decimals} size decimals for {} from the exchange metadata instead of {} and {}",
+                self.asset, self.decimals, self.size_decimals
+            );
+        }
+        self.decimals = decimals;
+        self.size_decimals = sz_decimals;
+        self.min_order_notional = MIN_ORDER_NOTIONAL;
+    }
+
+    // One line per configured field
+    #[allow(clippy::too_many_lines)]
+    fn with_clients(
+        input: Input,
+        info_client: InfoClient,
+        exchange_client: ExchangeClient,
+    ) -> Self {
+        let user_address = input.wallet.address();
+        let metrics = Metrics::new(&input.asset);
+        let (control_sender, control_receiver) = unbounded_channel();
+        Self {
+            asset: input.asset,
+            info_client,
+            exchange_client,
+            user_address,
+            binance_price: SharedReferencePrice::default(),
+            diffs: stats::RollingWindow::with_capacity(input.window_size),
+            window_mode: input.window_mode,
+            window_started_at: None,
+            ewma: match input.stat_mode {
+                StatMode::Window | StatMode::Decay { .. } => None,
+                StatMode::Ewma { alpha, min_samples } => Some(stats::Ewma::new(alpha, min_samples)),
+            },
+            decay_half_life: match input.stat_mode {
+                StatMode::Decay { half_life } => Some(half_life),
+                StatMode::Window | StatMode::Ewma { .. } => None,
+            },
+            window_size: input.window_size,
+            correlation: stats::ReturnCorrelation::new(input.window_size),
+            min_correlation: input.min_correlation,
+            z_threshold: input.z_threshold,
+            size_mode: input.size_mode,
+            balance_refresh: input.balance_refresh,
+            balance_checked_at: None,
+            withdrawable: None,
+            latest_mid_price: -1.0,
+            mid_missing: false,
+            latest_book: None,
+            one_sided_book_band_bps: input.one_sided_book_band_bps,
+            cross_buffer_bps: input.cross_buffer_bps,
+            reject_counts: HashMap::new(),
+            hedge_batcher: input
+                .hedge_delta_threshold
+                .or_else(|| input.hedge_trader.as_ref().map(|_| 0.0))
+                .map(hedge::HedgeBatcher::new),
+            hedge_trader: input.hedge_trader,
+            hedge_position: 0.0,
+            hedge_cash: 0.0,
+            window_prior: input.window_prior,
+            fill_deduper: fills::FillDeduper::new(input.fill_dedup_window),
+            reference_confirmation: input
+                .reference_confirm_bps
+                .map(signal::ReferenceConfirmation::new),
+            subscription_ids: Vec::new(),
+            shutdown_timeout: input.shutdown_timeout,
+            base_url: input.base_url,
+            market_kind: input.market_kind,
+            spot_base: None,
+            standby_wallet: input.standby_wallet,
+            half_spread: input.half_spread,
+            signal_skew_weight: input.signal_skew_weight,
+            decimals: input.decimals,
+            size_decimals: input.size_decimals,
+            buy_vwap: fills::SideVwap::default(),
+            sell_vwap: fills::SideVwap::default(),
+            inventory: fills::Inventory::default(),
+            round_trips: fills::RoundTripTracker::default(),
+            protective_stop_bps: input.protective_stop_bps,
+            protective_take_profit_bps: input.protective_take_profit_bps,
+            protective_reprice_bps: input.protective_reprice_bps,
+            stop_loss_bps: input.stop_loss_bps,
+            take_profit_bps: input.take_profit_bps,
+            min_hold: input.min_hold,
+            max_hold_time: input.max_hold_time,
+            opened_at: None,
+            exit_sent_for: None,
+            protective_orders: None,
+            post_fill_quiet: input.post_fill_quiet,
+            last_fill_at: None,
+            long_window_size: input.long_window_size,
+            long_z_threshold: input.long_z_threshold,
+            long_diffs: stats::RollingWindow::with_capacity(input.long_window_size.unwrap_or(0)),
+            returns: stats::ReturnSeries::new(input.returns_window),
+            return_interval: input.return_interval,
+            trade_results: stats::ReturnSeries::new(input.returns_window),
+            stats_interval: input.stats_interval,
+            stats_logged_at: Instant::now(),
+            interval_pnl: 0.0,
+            interval_started_at: Instant::now(),
+            exposure: input.exposure,
+            feeds: Vec::new(),
+            control_sender,
+            control_receiver: Some(control_receiver),
+            paused: false,
+            session: input
+                .session_end
+                .map(|end| session::TradingSession::new(input.session_start, end)),
+            z_threshold_schedule: input.z_threshold_schedule,
+            volatility_scaling: input.volatility_scaling,
+            loss_penalty: input.loss_penalty,
+            loss_penalty_half_life: input.loss_penalty_half_life,
+            last_loss_at: None,
+            recent_diffs: stats::RollingWindow::with_capacity(
+                input
+                    .volatility_scaling
+                    .map_or(0, |scaling| scaling.recent_window),
+            ),
+            decisions: decisions::DecisionHistory::new(input.decision_history),
+            reconcile_limiter: reconcile::ReconcileLimiter::new(input.reconcile_min_interval),
+            entry_band_bps: input.entry_band_bps,
+            min_stddev_bps: input.min_stddev_bps,
+            min_stddev_mean_fraction: input.min_stddev_mean_fraction,
+            reconnect_limiter: input.reconnect_limiter,
+            signal_debounce: signal::SignalDebounce::new(input.confirm_ticks),
+            outlier_k: input.outlier_k,
+            outlier_confirm_ticks: input.outlier_confirm_ticks,
+            outlier_streak: 0,
+            message_timeout: input.message_timeout,
+            heartbeat_interval: input.heartbeat_interval,
+            log_sample_rate: input.log_sample_rate,
+            quiet_ticks: 0,
+            last_tick_at: None,
+            mid_updated_at: None,
+            stale_orders: input.stale_orders,
+            quote_depth_fraction: input.quote_depth_fraction,
+            target_liquidity: input.target_liquidity,
+            max_absolute_position_size: input.max_absolute_position_size,
+            max_bps_diff: input.max_bps_diff,
+            max_bps_diff_band: input.max_bps_diff_band,
+            log_position_headroom: input.log_position_headroom,
+            fair_value: input.fair_value,
+            inline_fills: fills::InlineFills::default(),
+            dry_run: input
+                .dry_run
+                .or_else(|| input.paper_trading.then(|| FillModel::new(0, 0.0))),
+            execution_style: input.execution_style,
+            post_only: input.post_only,
+            maker_rebate_bps: input.maker_rebate_bps,
+            passive_order: None,
+            realized_pnl: 0.0,
+            max_price_age: input.max_price_age,
+            max_size_multiplier: input.max_size_multiplier,
+            inventory_skew: input.inventory_skew,
+            paper_trading: input.paper_trading,
+            simulated_latency: input.simulated_latency,
+            simulated_slippage_bps: input.simulated_slippage_bps,
+            min_trade_interval: input.min_trade_interval,
+            last_trade_at: None,
+            resting_order_timeout: input.resting_order_timeout,
+            unfilled_orders: HashMap::new(),
+            cloids: orders::CloidLog::default(),
+            max_open_orders: input.max_open_orders,
+            order_tif: input.order_tif,
+            strategy: input.strategy,
+            quotes: quote::TwoSidedQuotes::default(),
+            warmup_ticks: input.warmup_ticks,
+            ticks: 0,
+            max_order_notional: input.max_order_notional,
+            daily_budget: input
+                .max_daily_notional
+                .map(budget::DailyNotionalBudget::new),
+            min_order_notional: 0.0,
+            taker_fee_bps: input.taker_fee_bps,
+            min_edge_bps: input.min_edge_bps,
+            funding_refresh: input.funding_refresh,
+            expected_holding_time: input.expected_holding_time,
+            funding_rate: None,
+            funding_checked_at: None,
+            max_order_retries: input.max_order_retries,
+            order_retry_delay: input.order_retry_delay,
+            order_rate_limit: input.order_rate_limit,
+            order_jitter: input.order_jitter,
+            jitter_rng: input
+                .jitter_seed
+                .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+            circuit_breaker: input.circuit_breaker,
+            mid_source: input.mid_source,
+            subscriptions: input.subscriptions,
+            reference_venue: input.reference_venue,
+            mark_source: input.mark_source,
+            binance_price_source: input.binance_price_source,
+            recheck_edge: input.recheck_edge,
+            imbalance_threshold: input.imbalance_threshold,
+            imbalance_levels: input.imbalance_levels,
+            trade_log: None,
+            state_path: input.state_path,
+            state_max_age: input.state_max_age,
+            last_signal: None,
+            signal_at: None,
+            latency_warn_threshold: input.latency_warn_threshold,
+            observer: input.observer,
+            metrics,
+            #[cfg(feature = "metrics")]
+            metrics_port: input.metrics_port,
+            #[cfg(feature = "admin")]
+            admin_addr: input.admin_addr,
+        }
+    }
+
+    /// Rolling Sharpe ratio of realized returns, if there are enough samples.
+    #[must_use]
+    pub fn sharpe_ratio(&self) -> Option<f64> {
+        self.returns.sharpe()
+    }
+
+    /// Rolling Sortino ratio of realized returns, if there are enough samples.
+    #[must_use]
+    pub fn sortino_ratio(&self) -> Option<f64> {
+        self.returns.sortino()
+    }
+
+    /// Trade count, win rate, average `PnL` and Sharpe ratio of the last
+    /// `returns_window` closing trades.
+    #[must_use]
+    pub fn stats(&self) -> StrategyStats {
+        self.trade_results.strategy_stats()
+    }
+
+    /// Log [`MarketMaker::stats`] once `stats_interval` has passed since they last were.
+    fn log_stats(&mut self, now: Instant) {
+        let Some(interval) = self.stats_interval else {
+            return;
+        };
+        if now.duration_since(self.stats_logged_at) < interval {
+            return;
+        }
+        self.stats_logged_at = now;
+        let stats = self.stats();
+        let exposure = self.exposure();
+        let capture = self.spread_capture();
+        let fmt =
+            |value: Option<f64>| value.map_or_else(|| "n/a".to_string(), |v| format!("{v:.4}"));
+        info!(
+            "{} performance over {} trades: win rate {}, average PnL {}, Sharpe {}, bought {} for {:.2} and sold {} for {:.2}, {} round trips made {:.4} from the spread and {:.4} from drift",
+            self.asset,
+            stats.trades,
+            fmt(stats.win_rate),
+            fmt(stats.avg_pnl),
+            fmt(stats.sharpe),
+            self.fmt_sz(exposure.gross_bought),
+            exposure.bought_notional,
+            self.fmt_sz(exposure.gross_sold),
+            exposure.sold_notional,
+            capture.round_trips,
+            capture.edge_pnl,
+            capture.drift_pnl
+        );
+    }
+
+    /// Tracks the round trip a fill belongs to against the reference price, logging it
+    /// once the position is closed. Without a reference the whole move counts as drift.
+    fn record_round_trip(&mut self, signed_amount: f64, px: f64, time: u64) {
+        let reference = Some(self.reference_price())
+            .filter(|reference| is_valid_price(*reference))
+            .unwrap_or(px);
+        let Some(round_trip) = self.round_trips.apply(signed_amount, px, reference) else {
+            return;
+        };
+        info!(
+            "Closed {} round trip of {}: diff {:.2} bps at entry and {:.2} bps at exit, {:.4} from the spread and {:.4} from drift",
+            if round_trip.is_long { "long" } else { "short" },
+            self.fmt_sz(round_trip.size),
+            round_trip.entry_diff_bps,
+            round_trip.exit_diff_bps,
+            round_trip.edge_pnl,
+            round_trip.drift_pnl
+        );
+        if let Some(trade_log) = self.trade_log.as_mut() {
+            trade_log.round_trip(time, &self.asset, &round_trip);
+        }
+    }
+
+    fn record_realized_pnl(&mut self, realized: f64, now: Instant) {
+        if realized.abs() > EPSILON {
+            self.trade_results.push(realized);
+        }
+        if realized < -EPSILON {
+            self.last_loss_at = Some(now);
+        }
+        if self.return_interval.is_none() {
+            if realized.abs() > EPSILON {
+                self.returns.push(realized);
+            }
+            return;
+        }
+        self.roll_return_interval(now);
+        self.interval_pnl += realized;
+    }
+
+    /// Close out every return interval that has fully elapsed by `now`.
+    fn roll_return_interval(&mut self, now: Instant) {
+        let Some(interval) = self.return_interval else {
+            return;
+        };
+        while now.duration_since(self.interval_started_at) >= interval {
+            self.returns.push(self.interval_pnl);
+            self.interval_pnl = 0.0;
+            self.interval_started_at += interval;
+        }
+    }
+
+    /// Volume-weighted average price of this session's buy fills.
+    #[must_use]
+    pub fn avg_buy_px(&self) -> Option<f64> {
+        self.buy_vwap.average()
+    }
+
+    /// Volume-weighted average price of this session's sell fills.
+    #[must_use]
+    pub fn avg_sell_px(&self) -> Option<f64> {
+        self.sell_vwap.average()
+    }
+
+    /// Gross buys and sells of this session's fills of the asset, next to the net
+    /// position.
+    #[must_use]
+    pub const fn exposure(&self) -> Exposure {
+        Exposure {
+            position: self.inventory.position(),
+            gross_bought: self.buy_vwap.size(),
+            gross_sold: self.sell_vwap.size(),
+            bought_notional: self.buy_vwap.notional(),
+            sold_notional: self.sell_vwap.notional(),
+        }
+    }
+
+    /// This session's round trips split into `PnL` from the spread to the reference
+    /// converging and from the reference drifting while holding.
+    #[must_use]
+    pub const fn spread_capture(&self) -> SpreadCapture {
+        self.round_trips.capture()
+    }
+
+    /// Rounds a price to the asset's price decimals and at most five significant
+    /// figures, which the exchange requires of all but integer prices.
+    fn round_px(&self, px: f64) -> f64 {
+        self.round_px_with(px, f64::round)
+    }
+
+    /// Rounds the price of an order meant to cross away from the book, up for a buy
+    /// and down for a sell, so rounding can't leave it short of the touch.
+    fn round_cross_px(&self, px: f64, is_buy: bool) -> f64 {
+        // Nudged so a price already on a tick isn't rounded a tick further out
+        if is_buy {
+            self.round_px_with(px, |scaled| (scaled - 1e-9).ceil())
+        } else {
+            self.round_px_with(px, |scaled| (scaled + 1e-9).floor())
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn round_px_with(&self, px: f64, round: impl Fn(f64) -> f64) -> f64 {
+        let significant = if px.abs() > EPSILON {
+            4 - px.abs().log10().floor() as i32
+        } else {
+            i32::MAX
+        };
+        let decimals = i32::try_from(self.decimals)
+            .unwrap_or(i32::MAX)
+            .min(significant.max(0));
+        let pow10 = 10f64.powi(decimals);
+        round(px * pow10) / pow10
+    }
+
+    /// Rounds a size down to the asset's size decimals, so it never exceeds the
+    /// headroom it was clamped to.
+    fn round_sz(&self, sz: f64) -> f64 {
+        truncate_float(sz, self.size_decimals, false)
+    }
+
+    /// Formats a price with the asset's price decimals for logging.
+    fn fmt_px(&self, px: f64) -> String {
+        format!("{px:.*}", self.decimals as usize)
+    }
+
+    /// Formats a size with the asset's size decimals for logging.
+    fn fmt_sz(&self, sz: f64) -> String {
+        format!("{sz:.*}", self.size_decimals as usize)
+    }
+
+    /// Passive bid and ask prices around the latest mid, skewed by the z-score `z`.
+    #[must_use]
+    pub fn quote_prices(&self, z: f64) -> (f64, f64) {
+        quote::skewed_quotes(
+            self.latest_mid_price,
+            f64::from(self.half_spread),
+            z,
+            self.signal_skew_weight,
+        )
+    }
+
+    /// Signed position from `UserEvents` fills, positive when long.
+    #[must_use]
+    pub const fn position(&self) -> f64 {
+        self.inventory.position()
+    }
+
+    /// Profit realized by closing fills so far, net of fees.
+    #[must_use]
+    pub const fn realized_pnl(&self) -> f64 {
+        self.realized_pnl
+    }
+
+    /// Profit of the open position marked at the latest price of the mark source.
+    #[must_use]
+    pub fn unrealized_pnl(&self) -> f64 {
+        let mark = self
+            .mark_source
+            .mark_px(self.latest_mid_price, self.binance_price.get().price);
+        self.inventory.position() * (mark - self.inventory.avg_entry_px())
+    }
+
+    /// Position held on Binance by the hedge leg.
+    #[must_use]
+    pub const fn hedge_position(&self) -> f64 {
+        self.hedge_position
+    }
+
+    /// Profit of the hedge leg, marked at the latest Binance price.
+    #[must_use]
+    pub fn hedge_pnl(&self) -> f64 {
+        self.hedge_position
+            .mul_add(self.binance_price.get().price, self.hedge_cash)
+    }
+
+    /// Realized and unrealized profit of both legs together.
+    #[must_use]
+    pub fn combined_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl() + self.hedge_pnl()
+    }
+
+    /// Size to quote at `px`, capped by the visible depth when configured.
+    #[must_use]
+    pub fn quote_size(&self, is_buy: bool, px: f64) -> f64 {
+        match (self.quote_depth_fraction, &self.latest_book) {
+            (Some(fraction), Some(book)) => {
+                quote::depth_capped_size(&book.levels, is_buy, px, self.target_liquidity, fraction)
+            }
+            _ => self.target_liquidity,
+        }
+    }
+
+    /// Change the rolling window size at runtime. Shrinking drops the oldest samples
+    /// and keeps trading on the truncated window; growing rewarms until the larger
+    /// window has filled. Only count-based windows are bounded by the size.
+    pub fn resize_window(&mut self, window_size: usize) {
+        while self.window_mode == WindowMode::Count && self.diffs.len() > window_size {
+            self.diffs.pop_front();
+        }
+        self.window_size = window_size;
+        if !self.is_warm() {
+            info!(
+                "Window resized to {window_size}, rewarming with {} samples",
+                self.diffs.len()
+            );
+        }
+    }
+
+    /// Returns true once the rolling window holds a full window of samples, or in
+    /// time mode has been filling for the whole duration and holds at least two.
+    #[must_use]
+    pub fn is_warm(&self) -> bool {
+        match self.window_mode {
+            WindowMode::Count => self.diffs.len() >= self.window_size,
+            WindowMode::Time(duration) => {
+                self.diffs.len() >= 2
+                    && self
+                        .window_started_at
+                        .is_some_and(|started_at| started_at.elapsed() >= duration)
+            }
+        }
+    }
+
+    /// Delta from fills that hasn't been released as a hedge yet.
+    #[must_use]
+    pub fn unhedged_delta(&self) -> f64 {
+        self.hedge_batcher
+            .as_ref()
+            .map_or(0.0, hedge::HedgeBatcher::unhedged_delta)
+    }
+
+    /// Number of order rejections seen so far, tagged by category.
+    #[must_use]
+    pub const fn reject_counts(&self) -> &HashMap<RejectCategory, u64> {
+        &self.reject_counts
+    }
+
+    async fn record_reject(&mut self, error: &str) -> RejectCategory {
+        let category = RejectCategory::classify(error);
+        *self.reject_counts.entry(category).or_insert(0) += 1;
+        self.metrics
+            .record(|values| values.rejects[category as usize] += 1);
+        if category == RejectCategory::Auth {
+            self.rotate_to_standby_wallet().await;
+        }
+        category
+    }
+
+    /// Rebuild the exchange client with the standby wallet, if one is configured and
+    /// hasn't been switched to already.
+    async fn rotate_to_standby_wallet(&mut self) {
+        let Some(standby_wallet) = self.standby_wallet.take() else {
+            return;
+        };
+        let standby_address = standby_wallet.address();
+        match ExchangeClient::new(
+            None,
+            standby_wallet,
+            Some(self.base_url),
+            Some(self.exchange_client.meta.clone()),
+            self.exchange_client.vault_address,
+        )
+        .await
+        {
+            Ok(exchange_client) => {
+                info!(
+                    "Rotated order signing from {:?} to standby wallet {standby_address:?}",
+                    self.exchange_client.wallet.address()
+                );
+                self.exchange_client = exchange_client;
+            }
+            Err(e) => error!("Failed to rotate to standby wallet {standby_address:?}: {e}"),
+        }
+    }
+
+    /// Trade until the feeds end, then shut down.
+    pub async fn start(&mut self) {
+        self.start_until(std::future::pending()).await;
+    }
+
+    /// Trade until `shutdown` resolves or the feeds end, then
+    /// [`shut down`](MarketMaker::shutdown), cancelling resting orders.
+    pub async fn start_until(&mut self, shutdown: impl std::future::Future<Output = ()>) {
+        self.run(shutdown).await;
+        self.shutdown().await;
+    }
+
+    /// Subscribes and processes messages until `shutdown` resolves.
+    async fn run(&mut self, shutdown: impl std::future::Future<Output = ()>) {
+        #[cfg(feature = "metrics")]
+        if let Some(port) = self.metrics_port {
+            self.attach_feed(metrics::serve(port, self.metrics.clone()));
+        }
+        #[cfg(feature = "admin")]
+        if let Some(addr) = self.admin_addr {
+            let control = self.control_handle();
+            self.attach_feed(admin::serve(addr, self.metrics.clone(), control));
+        }
+        if self.signal_only() {
+            info!("Not subscribed to UserEvents, watching the signal without trading");
+        }
+        self.handle_stale_orders().await;
+        self.seed_position().await;
+        let Some(mut receiver) = self.subscribe().await else {
+            return;
+        };
+
+        // Main event loop
+        tokio::pin!(shutdown);
+        let Some(mut control) = self.control_receiver.take() else {
+            error!("Market maker is already running");
+            return;
+        };
+        self.check_session(Utc::now().time()).await;
+        let mut last_message = Instant::now();
+        let mut last_heartbeat = Instant::now();
+        let mut last_session_check = Instant::now();
+        loop {
+            let silence_deadline = last_message + self.message_timeout.unwrap_or_default();
+            let heartbeat_deadline = last_heartbeat + self.heartbeat_interval.unwrap_or_default();
+            let session_deadline = last_session_check + SESSION_CHECK_INTERVAL;
+            tokio::select! {
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    last_message = Instant::now();
+                    self.process_message(message).await;
+                }
+                () = tokio::time::sleep_until(silence_deadline.into()),
+                    if self.message_timeout.is_some() =>
+                {
+                    warn!(
+                        "No Hyperliquid message for {:?}, resubscribing",
+                        self.message_timeout.unwrap_or_default()
+                    );
+                    last_message = Instant::now();
+                    if let Some(fresh) = self.resubscribe().await {
+                        receiver = fresh;
+                        self.on_reconnect().await;
+                    }
+                }
+                () = tokio::time::sleep_until(heartbeat_deadline.into()),
+                    if self.heartbeat_interval.is_some() =>
+                {
+                    last_heartbeat = Instant::now();
+                    info!("{}", self.heartbeat(last_heartbeat));
+                }
+                () = tokio::time::sleep_until(session_deadline.into()),
+                    if self.session.is_some() =>
+                {
+                    last_session_check = Instant::now();
+                    self.check_session(Utc::now().time()).await;
+                }
+                Some(command) = control.recv() => self.on_control(command).await,
+                () = &mut shutdown => {
+                    info!("Shutdown requested");
+                    break;
+                }
+            }
+        }
+        self.control_receiver = Some(control);
+    }
+
+    fn record_tick(&mut self, hl_price: f64, reference_price: f64) {
+        self.ticks = self.ticks.saturating_add(1);
+        self.last_tick_at = Some(Instant::now());
+        self.correlation.push(hl_price, reference_price);
+        Span::current().record("tick", self.ticks);
+    }
+
+    /// Correlation of the price returns when it is below `min_correlation`.
+    fn decoupled(&self) -> Option<f64> {
+        let min_correlation = self.min_correlation?;
+        self.correlation
+            .correlation()
+            .filter(|correlation| *correlation < min_correlation)
+    }
+
+    /// Liveness line for the heartbeat, telling a bot with nothing to do apart from a
+    /// hung one.
+    fn heartbeat(&self, now: Instant) -> String {
+        let age = |at: Option<Instant>| {
+            at.map_or_else(
+                || "never".to_string(),
+                |at| format!("{:.1?} ago", now.duration_since(at)),
+            )
+        };
+        format!(
+            "{} alive: last tick {}, position {}, realized PnL {:.4}, unrealized PnL {:.4}, correlation {}, Hyperliquid mid {}, {} price {}{}",
+            self.asset,
+            age(self.last_tick_at),
+            self.fmt_sz(self.inventory.position()),
+            self.realized_pnl,
+            self.unrealized_pnl(),
+            self.correlation
+                .correlation()
+                .map_or_else(|| "n/a".to_string(), |correlation| format!("{correlation:.2}")),
+            age(self.mid_updated_at),
+            self.reference_venue.as_str(),
+            age(self.binance_price.get().updated_at),
+            if self.paused { ", paused" } else { "" }
+        )
+    }
+
+    /// Position, profit, signal, window and feed state, for the admin endpoint or a
+    /// log line to read without reaching into the bot.
+    #[must_use]
+    pub fn snapshot(&self) -> MarketMakerState {
+        let now = Instant::now();
+        let age =
+            |at: Option<Instant>| at.map(|at| now.saturating_duration_since(at).as_secs_f64());
+        let signal = self.last_signal.as_ref();
+        MarketMakerState {
+            asset: self.asset.clone(),
+            position: self.inventory.position(),
+            realized_pnl: self.realized_pnl,
+            unrealized_pnl: self.unrealized_pnl(),
+            z_score: signal.and_then(|signal| signal.z),
+            mean: signal.map(|signal| signal.mean),
+            stddev: signal.map(|signal| signal.stddev),
+            window_len: self.diffs.len(),
+            window_size: self.window_size,
+            last_trade_age_seconds: age(self.last_trade_at),
+            paused: self.paused,
+            halted: self
+                .circuit_breaker
+                .as_ref()
+                .and_then(CircuitBreaker::halted_until)
+                .is_some_and(|until| now < until),
+            mid_age_seconds: age(self.mid_updated_at),
+            reference_price_age_seconds: age(self.binance_price.get().updated_at),
+        }
+    }
+
+    /// Parks the bot at the end of the session, cancelling its working orders and
+    /// flattening, and resumes it at the start of the next one.
+    async fn check_session(&mut self, now: NaiveTime) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        let Some(parked) = session.update(now) else {
+            return;
+        };
+        if !parked {
+            info!(
+                "Trading session open at {now} UTC, {} trading again",
+                self.asset
+            );
+            return;
+        }
+        let position = self.inventory.position();
+        warn!(
+            "Trading session ended at {} UTC, parking {}: no new entries, flattening a position of {}",
+            session.end(),
+            self.asset,
+            self.fmt_sz(position)
+        );
+        let oids = self.take_working_orders();
+        if !oids.is_empty() && self.dry_run.is_none() {
+            let cancels = oids
+                .iter()
+                .map(|&oid| ClientCancelRequest {
+                    asset: self.asset.clone(),
+                    oid,
+                })
+                .collect();
+            if let Err(e) = self.exchange_client.bulk_cancel(cancels, None).await {
+                error!("Error cancelling orders {oids:?} at the end of the session: {e}");
+            }
+        }
+        if self.flatten().await {
+            info!("{} is flat and parked until the session starts", self.asset);
+        }
+    }
+
+    fn session_parked(&self) -> bool {
+        self.session.is_some_and(|session| session.parked())
+    }
+
+    async fn on_control(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::Pause => self.set_paused(true),
+            ControlCommand::Resume => self.set_paused(false),
+            ControlCommand::Flatten => {
+                info!("Flattening {} on request", self.asset);
+                self.flatten().await;
+            }
+            ControlCommand::DumpDecisions => self.dump_decisions(),
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        if paused == self.paused {
+            return;
+        }
+        self.paused = paused;
+        self.metrics.record(|values| values.paused = paused);
+        if paused {
+            info!("Trading {} paused, still tracking prices", self.asset);
+        } else {
+            info!("Trading {} resumed", self.asset);
+        }
+    }
+
+    /// Subscribe to fills, mids and, if needed, the book on a fresh channel.
+    async fn subscribe(&mut self) -> Option<UnboundedReceiver<Message>> {
+        let (sender, receiver) = unbounded_channel();
+
+        // UserEvents for fills, AllMids for the latest mid prices
+        let mut subscriptions = Vec::new();
+        if self.subscriptions.user_events {
+            subscriptions.push(Subscription::UserEvents {
+                user: self.user_address,
+            });
+        }
+        if self.subscriptions.all_mids {
+            subscriptions.push(Subscription::AllMids);
+        }
+        if self.needs_book() {
+            subscriptions.push(Subscription::L2Book {
+                coin: self.asset.clone(),
+            });
+        }
+        for subscription in subscriptions {
+            let subscription_id = reconnect::subscribe_with_retry(
+                &mut self.info_client,
+                &subscription,
+                &sender,
+                SUBSCRIBE_BACKOFF,
+                SUBSCRIBE_ATTEMPTS,
+            )
+            .await?;
+            self.subscription_ids.push(subscription_id);
+        }
+        Some(receiver)
+    }
+
+    /// Start over on a new connection after the current one went silent, as it may be
+    /// half-open. Counts against the shared reconnect limit.
+    async fn resubscribe(&mut self) -> Option<UnboundedReceiver<Message>> {
+        if let Some(limiter) = &self.reconnect_limiter {
+            let attempt = limiter.lock().unwrap().try_attempt(Instant::now());
+            if let Err(until) = attempt {
+                warn!(
+                    "Not resubscribing, reconnects are paused for {:?}",
+                    until.saturating_duration_since(Instant::now())
+                );
+                return None;
+            }
+        }
+        match InfoClient::new(None, Some(self.base_url)).await {
+            Ok(info_client) => self.info_client = info_client,
+            Err(e) => {
+                error!("Error creating a new info client: {e}");
+                return None;
+            }
+        }
+        self.subscription_ids.clear();
+        self.subscribe().await
+    }
+
+    /// Start from the position the account already holds in this asset, so the cap
+    /// is enforced against inventory left by a previous run.
+    async fn seed_position(&mut self) {
+        // A bot watching the signal never trades the account's position
+        if self.paper_trading || self.signal_only() {
+            return;
+        }
+        let (position, entry_px) = match self.fetch_exchange_position().await {
+            Ok(position) => position,
+            Err(e) => {
+                error!("Error fetching the starting position: {e}");
+                return;
+            }
+        };
+        self.set_position(position, entry_px);
+        info!(
+            "Starting {} position: {}",
+            self.asset,
+            self.fmt_sz(position)
+        );
+    }
+
+    /// Signed size and entry price of the account's position in this asset: the perp
+    /// position, or the base token balance on a spot market.
+    // Borrowed mutably so the future stays `Send`, the maker isn't `Sync`
+    #[allow(clippy::needless_pass_by_ref_mut)]
+    async fn fetch_exchange_position(&mut self) -> Result<(f64, f64), crate::Error> {
+        if let Some(base) = &self.spot_base {
+            self.info_client
+                .user_token_balances(self.user_address)
+                .await
+                .map(|balances| fills::spot_position(&balances.balances, base))
+        } else {
+            self.info_client
+                .user_state(self.user_address)
+                .await
+                .map(|user_state| {
+                    fills::exchange_position(&user_state.asset_positions, &self.asset)
+                })
+        }
+    }
+
+    /// Whether the bot watches the signal without sending orders, having no fills to
+    /// track them by.
+    const fn signal_only(&self) -> bool {
+        !self.subscriptions.user_events && self.dry_run.is_none()
+    }
+
+    /// Why the bot isn't trading at all right now: paused, parked outside the trading
+    /// session or only watching the signal.
+    fn stood_down(&self, intent: &TradeIntent) -> Option<&'static str> {
+        if self.paused {
+            debug!("Trading is paused, not acting on the signal");
+            return Some("paused");
+        }
+        if self.session_parked() {
+            debug!("Outside the trading session, not acting on the signal");
+            return Some("session_closed");
+        }
+        self.watching_only(intent).then_some("signal_only")
+    }
+
+    /// Logs what a signal-only bot would have traded on `intent`. Returns whether it
+    /// is signal-only.
+    fn watching_only(&self, intent: &TradeIntent) -> bool {
+        if !self.signal_only() {
+            return false;
+        }
+        info!(
+            "Signal-only, would {} {} at {}",
+            if intent.is_buy { "buy" } else { "sell" },
+            self.fmt_sz(intent.size),
+            intent.signal
+        );
+        true
+    }
+
+    /// The book is only needed for book-derived mids, the one-sided and imbalance
+    /// checks, depth-capped quotes and book-based crossing prices.
+    fn needs_book(&self) -> bool {
+        self.mid_source != MidSource::AllMids
+            || self.one_sided_book_band_bps.is_some()
+            || self.imbalance_threshold.is_some()
+            || self.quote_depth_fraction.is_some()
+            || self.cross_buffer_bps.is_some()
+    }
+
+    /// Cancel or adopt orders a previous run left resting, per the configured policy.
+    async fn handle_stale_orders(&mut self) {
+        let Some(policy) = self
+            .stale_orders
+            .filter(|_| !self.paper_trading && !self.signal_only())
+        else {
+            return;
+        };
+        let open_orders = match self.info_client.open_orders(self.user_address).await {
+            Ok(open_orders) => open_orders,
+            Err(e) => {
+                error!("Error fetching open orders from a previous run: {e}");
+                return;
+            }
+        };
+        let cancels = self.apply_stale_order_policy(policy, &open_orders);
+        if cancels.is_empty() {
+            return;
+        }
+        let count = cancels.len();
+        match self.exchange_client.bulk_cancel(cancels, None).await {
+            Ok(response) => info!(
+                "Cancelled {} of {count} stale {} orders",
+                orders::cancelled_count(&response),
+                self.asset
+            ),
+            Err(e) => error!("Error cancelling stale orders: {e}"),
+        }
+    }
+
+    /// Adopt the asset's open orders as unfilled orders of this run, so they count
+    /// towards `max_open_orders`, get swept and are cancelled on shutdown, or return the
+    /// cancels to send for them.
+    fn apply_stale_order_policy(
+        &mut self,
+        policy: StaleOrderPolicy,
+        open_orders: &[OpenOrdersResponse],
+    ) -> Vec<ClientCancelRequest> {
+        let stale: Vec<_> = open_orders
+            .iter()
+            .filter(|order| {
+                order.coin == self.asset && !self.unfilled_orders.contains_key(&order.oid)
+            })
+            .collect();
+        info!(
+            "Found {} stale {} orders from a previous run, policy {policy:?}",
+            stale.len(),
+            self.asset
+        );
+
+        match policy {
+            StaleOrderPolicy::CancelAll => stale
+                .into_iter()
+                .map(|order| ClientCancelRequest {
+                    asset: self.asset.clone(),
+                    oid: order.oid,
+                })
+                .collect(),
+            StaleOrderPolicy::Adopt => {
+                for order in stale {
+                    if let Ok(sz) = order.sz.parse() {
+                        self.unfilled_orders.insert(order.oid, (Instant::now(), sz));
+                    } else {
+                        error!("Could not adopt malformed open order {order:?}");
+                    }
+                }
+                Vec::new()
+            }
+        }
+    }
+
+    /// Drive the bot from a recorded capture instead of live subscriptions. Binance
+    /// frames update the reference price and Hyperliquid frames go through the same
+    /// handling as `start`, in capture order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference price mutex is poisoned.
+    pub async fn replay(&mut self, feed: ReplayFeed) {
+        let (sender, mut receiver) = unbounded_channel();
+        let feed_task = tokio::spawn(feed.run(sender));
+
+        while let Some(event) = receiver.recv().await {
+            match event {
+                replay::ReplayEvent::BinancePrice(price) => {
+                    self.binance_price.set(price);
+                }
+                replay::ReplayEvent::Hyperliquid(message) => self.process_message(*message).await,
+            }
+        }
+
+        if let Err(e) = feed_task.await {
+            error!("Replay feed task failed: {e}");
+        }
+    }
+
+    /// Most recent decisions, oldest first.
+    pub fn decisions(&self) -> impl Iterator<Item = &Decision> {
+        self.decisions.iter()
+    }
+
+    /// Log the retained decision history.
+    pub fn dump_decisions(&self) {
+        for decision in self.decisions() {
+            info!("{decision:?}");
+        }
+    }
+
+    /// Call once the Hyperliquid subscriptions are re-established, to pick up fills and
+    /// cancels missed during the gap.
+    pub async fn on_reconnect(&mut self) {
+        if self.should_reconcile(Instant::now()) {
+            self.reconcile().await;
+        }
+    }
+
+    fn should_reconcile(&mut self, now: Instant) -> bool {
+        if self.reconcile_limiter.try_acquire(now) {
+            return true;
+        }
+        info!("Skipping reconciliation, the last one was too recent");
+        false
+    }
+
+    /// Align the tracked position and protective orders with the exchange.
+    async fn reconcile(&mut self) {
+        // The exchange knows nothing of paper positions, and a bot watching the signal
+        // never trades the account's position
+        if self.paper_trading || self.signal_only() {
+            return;
+        }
+        match self.fetch_exchange_position().await {
+            Ok((position, entry_px)) => self.reconcile_position(position, entry_px),
+            Err(e) => {
+                error!("Reconciliation failed to fetch the position: {e}");
+                return;
+            }
+        }
+        if self.inline_fills.pending() > 0 {
+            info!(
+                "{} inline fills were not confirmed on UserEvents before reconciling",
+                self.inline_fills.pending()
+            );
+        }
+
+        match self.info_client.open_orders(self.user_address).await {
+            Ok(open_orders) => {
+                if let Some(protective) = self.protective_orders.as_mut() {
+                    let before = protective.oids.len();
+                    protective
+                        .oids
+                        .retain(|oid| open_orders.iter().any(|order| order.oid == *oid));
+                    info!(
+                        "Reconciled {} orders: {} protective orders no longer resting",
+                        self.asset,
+                        before - protective.oids.len()
+                    );
+                }
+            }
+            Err(e) => error!("Reconciliation failed to fetch open orders: {e}"),
+        }
+        self.update_protective_orders().await;
+    }
+
+    /// Align the tracked position with the one the exchange reports.
+    fn reconcile_position(&mut self, position: f64, entry_px: f64) {
+        let delta = position - self.inventory.position();
+        if delta.abs() > EPSILON {
+            info!(
+                "Reconciled {} position: applied delta {}",
+                self.asset,
+                self.fmt_sz(delta)
+            );
+            self.set_position(position, entry_px);
+        }
+    }
+
+    /// Track `position` as the account's position in this asset, shared with the pool.
+    fn set_position(&mut self, position: f64, entry_px: f64) {
+        self.inventory.reset(position, entry_px);
+        self.round_trips.reset(position);
+        self.metrics.record(|values| values.position = position);
+        if let Some(exposure) = &self.exposure {
+            exposure
+                .lock()
+                .unwrap()
+                .update_position(&self.asset, position);
+        }
+    }
+
+    /// Handle to pause and resume trading while [`MarketMaker::start`] runs.
+    #[must_use]
+    pub fn control_handle(&self) -> ControlHandle {
+        ControlHandle(self.control_sender.clone())
+    }
+
+    /// Last known outcome of a recent order by its client order id, as a `0x` hex
+    /// string, updated by its fills. Saves asking the exchange about it.
+    #[must_use]
+    pub fn order_by_cloid(&self, cloid: &str) -> Option<&OrderOutcome> {
+        self.cloids.get(cloid)
+    }
+
+    /// Whether trading is paused by a [`ControlCommand::Pause`].
+    #[must_use]
+    pub const fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Metrics updated as the bot runs, for serving from an endpoint of your own.
+    #[must_use]
+    pub const fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Run the strategy over historical prices through the same signal, checks and
+    /// paper fills as live trading. Needs a bot built with `paper_trading`, so nothing
+    /// reaches the exchange.
+    ///
+    /// Rows are processed as fast as possible, so time-based checks such as cooldowns
+    /// see the whole run as happening at once.
+    pub async fn backtest(&mut self, backtest: Backtest) -> BacktestReport {
+        let mut report = BacktestReport::default();
+        if !self.paper_trading {
+            error!("Backtesting needs paper_trading, not running");
+            return report;
+        }
+
+        for row in backtest.rows {
+            let position = self.inventory.position();
+            self.binance_price.set(row.binance_price);
+            self.on_mid(row.hl_mid).await;
+
+            let traded = self.inventory.position() - position;
+            if traded.abs() > EPSILON {
+                let is_buy = traded > 0.0;
+                report.trades.push(BacktestTrade {
+                    time: row.time,
+                    is_buy,
+                    size: traded.abs(),
+                    price: self.cross_price(is_buy),
+                });
+            }
+            let unrealized =
+                self.inventory.position() * (row.hl_mid - self.inventory.avg_entry_px());
+            report
+                .pnl_curve
+                .push((row.time, self.realized_pnl + unrealized));
+        }
+        report
+    }
+
+    /// Hand a background feed to the bot so `shutdown` stops it cleanly.
+    pub fn attach_feed(&mut self, feed: FeedTask) {
+        self.feeds.push(feed);
+    }
+
+    /// Resume from the state saved in `state_path`, unless it is missing, stale or for
+    /// another asset. A live position is reseeded from the exchange when the bot runs.
+    fn restore_state(&mut self) {
+        let Some(path) = self.state_path.as_deref() else {
+            return;
+        };
+        let now = Utc::now().timestamp_millis();
+        let state = match state::SavedState::load(path, &self.asset, self.state_max_age, now) {
+            Ok(state) => state,
+            Err(e) => {
+                info!("Starting fresh, not resuming from {}: {e}", path.display());
+                return;
+            }
+        };
+        let skip = state.diffs.len().saturating_sub(self.window_size);
+        for &diff in &state.diffs[skip..] {
+            self.diffs.push_back(diff);
+        }
+        if state.ewma.is_some() && self.ewma.is_some() {
+            self.ewma = state.ewma;
+        }
+        self.ticks = state.ticks;
+        self.inventory.reset(state.position, state.avg_entry_px);
+        self.round_trips.reset(state.position);
+        self.realized_pnl = state.realized_pnl;
+        info!(
+            "Resumed {} from {} with {} diffs and position {}",
+            self.asset,
+            path.display(),
+            self.diffs.len(),
+            self.fmt_sz(state.position)
+        );
+    }
+
+    fn save_state(&self) {
+        let Some(path) = self.state_path.as_deref() else {
+            return;
+        };
+        let state = state::SavedState {
+            asset: self.asset.clone(),
+            saved_at: Utc::now().timestamp_millis(),
+            ticks: self.ticks,
+            diffs: self.diffs.values().iter().copied().collect(),
+            ewma: self.ewma.clone(),
+            position: self.inventory.position(),
+            avg_entry_px: self.inventory.avg_entry_px(),
+            realized_pnl: self.realized_pnl,
+        };
+        if let Err(e) = state.save(path) {
+            error!("Error saving state to {}: {e}", path.display());
+        }
+    }
+
+    /// Tear down the bot, bounding the whole sequence by the configured shutdown
+    /// timeout. Steps that don't complete in time are logged and abandoned.
+    pub async fn shutdown(&mut self) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_halt("shutdown");
+        }
+        let deadline = shutdown::ShutdownDeadline::new(self.shutdown_timeout);
+
+        // Protective orders are left in place to guard any remaining position
+        let oids = self.take_working_orders();
+        if !oids.is_empty() && self.dry_run.is_none() {
+            let cancels = oids
+                .iter()
+                .map(|&oid| ClientCancelRequest {
+                    asset: self.asset.clone(),
+                    oid,
+                })
+                .collect();
+            let exchange_client = &self.exchange_client;
+            deadline
+                .run("cancel orders", async {
+                    if let Err(e) = exchange_client.bulk_cancel(cancels, None).await {
+                        error!("Error cancelling orders {oids:?}: {e}");
+                    }
+                })
+                .await;
+        }
+
+        let info_client = &mut self.info_client;
+        let subscription_ids = std::mem::take(&mut self.subscription_ids);
+        deadline
+            .run("unsubscribe", async {
+                for subscription_id in subscription_ids {
+                    if let Err(e) = info_client.unsubscribe(subscription_id).await {
+                        error!("Error unsubscribing {subscription_id}: {e}");
+                    }
+                }
+            })
+            .await;
+
+        let feeds = std::mem::take(&mut self.feeds);
+        deadline
+            .run("stop feeds", async {
+                for feed in feeds {
+                    feed.stop().await;
+                }
+            })
+            .await;
+
+        info!(
+            "Final {} position: {}, realized: {:.2}, unrealized: {:.2}",
+            self.asset,
+            self.fmt_sz(self.inventory.position()),
+            self.realized_pnl,
+            self.unrealized_pnl()
+        );
+        if self.hedge_trader.is_some() {
+            info!(
+                "Final hedge position: {}, combined profit: {:.2}",
+                self.fmt_sz(self.hedge_position),
+                self.combined_pnl()
+            );
+        }
+        self.save_state();
+    }
+
+    /// Orders this run placed that may still rest on the exchange: quotes, the passive
+    /// order and orders from `place_order` that didn't fill immediately.
+    #[must_use]
+    pub fn open_orders(&self) -> usize {
+        self.quotes.orders.len()
+            + usize::from(self.passive_order.is_some())
+            + self.unfilled_orders.len()
+    }
+
+    /// Whether `max_open_orders` are already open, logging that the next order is
+    /// suppressed.
+    fn at_open_order_cap(&self) -> bool {
+        let open = self.open_orders();
+        let at_cap = self.max_open_orders.is_some_and(|max| open >= max);
+        if at_cap {
+            warn!("{open} orders are open, at the maximum, not placing another");
+        }
+        at_cap
+    }
+
+    /// Forgets and returns the oids of every quote, passive order and unfilled order
+    /// this run placed.
+    fn take_working_orders(&mut self) -> Vec<u64> {
+        let mut oids: Vec<u64> = self.quotes.orders.drain(..).map(|(oid, _)| oid).collect();
+        oids.extend(self.passive_order.take().map(|order| order.oid));
+        oids.extend(self.unfilled_orders.drain().map(|(oid, _)| oid));
+        oids
+    }
+
+    async fn process_message(&mut self, message: Message) {
+        match message {
+            Message::AllMids(_) if self.mid_source != MidSource::AllMids => {}
+            Message::AllMids(all_mids) => {
+                let Some(mid_str) = all_mids.data.mids.get(&self.asset) else {
+                    if !self.mid_missing {
+                        error!(
+                            "No mid for {} among the {} assets in AllMids, is the asset name right?",
+                            self.asset,
+                            all_mids.data.mids.len()
+                        );
+                        self.mid_missing = true;
+                    }
+                    return;
+                };
+                if std::mem::take(&mut self.mid_missing) {
+                    info!("Mid for {} is in AllMids again", self.asset);
+                }
+                if let Some(mid) = mid_str
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|mid| is_valid_price(*mid))
+                {
+                    self.on_mid(mid).await;
+                } else {
+                    error!("Invalid mid price for asset {}: {:?}", self.asset, mid_str);
+                }
+            }
+            Message::L2Book(l2_book) if l2_book.data.coin == self.asset => {
+                let mid = self.mid_source.price(&l2_book.data.levels);
+                self.latest_book = Some(l2_book.data);
+                if let Some(mid) = mid {
+                    self.on_mid(mid).await;
+                } else if self.mid_source != MidSource::AllMids {
+                    warn!("No mid in the {} book, a side is empty", self.asset);
+                }
+            }
+            Message::User(user_events) => {
+                // UserEvents are the only source of position updates outside paper
+                // trading, inline order responses are matched off as acknowledgements.
+                for fill in user_events.data.fills {
+                    if fill.coin == self.asset {
+                        self.apply_fill(&fill).await;
+                    }
+                }
+            }
+            _ => {
+                // Other messages are ignored for now
+            }
+        }
+    }
+
+    async fn on_mid(&mut self, mid: f64) {
+        self.latest_mid_price = mid;
+        self.mid_updated_at = Some(Instant::now());
+        let unrealized_pnl = self.unrealized_pnl();
+        self.metrics
+            .record(|values| values.unrealized_pnl = unrealized_pnl);
+        if let Some(exposure) = &self.exposure {
+            exposure.lock().unwrap().update_mark(&self.asset, mid);
+        }
+        self.on_price_update().await;
+    }
+
+    /// Position, profit and order tracking for one fill of this asset. Shared by fills
+    /// from `UserEvents` and simulated paper fills.
+    async fn apply_fill(&mut self, fill: &TradeInfo) {
+        if !self.fill_deduper.insert(fill) {
+            info!(
+                "Ignoring duplicate fill: oid={}, time={}",
+                fill.oid, fill.time
+            );
+            return;
+        }
+        if let Some(trade_log) = self.trade_log.as_mut() {
+            trade_log.fill(fill);
+        }
+        self.last_fill_at = Some(Instant::now());
+        let amount: f64 = fill.sz.parse().unwrap_or(0.0);
+        let px: f64 = fill.px.parse().unwrap_or(0.0);
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_fill(fill.side == "B", amount, px);
+        }
+        if self.inline_fills.confirm(fill.oid, amount) {
+            info!("Fill for oid={} confirms its inline report", fill.oid);
+        }
+        if let Some(passive) = self.passive_order.as_mut() {
+            if passive.oid == fill.oid && passive.on_fill(amount) {
+                self.passive_order = None;
+            }
+        }
+        self.quotes.on_fill(fill.oid, amount);
+        if let Some(cloid) = &fill.cloid {
+            self.cloids.record_fill(cloid, amount, fill.oid);
+        }
+        if let Some((_, remaining)) = self.unfilled_orders.get_mut(&fill.oid) {
+            *remaining -= amount;
+            if *remaining <= EPSILON {
+                self.unfilled_orders.remove(&fill.oid);
+            }
+        }
+        info!(
+            oid = fill.oid,
+            side = %fill.side,
+            size = amount,
+            price = px,
+            "Fill event: side={}, amount={}, px={}",
+            fill.side,
+            self.fmt_sz(amount),
+            self.fmt_px(px)
+        );
+
+        if fill.side == "B" {
+            self.buy_vwap.add(px, amount);
+        } else {
+            self.sell_vwap.add(px, amount);
+        }
+
+        let signed_amount = if fill.side == "B" { amount } else { -amount };
+        let fee = fills::fee(fill, self.maker_rebate_bps);
+        let position_before = self.inventory.position();
+        let realized = self.inventory.apply(signed_amount, px) - fee;
+        self.record_round_trip(signed_amount, px, fill.time);
+        let position_after = self.inventory.position();
+        if position_after.abs() > position_before.abs() + EPSILON
+            || position_after * position_before < 0.0
+        {
+            self.opened_at = Some(Instant::now());
+        }
+        if let Some(budget) = self.daily_budget.as_mut() {
+            // Only the part of the fill beyond closing the old position opens one
+            let closing = if position_before * signed_amount < 0.0 {
+                amount.min(position_before.abs())
+            } else {
+                0.0
+            };
+            budget.record((amount - closing) * px, Utc::now().date_naive());
+        }
+        self.realized_pnl += realized;
+        self.record_realized_pnl(realized, Instant::now());
+        let (position, realized_pnl) = (self.inventory.position(), self.realized_pnl);
+        self.metrics.record(|values| {
+            values.fills += 1;
+            values.position = position;
+            values.realized_pnl = realized_pnl;
+        });
+        if let Some(exposure) = &self.exposure {
+            exposure
+                .lock()
+                .unwrap()
+                .update_position(&self.asset, self.inventory.position());
+        }
+        self.update_protective_orders().await;
+
+        if let Some(hedge) = self
+            .hedge_batcher
+            .as_mut()
+            .and_then(|batcher| batcher.on_fill(signed_amount))
+        {
+            info!(
+                "Unhedged delta crossed threshold, hedge due: {}",
+                self.fmt_sz(hedge)
+            );
+            self.send_hedge(hedge).await;
+        }
+    }
+
+    /// Offset `hedge`, signed positive to buy, on Binance and book the leg. Whatever
+    /// isn't filled carries over into the next hedge.
+    async fn send_hedge(&mut self, hedge: f64) {
+        let Some(trader) = &self.hedge_trader else {
+            return;
+        };
+        let is_buy = hedge > 0.0;
+        let (size, px) = if self.paper_trading || self.dry_run.is_some() {
+            (trader.round_quantity(hedge), self.binance_price.get().price)
+        } else {
+            match trader.market_order(is_buy, hedge.abs()).await {
+                Ok(order) => order.filled(),
+                Err(e) => {
+                    error!("Error sending hedge of {}: {e}", self.fmt_sz(hedge));
+                    (0.0, 0.0)
+                }
+            }
+        };
+        let signed_size = if is_buy { size } else { -size };
+        self.hedge_position += signed_size;
+        self.hedge_cash -= signed_size * px;
+        if let Some(batcher) = &mut self.hedge_batcher {
+            batcher.carry(signed_size - hedge);
+        }
+        if size > 0.0 {
+            info!(
+                "Hedged {} on Binance at {}, hedge position: {}",
+                self.fmt_sz(signed_size),
+                self.fmt_px(px),
+                self.fmt_sz(self.hedge_position)
+            );
+        }
+    }
+
+    /// Flatten the position if its unrealized loss has passed `stop_loss_bps`, its
+    /// profit `take_profit_bps`, or it has been held for `max_hold_time`. Returns
+    /// whether it tried to.
+    async fn check_position_exit(&mut self) -> bool {
+        if self.stop_loss_bps.is_none()
+            && self.take_profit_bps.is_none()
+            && self.max_hold_time.is_none()
+        {
+            return false;
+        }
+        let position = self.inventory.position();
+        if self
+            .exit_sent_for
+            .is_some_and(|sent_for| (sent_for - position).abs() < EPSILON)
+        {
+            return false;
+        }
+        self.exit_sent_for = None;
+        if position.abs() < EPSILON || !is_valid_price(self.latest_mid_price) {
+            return false;
+        }
+        let held = self.opened_at.get_or_insert_with(Instant::now).elapsed();
+        let entry_px = self.inventory.avg_entry_px();
+        let pnl_bps = protective::unrealized_bps(position, entry_px, self.latest_mid_price);
+        let exit = if self.max_hold_time.is_some_and(|max| held >= max) {
+            "Time stop"
+        } else if held < self.min_hold {
+            return false;
+        } else if self.stop_loss_bps.is_some_and(|bps| pnl_bps <= -bps) {
+            "Stop-loss"
+        } else if self.take_profit_bps.is_some_and(|bps| pnl_bps >= bps) {
+            "Take-profit"
+        } else {
+            return false;
+        };
+        warn!(
+            "{exit}: {} position held for {held:.0?} is {pnl_bps:.1} bps from its entry at {}, flattening",
+            self.fmt_sz(position),
+            self.fmt_px(entry_px)
+        );
+        let realized_before = self.realized_pnl;
+        if self.flatten().await {
+            self.exit_sent_for = Some(position);
+            // Paper fills are booked right away, live ones when they arrive
+            let realized = if self.paper_trading {
+                self.realized_pnl - realized_before
+            } else {
+                self.unrealized_pnl()
+            };
+            info!(
+                "{exit} closed {}, realizing ~{realized:.2}",
+                self.fmt_sz(position)
+            );
+        }
+        self.last_trade_at = Some(Instant::now());
+        true
+    }
+
+    /// Cancel and re-place the protective orders if the position or its average entry
+    /// has changed materially since they were placed.
+    async fn update_protective_orders(&mut self) {
+        if self.paper_trading
+            || (self.protective_stop_bps.is_none() && self.protective_take_profit_bps.is_none())
+        {
+            return;
+        }
+        let position = self.inventory.position();
+        let entry_px = self.inventory.avg_entry_px();
+        if !protective::needs_reprice(
+            self.protective_orders.as_ref(),
+            position,
+            entry_px,
+            self.protective_reprice_bps,
+        ) {
+            return;
+        }
+
+        if let Some(orders) = self.protective_orders.take() {
+            for oid in orders.oids {
+                let cancel = ClientCancelRequest {
+                    asset: self.asset.clone(),
+                    oid,
+                };
+                if let Err(e) = self.exchange_client.cancel(cancel, None).await {
+                    error!("Error cancelling protective order {oid}: {e}");
+                }
+            }
+        }
+        if position.abs() < EPSILON {
+            return;
+        }
+
+        let is_long = position > 0.0;
+        let mut oids = Vec::new();
+        for (is_stop, bps) in [
+            (true, self.protective_stop_bps),
+            (false, self.protective_take_profit_bps),
+        ] {
+            let Some(bps) = bps else {
+                continue;
+            };
+            let trigger_px = self.round_px(protective::trigger_px(entry_px, is_long, is_stop, bps));
+            let order = ClientOrderRequest {
+                asset: self.asset.clone(),
+                is_buy: !is_long,
+                reduce_only: self.market_kind.reduce_only(true),
+                limit_px: trigger_px,
+                sz: position.abs(),
+                cloid: None,
+                order_type: ClientOrder::Trigger(ClientTrigger {
+                    trigger_px,
+                    is_market: true,
+                    tpsl: if is_stop { "sl" } else { "tp" }.to_string(),
+                }),
+            };
+            match self.exchange_client.order(order, None).await {
+                Ok(ExchangeResponseStatus::Ok(response)) => {
+                    if let Some(ExchangeDataStatus::Resting(resting)) = response
+                        .data
+                        .and_then(|data| data.statuses.into_iter().next())
+                    {
+                        oids.push(resting.oid);
+                    }
+                }
+                Ok(ExchangeResponseStatus::Err(e)) => {
+                    error!("Error placing protective order: {e}");
+                }
+                Err(e) => error!("Error placing protective order: {e}"),
+            }
+        }
+        if oids.is_empty() {
+            // Nothing is resting, try again on the next fill
+            return;
+        }
+        info!(
+            "Re-priced protective orders for position {} at entry {}",
+            self.fmt_sz(position),
+            self.fmt_px(entry_px)
+        );
+        self.protective_orders = Some(protective::ProtectiveOrders {
+            entry_px,
+            position,
+            oids,
+        });
+    }
+
+    /// One price update, decision and order cycle, in a span carrying the asset, tick
+    /// and z-score so its order and fill events can be correlated.
+    async fn on_price_update(&mut self) {
+        let span =
+            info_span!("price_update", asset = %self.asset, tick = field::Empty, z = field::Empty);
+        self.update_and_decide().instrument(span).await;
+    }
+
+    async fn update_and_decide(&mut self) {
+        let reference_age = self
+            .binance_price
+            .get()
+            .updated_at
+            .map(|updated_at| updated_at.elapsed());
+        self.metrics
+            .record(|values| values.reference_age = reference_age);
+        self.roll_return_interval(Instant::now());
+        self.escalate_passive_order(Instant::now()).await;
+        self.sweep_resting_orders(Instant::now()).await;
+        self.refresh_funding_rate(Instant::now()).await;
+        self.refresh_balance(Instant::now()).await;
+        self.log_stats(Instant::now());
+        // An exit wins over acting on the signal this tick
+        if self.check_position_exit().await {
+            return;
+        }
+        if self.strategy == StrategyMode::Quoting {
+            if !self.paused && !self.signal_only() && !self.session_parked() {
+                self.quote().await;
+            }
+            return;
+        }
+        let hl_price = self.latest_mid_price;
+        let binance_price = self.reference_price();
+
+        if !is_valid_price(binance_price) {
+            return; // can't compute a relative diff without a sane reference price
+        }
+
+        let diff = (hl_price - binance_price) / binance_price;
+        if self.is_outlier(diff) {
+            return;
+        }
+        self.record_tick(hl_price, binance_price);
+
+        // Update rolling window
+        let now = Instant::now();
+        self.push_diff(diff, now);
+        let long_z = self.update_long_window(diff);
+
+        // Compute mean and stddev
+        let (mean, stddev) = if let Some(ewma) = self.ewma.as_mut() {
+            ewma.push(diff);
+            if !ewma.is_warm() {
+                return;
+            }
+            (ewma.mean(), ewma.stddev())
+        } else if let Some(prior) = self.window_prior {
+            let (live_mean, live_stddev) = self.window_mean_stddev();
+            prior.blend(live_mean, live_stddev, self.diffs.len(), self.window_size)
+        } else if !self.is_warm() {
+            // Wait until we have a full window
+            return;
+        } else {
+            self.window_mean_stddev()
+        };
+        let Some(signal) = self.entry_signal(diff, mean, stddev, Utc::now().time()) else {
+            return;
+        };
+        let z = signal.z();
+        if let Some(z) = z {
+            Span::current().record("z", z);
+        }
+        if self.sample_tick_log(signal) {
+            debug!(
+                hl_price,
+                binance_price,
+                diff,
+                mean,
+                stddev,
+                %signal,
+                "hl={} binance={} diff={diff:.6} mean={mean:.6} stddev={stddev:.6} {signal}",
+                self.fmt_px(hl_price),
+                self.fmt_px(binance_price)
+            );
+        }
+        self.last_signal = Some(trade_log::SignalSnapshot {
+            z,
+            mean,
+            stddev,
+            funding_rate: self.funding_rate,
+        });
+        // Band signals are in bps, not a z-score to report as one
+        if let Some(z) = z {
+            if let Some(observer) = self.observer.as_mut() {
+                observer.on_signal(z, mean, stddev);
+            }
+            self.metrics.record(|values| values.z_score = z);
+        }
+        self.signal_at = Some((now, reference_age));
+        let edge_bps = fraction_to_bps(diff - mean).abs();
+        let outcome = self
+            .act_on_signal(signal, long_z, binance_price, edge_bps)
+            .await;
+        self.signal_at = None;
+        self.decisions.push(Decision {
+            time: Utc::now().timestamp_millis(),
+            hl_mid: hl_price,
+            reference_price: binance_price,
+            reference_age,
+            diff,
+            signal,
+            edge_bps,
+            fee_bps: self.round_trip_fee_bps(),
+            outcome,
+        });
+    }
+
+    /// Whether to log this tick at debug: always when it signals a trade, otherwise
+    /// one in `log_sample_rate` of the ticks that don't, starting with the first.
+    fn sample_tick_log(&mut self, signal: EntrySignal) -> bool {
+        if self.trade_intent(signal).is_some() {
+            return true;
+        }
+        let sampled = self.quiet_ticks.is_multiple_of(self.log_sample_rate);
+        self.quiet_ticks = self.quiet_ticks.wrapping_add(1);
+        sampled
+    }
+
+    /// Returns true if `diff` is too far from the rolling window to be trusted, until
+    /// `outlier_confirm_ticks` outliers in a row show the regime has shifted.
+    fn is_outlier(&mut self, diff: f64) -> bool {
+        let Some(k) = self.outlier_k else {
+            return false;
+        };
+        let stddev = self.diffs.stddev();
+        if stddev < EPSILON || (diff - self.diffs.mean()).abs() <= k * stddev {
+            self.outlier_streak = 0;
+            return false;
+        }
+        self.outlier_streak += 1;
+        if self.outlier_streak >= self.outlier_confirm_ticks {
+            info!(
+                "{} consecutive outlying diffs, accepting {diff:.6} as a regime shift",
+                self.outlier_streak
+            );
+            self.outlier_streak = 0;
+            return false;
+        }
+        warn!(
+            "Dropping diff {diff:.6}, more than {k} standard deviations from the rolling mean {:.6}",
+            self.diffs.mean()
+        );
+        true
+    }
+
+    /// Mean and stddev of the rolling window, weighted by recency in decay mode.
+    fn window_mean_stddev(&self) -> (f64, f64) {
+        self.decay_half_life.map_or_else(
+            || (self.diffs.mean(), self.diffs.stddev()),
+            |half_life| self.diffs.decayed_mean_stddev(half_life),
+        )
+    }
+
+    /// Push `diff` into the rolling window, evicting what falls out of it, and into the
+    /// recent window volatility scaling measures.
+    fn push_diff(&mut self, diff: f64, now: Instant) {
+        match self.window_mode {
+            WindowMode::Count => {
+                if self.diffs.len() >= self.window_size {
+                    self.diffs.pop_front();
+                }
+            }
+            WindowMode::Time(duration) => {
+                if let Some(cutoff) = now.checked_sub(duration) {
+                    self.diffs.evict_before(cutoff);
+                }
+                if self.diffs.is_empty() {
+                    self.window_started_at = Some(now);
+                }
+            }
+        }
+        self.diffs.push_back_at(diff, now);
+        if let Some(scaling) = self.volatility_scaling {
+            if self.recent_diffs.len() >= scaling.recent_window {
+                self.recent_diffs.pop_front();
+            }
+            self.recent_diffs.push_back(diff);
+        }
+    }
+
+    /// Trade on `signal` if it crosses its threshold and every check allows it. `edge_bps`
+    /// is the distance of the diff from its mean, the profit expected from reversion.
+    ///
+    /// Whether to trade is decided by [`MarketMaker::trade_intent`] alone, without side
+    /// effects, and only [`MarketMaker::act_on_intent`] sends orders.
+    async fn act_on_signal(
+        &mut self,
+        signal: EntrySignal,
+        long_z: Option<f64>,
+        binance_price: f64,
+        edge_bps: f64,
+    ) -> DecisionOutcome {
+        let Some(intent) = self.trade_intent(signal) else {
+            // No trade
+            if let Some(confirmation) = self.reference_confirmation.as_mut() {
+                confirmation.reset();
+            }
+            self.signal_debounce.reset();
+            return DecisionOutcome::NoSignal;
+        };
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_intent(&intent);
+        }
+        self.act_on_intent(intent, long_z, binance_price, edge_bps)
+            .await
+    }
+
+    /// Run a trade intent through the risk and execution checks and send it if they
+    /// all pass.
+    async fn act_on_intent(
+        &mut self,
+        intent: TradeIntent,
+        long_z: Option<f64>,
+        binance_price: f64,
+        edge_bps: f64,
+    ) -> DecisionOutcome {
+        let TradeIntent { is_buy, signal, .. } = intent;
+        // Counted on every signal tick, whatever blocks trading on it
+        let persisted = self.signal_debounce.observe(is_buy);
+
+        if let Some(reason) = self.stood_down(&intent) {
+            return DecisionOutcome::Blocked(reason);
+        }
+        if intent.size < EPSILON {
+            warn!("Trade size is zero, is the account balance known?");
+            return DecisionOutcome::Blocked("size");
+        }
+        if !persisted {
+            debug!("Signal {signal} hasn't persisted for enough ticks yet, not trading");
+            return DecisionOutcome::Blocked("confirm_ticks");
+        }
+
+        if self.ticks < self.warmup_ticks {
+            info!(
+                "Warming up, {} of {} ticks observed, not trading",
+                self.ticks, self.warmup_ticks
+            );
+            return DecisionOutcome::Blocked("warmup");
+        }
+        if let Some(correlation) = self.decoupled() {
+            info!("Correlation with the reference fell to {correlation:.2}, not trading");
+            return DecisionOutcome::Blocked("correlation");
+        }
+        if self.long_window_size.is_some() {
+            // Only the direction of the short signal is compared, in whichever unit
+            let short = signal.level().0;
+            let agree = long_z.is_some_and(|long_z| {
+                stats::timeframes_agree(short, long_z, self.long_z_threshold)
+            });
+            if !agree {
+                info!("Long timeframe z={long_z:?} doesn't confirm {signal}, not trading");
+                return DecisionOutcome::Blocked("long_timeframe");
+            }
+        }
+        if let Some(confirmation) = self.reference_confirmation.as_mut() {
+            if !confirmation.confirm(is_buy, binance_price) {
+                return DecisionOutcome::Blocked("reference_confirmation");
+            }
+        }
+        if !self.book_allows_trading() {
+            return DecisionOutcome::Blocked("one_sided_book");
+        }
+        if !self.imbalance_allows(is_buy) {
+            return DecisionOutcome::Blocked("book_imbalance");
+        }
+        if self.in_post_fill_quiet(Instant::now()) {
+            info!("Signal within the post-fill quiet window, deferring trade");
+            return DecisionOutcome::Blocked("post_fill_quiet");
+        }
+        if self.in_trade_cooldown(Instant::now()) {
+            debug!("Signal within the minimum trade interval, not trading");
+            return DecisionOutcome::Blocked("trade_cooldown");
+        }
+        if !self.pool_allows_entry() {
+            return DecisionOutcome::Blocked("max_active_assets");
+        }
+        if self.reconnect_cooling_down(Instant::now()) {
+            info!("Reconnects are cooling down, not trading");
+            return DecisionOutcome::Blocked("reconnect_cooldown");
+        }
+        if let Some(reason) = self.edge_shortfall(is_buy, edge_bps) {
+            return DecisionOutcome::Blocked(reason);
+        }
+        let headroom = self.position_headroom(is_buy, intent.size);
+        if headroom.clamped_size < EPSILON {
+            info!(
+                "Position {} is at the cap of {}, skipping trade",
+                self.fmt_sz(headroom.position),
+                self.fmt_sz(headroom.cap)
+            );
+            return DecisionOutcome::Blocked("position_cap");
+        }
+        if !self.exposure_allows(is_buy, headroom.clamped_size) {
+            return DecisionOutcome::Blocked("exposure");
+        }
+        if self.recheck_edge && !self.edge_persists(is_buy) {
+            return DecisionOutcome::Blocked("edge_collapsed");
+        }
+
+        if let ExecutionStyle::PostThenCross { .. } = self.execution_style {
+            if self.passive_order.is_some() {
+                return DecisionOutcome::Blocked("passive_order_pending");
+            }
+            self.last_trade_at = Some(Instant::now());
+            self.post_passive_order(is_buy, headroom.clamped_size).await;
+            return DecisionOutcome::Posted { is_buy, headroom };
+        }
+
+        self.last_trade_at = Some(Instant::now());
+        let filled = self
+            .execute_immediate_trade(is_buy, headroom.clamped_size)
+            .await;
+        DecisionOutcome::Traded {
+            is_buy,
+            filled,
+            headroom,
+        }
+    }
+
+    /// Whether the freshest prices still put the diff on the side of its mean the
+    /// `is_buy` signal was taken on: Hyperliquid cheap for a buy, rich for a sell.
+    fn edge_persists(&self, is_buy: bool) -> bool {
+        let Some(mean) = self.last_signal.map(|signal| signal.mean) else {
+            return true;
+        };
+        let reference = self.reference_price();
+        if !is_valid_price(reference) || !is_valid_price(self.latest_mid_price) {
+            return false;
+        }
+        let diff = (self.latest_mid_price - reference) / reference;
+        let persists = if is_buy { diff < mean } else { diff > mean };
+        if !persists {
+            info!(
+                "Edge collapsed before sending, diff {diff:.6} is back past the mean {mean:.6}, not trading"
+            );
+        }
+        persists
+    }
+
+    /// Trade a signal calls for, leaning harder into stronger mispricings and skewing
+    /// the threshold toward flat inventory. The position cap is applied afterwards.
+    fn trade_intent(&self, signal: EntrySignal) -> Option<TradeIntent> {
+        let inventory = if self.max_absolute_position_size > EPSILON {
+            self.position() / self.max_absolute_position_size
+        } else {
+            0.0
+        };
+        // A signal above the mean is a sell signal
+        let (level, threshold) = signal.level();
+        let threshold =
+            decisions::skewed_threshold(threshold, level < 0.0, inventory, self.inventory_skew);
+        decisions::trade_intent(
+            signal.with_threshold(threshold),
+            self.base_trade_size(),
+            self.max_size_multiplier,
+        )
+    }
+
+    /// Size a signal at its threshold trades, zero until the balance is known in
+    /// equity mode.
+    fn base_trade_size(&self) -> f64 {
+        match self.size_mode {
+            SizeMode::Fixed(size) => size,
+            SizeMode::FractionOfEquity(fraction) => self.withdrawable.map_or(0.0, |withdrawable| {
+                decisions::equity_size(
+                    fraction,
+                    withdrawable,
+                    self.latest_mid_price,
+                    self.max_order_notional,
+                )
+            }),
+        }
+    }
+
+    /// Fetch the withdrawable collateral once `balance_refresh` has passed since the
+    /// last query, when sizing off equity.
+    async fn refresh_balance(&mut self, now: Instant) {
+        if !matches!(self.size_mode, SizeMode::FractionOfEquity(_))
+            || self
+                .balance_checked_at
+                .is_some_and(|checked_at| now.duration_since(checked_at) < self.balance_refresh)
+        {
+            return;
+        }
+        self.balance_checked_at = Some(now);
+        match self.info_client.user_state(self.user_address).await {
+            Ok(user_state) => match user_state.withdrawable.parse::<f64>() {
+                Ok(withdrawable) => {
+                    debug!("Withdrawable collateral is {withdrawable:.2}");
+                    self.withdrawable = Some(withdrawable);
+                }
+                Err(e) => warn!(
+                    "Error parsing withdrawable collateral {}: {e}",
+                    user_state.withdrawable
+                ),
+            },
+            Err(e) => warn!("Error fetching the account balance: {e}"),
+        }
+    }
+
+    /// Signal to decide an entry on: the z-score in sigma mode, or the distance from the
+    /// rolling mean in bps in band mode. `None` if the window varies too little to
+    /// measure a z-score against.
+    ///
+    /// The band is volatility-independent, so the stddev floor, the time-of-day
+    /// schedule and volatility scaling, all in sigma, only apply to the z-score. The
+    /// loss penalty widens either threshold.
+    fn entry_signal(
+        &self,
+        diff: f64,
+        mean: f64,
+        stddev: f64,
+        now: NaiveTime,
+    ) -> Option<EntrySignal> {
+        let loss_multiplier = self.loss_multiplier(Instant::now());
+        if let Some(band_bps) = self.entry_band_bps {
+            return Some(EntrySignal::Band {
+                bps: fraction_to_bps(diff - mean),
+                threshold_bps: band_bps * loss_multiplier,
+            });
+        }
+        if self.too_quiet(mean, stddev) {
+            return None;
+        }
+        let threshold = self.z_threshold_at(now)
+            * self.volatility_scaling.map_or(1.0, |scaling| {
+                scaling.multiplier(self.recent_diffs.stddev(), stddev)
+            })
+            * loss_multiplier;
+        Some(EntrySignal::ZScore {
+            z: (diff - mean) / stddev,
+            threshold,
+        })
+    }
+
+    /// Whether the window varies too little for a z-score over it to mean anything:
+    /// below the absolute floor, or small next to the mean it varies around.
+    fn too_quiet(&self, mean: f64, stddev: f64) -> bool {
+        if stddev < EPSILON {
+            return true;
+        }
+        let floor = self
+            .min_stddev_bps
+            .map_or(0.0, bps_to_fraction)
+            .max(self.min_stddev_mean_fraction.unwrap_or(0.0) * mean.abs());
+        if stddev < floor {
+            debug!(
+                "Rolling stddev of {:.3} bps is below the floor of {:.3} bps, window too quiet to trust",
+                fraction_to_bps(stddev),
+                fraction_to_bps(floor)
+            );
+            return true;
+        }
+        false
+    }
+
+    /// Why an `edge_bps` deviation isn't worth trading on: wider than is believable,
+    /// below the minimum edge, not covering the round-trip fees, or eaten by the
+    /// expected funding.
+    fn edge_shortfall(&self, is_buy: bool, edge_bps: f64) -> Option<&'static str> {
+        if self.max_bps_diff_band && edge_bps > f64::from(self.max_bps_diff) {
+            warn!(
+                "Deviation of {edge_bps:.2} bps is beyond max_bps_diff of {} bps, likely bad data or decoupled markets, not trading",
+                self.max_bps_diff
+            );
+            return Some("max_bps_diff");
+        }
+        if edge_bps <= self.min_edge_bps {
+            info!(
+                "Edge of {edge_bps:.2} bps is below the minimum of {:.2} bps, not trading",
+                self.min_edge_bps
+            );
+            return Some("min_edge");
+        }
+        let fee_bps = self.round_trip_fee_bps();
+        if fee_bps > 0.0 && edge_bps <= fee_bps {
+            info!("Edge of {edge_bps:.2} bps doesn't cover {fee_bps:.2} bps of round-trip fees, not trading");
+            return Some("fees");
+        }
+        if !self.funding_allows(is_buy, edge_bps - fee_bps) {
+            return Some("funding");
+        }
+        None
+    }
+
+    /// Fair value from the configured source, or else the reference venue's price.
+    /// Zero if there is none yet, or the price is stale.
+    fn reference_price(&self) -> f64 {
+        let Some(source) = &self.fair_value else {
+            let reference = self.binance_price.get();
+            if reference.is_stale(Instant::now(), self.max_price_age) {
+                let source = match self.reference_venue {
+                    ReferenceVenue::Binance => self.binance_price_source.as_str(),
+                    ReferenceVenue::Coinbase => "ticker",
+                };
+                warn!(
+                    "{} {source} price is older than {:?}, not using it",
+                    self.reference_venue.as_str(),
+                    self.max_price_age
+                );
+                return 0.0;
+            }
+            return reference.price;
+        };
+        source.fair_value().unwrap_or(0.0)
+    }
+
+    /// Penalty on the z-score threshold for the most recent losing trade, one without
+    /// one.
+    fn loss_multiplier(&self, now: Instant) -> f64 {
+        self.last_loss_at.map_or(1.0, |loss_at| {
+            signal::loss_penalty(
+                self.loss_penalty,
+                self.loss_penalty_half_life,
+                now.saturating_duration_since(loss_at),
+            )
+        })
+    }
+
+    /// Active z-score threshold at the UTC time of day `now`.
+    fn z_threshold_at(&self, now: NaiveTime) -> f64 {
+        signal::z_threshold_at(&self.z_threshold_schedule, now, self.z_threshold)
+    }
+
+    /// Push `diff` into the long window and return its z-score once the window is full.
+    fn update_long_window(&mut self, diff: f64) -> Option<f64> {
+        let long_window_size = self.long_window_size?;
+        if self.long_diffs.len() == long_window_size {
+            self.long_diffs.pop_front();
+        }
+        self.long_diffs.push_back(diff);
+        if self.long_diffs.len() < long_window_size {
+            return None;
+        }
+        let (mean, stddev) = (self.long_diffs.mean(), self.long_diffs.stddev());
+        (stddev >= EPSILON).then(|| (diff - mean) / stddev)
+    }
+
+    /// Headroom under the absolute position cap for a trade of `size` on the `is_buy`
+    /// side, logged when configured.
+    fn position_headroom(&self, is_buy: bool, size: f64) -> PositionHeadroom {
+        let headroom = self.market_kind.headroom(
+            self.inventory.position(),
+            self.max_absolute_position_size,
+            is_buy,
+            size,
+        );
+        if self.log_position_headroom {
+            info!(
+                "Position {} cap {} headroom {} on the {} side, size {} clamped to {}",
+                self.fmt_sz(headroom.position),
+                self.fmt_sz(headroom.cap),
+                self.fmt_sz(headroom.headroom),
+                if is_buy { "buy" } else { "sell" },
+                self.fmt_sz(headroom.requested_size),
+                self.fmt_sz(headroom.clamped_size)
+            );
+        }
+        headroom
+    }
+
+    /// Returns false if this asset is flat and the pool already has the maximum number
+    /// of assets with a position.
+    fn pool_allows_entry(&self) -> bool {
+        let Some(exposure) = &self.exposure else {
+            return true;
+        };
+        if exposure.lock().unwrap().allows_entry(&self.asset) {
+            return true;
+        }
+        info!(
+            "Maximum number of active assets reached, suppressing entry on {}",
+            self.asset
+        );
+        false
+    }
+
+    /// Returns false if the trade would breach the shared exposure budget.
+    fn exposure_allows(&self, is_buy: bool, size: f64) -> bool {
+        let Some(exposure) = &self.exposure else {
+            return true;
+        };
+        let signed_size = if is_buy { size } else { -size };
+        if exposure
+            .lock()
+            .unwrap()
+            .allows(&self.asset, signed_size, self.latest_mid_price)
+        {
+            return true;
+        }
+        info!(
+            "Trading {} {} would breach the exposure budget, not trading",
+            self.fmt_sz(signed_size),
+            self.asset
+        );
+        false
+    }
+
+    fn reconnect_cooling_down(&self, now: Instant) -> bool {
+        self.reconnect_limiter
+            .as_ref()
+            .is_some_and(|limiter| limiter.lock().unwrap().is_cooling_down(now))
+    }
+
+    /// Returns true if our last fill was less than `post_fill_quiet` before `now`.
+    fn in_post_fill_quiet(&self, now: Instant) -> bool {
+        self.last_fill_at
+            .is_some_and(|last_fill_at| now.duration_since(last_fill_at) < self.post_fill_quiet)
+    }
+
+    /// Fees paid entering and later exiting a position, in bps: the taker fee on both
+    /// legs, or with `post_only` the maker rebate earned on both.
+    fn round_trip_fee_bps(&self) -> f64 {
+        if self.post_only {
+            -2.0 * self.maker_rebate_bps
+        } else {
+            2.0 * self.taker_fee_bps
+        }
+    }
+
+    /// Returns false if the funding expected while holding the position would eat up
+    /// `edge_bps`, the edge left after fees. Funding that would be earned counts toward
+    /// the edge.
+    fn funding_allows(&self, is_buy: bool, edge_bps: f64) -> bool {
+        let Some(funding_rate) = self.funding_rate.filter(|_| self.funding_refresh.is_some())
+        else {
+            return true;
+        };
+        let funding_bps =
+            decisions::funding_cost_bps(funding_rate, is_buy, self.expected_holding_time);
+        if edge_bps > funding_bps {
+            return true;
+        }
+        info!(
+            "Edge of {edge_bps:.2} bps doesn't cover {funding_bps:.2} bps of expected funding, not trading"
+        );
+        false
+    }
+
+    /// Fetch the latest funding rate once `funding_refresh` has passed since the last
+    /// query. The previous rate is kept on failure.
+    async fn refresh_funding_rate(&mut self, now: Instant) {
+        // Spot pairs pay no funding
+        let Some(refresh) = self
+            .funding_refresh
+            .filter(|_| self.market_kind == MarketKind::Perp)
+        else {
+            return;
+        };
+        if self
+            .funding_checked_at
+            .is_some_and(|checked_at| now.duration_since(checked_at) < refresh)
+        {
+            return;
+        }
+        self.funding_checked_at = Some(now);
+        // Funding is paid hourly, so the last two hours hold the latest rate
+        let start_time =
+            u64::try_from((Utc::now() - chrono::Duration::hours(2)).timestamp_millis())
+                .unwrap_or(0);
+        match self
+            .info_client
+            .funding_history(self.asset.clone(), start_time, None)
+            .await
+        {
+            Ok(history) => {
+                let latest = history
+                    .iter()
+                    .max_by_key(|funding| funding.time)
+                    .and_then(|funding| funding.funding_rate.parse::<f64>().ok());
+                if let Some(funding_rate) = latest {
+                    debug!("{} funding rate is {funding_rate}", self.asset);
+                    self.funding_rate = Some(funding_rate);
+                }
+            }
+            Err(e) => warn!("Error fetching the {} funding rate: {e}", self.asset),
+        }
+    }
+
+    /// Latest hourly funding rate, if funding is checked and one was fetched.
+    #[must_use]
+    pub const fn funding_rate(&self) -> Option<f64> {
+        self.funding_rate
+    }
+
+    fn in_trade_cooldown(&self, now: Instant) -> bool {
+        self.last_trade_at.is_some_and(|last_trade_at| {
+            now.duration_since(last_trade_at) < self.min_trade_interval
+        })
+    }
+
+    /// Returns false if the stored book is one-sided within the configured band.
+    fn book_allows_trading(&self) -> bool {
+        if self.book_one_sided() {
+            info!(
+                "Book for {} is one-sided within {} bps of {}, not trading",
+                self.asset,
+                self.one_sided_book_band_bps.unwrap_or_default(),
+                self.fmt_px(self.latest_mid_price)
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Whether the stored book is one-sided within the configured band, false without
+    /// a band or a book.
+    fn book_one_sided(&self) -> bool {
+        let (Some(band_bps), Some(book)) = (self.one_sided_book_band_bps, &self.latest_book) else {
+            return false;
+        };
+        book::is_one_sided(&book.levels, self.latest_mid_price, band_bps)
+    }
+
+    /// Imbalance of the latest book over the configured levels, if there is one.
+    fn book_imbalance(&self) -> Option<f64> {
+        let book = self.latest_book.as_ref()?;
+        book::imbalance(&book.levels, self.imbalance_levels)
+    }
+
+    /// Returns false if the book leans against a trade beyond the threshold. Allowed
+    /// while no book has arrived yet.
+    fn imbalance_allows(&self, is_buy: bool) -> bool {
+        let (Some(threshold), Some(imbalance)) = (self.imbalance_threshold, self.book_imbalance())
+        else {
+            return true;
+        };
+        let against = if is_buy { -imbalance } else { imbalance };
+        if against > threshold {
+            info!(
+                "Book imbalance {imbalance:.2} leans against a {} beyond {threshold}, not trading",
+                if is_buy { "buy" } else { "sell" }
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Post a post-only order at the touch, to be escalated to a cross if it doesn't
+    /// fill in time.
+    async fn post_passive_order(&mut self, is_buy: bool, size: f64) {
+        let book_side = usize::from(!is_buy);
+        let touch = self
+            .latest_book
+            .as_ref()
+            .and_then(|book| book.levels.get(book_side)?.first()?.px.parse::<f64>().ok())
+            .unwrap_or(self.latest_mid_price);
+        let px = self.round_px(touch);
+        let size = self.round_sz(size);
+
+        if let Some(oid) = self.post_only_order(is_buy, px, size).await {
+            info!(
+                "Posted passive {} of {} at {}",
+                if is_buy { "buy" } else { "sell" },
+                self.fmt_sz(size),
+                self.fmt_px(px)
+            );
+            self.passive_order = Some(execution::PassiveOrder {
+                oid,
+                is_buy,
+                remaining: size,
+                placed_at: Instant::now(),
+            });
+        }
+    }
+
+    /// Whether an order of `notional` fits in what is left of the daily budget.
+    fn daily_budget_allows(&mut self, notional: f64) -> bool {
+        self.daily_budget
+            .as_mut()
+            .is_none_or(|budget| budget.allows(notional, Utc::now().date_naive()))
+    }
+
+    /// Send an add-liquidity-only order and return its oid if it rests.
+    async fn post_only_order(&mut self, is_buy: bool, px: f64, size: f64) -> Option<u64> {
+        if self.at_open_order_cap() || !self.daily_budget_allows(size * px) {
+            return None;
+        }
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_order(is_buy, size, px);
+        }
+        self.metrics.record(|values| values.orders += 1);
+        let oid = self.send_post_only_order(is_buy, px, size).await;
+        let result = oid.map_or_else(
+            || "rejected".to_string(),
+            |oid| format!("resting oid {oid}"),
+        );
+        self.log_order(is_buy, size, px, &result);
+        oid
+    }
+
+    async fn send_post_only_order(&mut self, is_buy: bool, px: f64, size: f64) -> Option<u64> {
+        if !self.acquire_order_token() {
+            return None;
+        }
+        let response = if let Some(fill_model) = self.dry_run.as_mut() {
+            fill_model.post()
+        } else {
+            self.exchange_client
+                .order(
+                    ClientOrderRequest {
+                        asset: self.asset.clone(),
+                        is_buy,
+                        reduce_only: false,
+                        limit_px: px,
+                        sz: size,
+                        cloid: None,
+                        order_type: ClientOrder::Limit(ClientLimit {
+                            tif: TimeInForce::Alo.as_str().to_string(),
+                        }),
+                    },
+                    None,
+                )
+                .await
+        };
+
+        match response {
+            Ok(ExchangeResponseStatus::Ok(response)) => {
+                match response
+                    .data
+                    .and_then(|data| data.statuses.into_iter().next())
+                {
+                    Some(ExchangeDataStatus::Resting(resting)) => return Some(resting.oid),
+                    Some(ExchangeDataStatus::Error(e)) => {
+                        let category = self.record_reject(&e).await;
+                        if category == RejectCategory::WouldCross {
+                            // The touch moved through the price, nothing to fill
+                            info!("Passive order would have crossed, not posted: {e}");
+                        } else {
+                            error!("Passive order error ({}): {e}", category.as_str());
+                        }
+                    }
+                    status => error!("Unexpected passive order status: {status:?}"),
+                }
+            }
+            Ok(ExchangeResponseStatus::Err(e)) => {
+                let category = self.record_reject(&e).await;
+                error!("Error posting passive order ({}): {e}", category.as_str());
+            }
+            Err(e) => error!("Error posting passive order: {e}"),
+        }
+        None
+    }
+
+    /// Rest a bid and an ask around the latest mid, cancelling and replacing them once
+    /// the mid has moved more than `max_bps_diff` since they were placed.
+    pub async fn quote(&mut self) {
+        let mid = self.latest_mid_price;
+        if !is_valid_price(mid) {
+            return;
+        }
+        if self.book_one_sided() {
+            // Quoting into an empty side is quoting blind, pull the quotes until it refills
+            if !self.quotes.orders.is_empty() {
+                info!(
+                    "Book for {} is one-sided, cancelling the quotes",
+                    self.asset
+                );
+                if self.cancel_quotes().await {
+                    self.quotes.mid = None;
+                }
+            }
+            return;
+        }
+        // Requoting while the old quote may still rest would double up
+        if !self.quotes.needs_requote(mid, self.max_bps_diff) || !self.cancel_quotes().await {
+            return;
+        }
+
+        let (bid, ask) = self.quote_prices(0.0);
+        for (is_buy, px) in [(true, bid), (false, ask)] {
+            let px = self.round_px(px);
+            let size = self.round_sz(
+                self.market_kind
+                    .headroom(
+                        self.inventory.position(),
+                        self.max_absolute_position_size,
+                        is_buy,
+                        self.quote_size(is_buy, px),
+                    )
+                    .clamped_size,
+            );
+            if size < EPSILON {
+                continue;
+            }
+            if let Some(oid) = self.post_only_order(is_buy, px, size).await {
+                self.quotes.orders.push((oid, size));
+            }
+        }
+        self.quotes.mid = Some(mid);
+        info!(
+            "Quoting {} / {} around {}",
+            self.fmt_px(self.round_px(bid)),
+            self.fmt_px(self.round_px(ask)),
+            self.fmt_px(mid)
+        );
+    }
+
+    /// Cancels the resting quotes, newest first. Returns false if a cancel failed,
+    /// leaving it and the older quotes tracked.
+    async fn cancel_quotes(&mut self) -> bool {
+        while let Some(&(oid, _)) = self.quotes.orders.last() {
+            if self.dry_run.is_none() {
+                let cancel = ClientCancelRequest {
+                    asset: self.asset.clone(),
+                    oid,
+                };
+                if let Err(e) = self.exchange_client.cancel(cancel, None).await {
+                    error!("Error cancelling quote {oid}: {e}");
+                    return false;
+                }
+            }
+            self.quotes.orders.pop();
+        }
+        true
+    }
+
+    /// Cancel a passive order that hasn't filled within the wait and cross with the
+    /// remaining size.
+    async fn escalate_passive_order(&mut self, now: Instant) {
+        let ExecutionStyle::PostThenCross { wait } = self.execution_style else {
+            return;
+        };
+        let Some(order) = self.passive_order else {
+            return;
+        };
+        if !order.should_escalate(now, wait) {
+            return;
+        }
+
+        if self.dry_run.is_none() {
+            let cancel = ClientCancelRequest {
+                asset: self.asset.clone(),
+                oid: order.oid,
+            };
+            if let Err(e) = self.exchange_client.cancel(cancel, None).await {
+                // Crossing while the order may still rest could fill twice
+                error!("Error cancelling passive order {}: {e}", order.oid);
+                return;
+            }
+        }
+        self.passive_order = None;
+        if self.post_only {
+            info!(
+                "Passive order {} unfilled after {wait:?}, cancelled {} without crossing",
+                order.oid,
+                self.fmt_sz(order.remaining)
+            );
+            return;
+        }
+        info!(
+            "Passive order {} unfilled after {wait:?}, crossing {}",
+            order.oid,
+            self.fmt_sz(order.remaining)
+        );
+        self.execute_immediate_trade(order.is_buy, order.remaining)
+            .await;
+    }
+
+    /// Cancel orders from `place_order` that have rested longer than the timeout.
+    /// Orders whose cancel fails are kept and retried on the next sweep.
+    async fn sweep_resting_orders(&mut self, now: Instant) {
+        let Some(timeout) = self.resting_order_timeout else {
+            return;
+        };
+        let expired: Vec<u64> = self
+            .unfilled_orders
+            .iter()
+            .filter(|(_, (placed_at, _))| now.duration_since(*placed_at) >= timeout)
+            .map(|(oid, _)| *oid)
+            .collect();
+        for oid in expired {
+            if self.dry_run.is_none() {
+                let cancel = ClientCancelRequest {
+                    asset: self.asset.clone(),
+                    oid,
+                };
+                if let Err(e) = self.exchange_client.cancel(cancel, None).await {
+                    error!("Error cancelling resting order {oid}: {e}");
+                    continue;
+                }
+            }
+            info!("Cancelled order {oid} resting for longer than {timeout:?}");
+            self.unfilled_orders.remove(&oid);
+        }
+    }
+
+    /// Marketable limit price `cross_buffer_bps` past the best opposite price of the
+    /// book, or without one `max_bps_diff` past the mid. Always at least one tick
+    /// past the mid, so it still crosses on low-priced assets and on a lagging book.
+    fn cross_price(&self, is_buy: bool) -> f64 {
+        let tick = 10f64.powi(-i32::try_from(self.decimals).unwrap_or(i32::MAX));
+        let mid = self.latest_mid_price;
+        let touch = self.cross_buffer_bps.and_then(|buffer_bps| {
+            let touch = book::touch_price(&self.latest_book.as_ref()?.levels, is_buy)?;
+            Some((buffer_bps, touch))
+        });
+        let price = if let Some((buffer_bps, touch)) = touch {
+            let offset = bps_to_price(touch, buffer_bps);
+            if is_buy {
+                (touch + offset).max(mid + tick)
+            } else {
+                (touch - offset).min(mid - tick)
+            }
+        } else {
+            let offset = bps_to_price(mid, f64::from(self.max_bps_diff)).max(tick);
+            if is_buy {
+                mid + offset
+            } else {
+                mid - offset
+            }
+        };
+        self.round_cross_px(price, is_buy)
+    }
+
+    /// Execute a quick trade to capture the arbitrage opportunity. Returns whether
+    /// anything was filled.
+    async fn execute_immediate_trade(&mut self, is_buy: bool, size: f64) -> bool {
+        // Never let a trade push the position past the cap, whichever path sent it
+        let size = self.round_sz(
+            self.market_kind
+                .headroom(
+                    self.inventory.position(),
+                    self.max_absolute_position_size,
+                    is_buy,
+                    size,
+                )
+                .clamped_size,
+        );
+        if size < EPSILON {
+            info!(
+                "Position {} is at the cap of {}, skipping trade",
+                self.fmt_sz(self.inventory.position()),
+                self.fmt_sz(self.max_absolute_position_size)
+            );
+            return false;
+        }
+
+        let order_price = self.cross_price(is_buy);
+
+        let outcome = self
+            .place_order(self.asset.clone(), size, order_price, is_buy, false)
+            .await;
+        let (amount_filled, oid) = outcome.filled();
+        if amount_filled > EPSILON {
+            // The position moves when the fill arrives on UserEvents
+            self.inline_fills.acknowledge(oid, amount_filled);
+            if self.paper_trading {
+                self.apply_paper_fill(is_buy, amount_filled, order_price, oid)
+                    .await;
+            }
+            info!(
+                "Executed immediate {} of {} at ~{}, book imbalance {}",
+                if is_buy { "buy" } else { "sell" },
+                self.fmt_sz(size),
+                self.fmt_px(order_price),
+                self.book_imbalance()
+                    .map_or_else(|| "n/a".to_string(), |imbalance| format!("{imbalance:.2}"))
+            );
+            return true;
+        }
+        match outcome {
+            OrderOutcome::Resting { oid } => info!(
+                "Immediate trade rested as oid {oid} without a fill, it is cancelled if it stays unfilled"
+            ),
+            OrderOutcome::Rejected { reason } => {
+                error!("Immediate trade rejected by the exchange: {reason}");
+            }
+            OrderOutcome::TransportError => {
+                error!("Immediate trade failed, the exchange didn't answer");
+            }
+            OrderOutcome::NotSent { reason } => info!("Immediate trade not sent ({reason})"),
+            OrderOutcome::Filled { .. } => {
+                error!("Failed to execute immediate trade, no fill received.");
+            }
+        }
+        false
+    }
+
+    /// Close the tracked position with marketable reduce-only orders, retrying
+    /// whatever was left unfilled up to [`FLATTEN_ATTEMPTS`] times. Returns whether
+    /// the position was closed.
+    ///
+    /// Fills are booked when they arrive on `UserEvents`, so attempts size off the
+    /// position net of what earlier attempts already filled.
+    pub async fn flatten(&mut self) -> bool {
+        let mut remaining = self.inventory.position();
+        for attempt in 1..=FLATTEN_ATTEMPTS {
+            let size = self.round_sz(remaining.abs());
+            if size < EPSILON {
+                break;
+            }
+            let is_buy = remaining < 0.0;
+            let price = self.cross_price(is_buy);
+            let (filled, oid) = self
+                .place_order(self.asset.clone(), size, price, is_buy, true)
+                .await
+                .filled();
+            if filled < EPSILON {
+                warn!("Flatten attempt {attempt} of {FLATTEN_ATTEMPTS} got no fill");
+                continue;
+            }
+            self.inline_fills.acknowledge(oid, filled);
+            if self.paper_trading {
+                self.apply_paper_fill(is_buy, filled, price, oid).await;
+            }
+            remaining += if is_buy { filled } else { -filled };
+            info!(
+                "Flatten {} of {} at ~{}, {} left",
+                if is_buy { "buy" } else { "sell" },
+                self.fmt_sz(filled),
+                self.fmt_px(price),
+                self.fmt_sz(remaining)
+            );
+        }
+
+        let flat = self.round_sz(remaining.abs()) < EPSILON;
+        if !flat {
+            error!(
+                "Position still {} after {FLATTEN_ATTEMPTS} flatten attempts",
+                self.fmt_sz(remaining)
+            );
+        }
+        flat
+    }
+
+    /// Book a paper fill of an order at `price` after the simulated latency, at the
+    /// simulated slippage.
+    async fn apply_paper_fill(&mut self, is_buy: bool, size: f64, price: f64, oid: u64) {
+        if !self.simulated_latency.is_zero() {
+            tokio::time::sleep(self.simulated_latency).await;
+        }
+        let px = sim::slipped_px(price, is_buy, self.simulated_slippage_bps);
+        let fill = fills::simulated_fill(&self.asset, is_buy, size, px, oid);
+        self.apply_fill(&fill).await;
+    }
+
+    async fn place_order(
+        &mut self,
+        asset: String,
+        amount: f64,
+        price: f64,
+        is_buy: bool,
+        reduce_only: bool,
+    ) -> OrderOutcome {
+        // Sizes off the lot size are rejected, round down so the cap still holds
+        let amount = self.round_sz(amount);
+        if amount < EPSILON {
+            warn!("Order size rounds down to zero, not sending");
+            return OrderOutcome::NotSent { reason: "size" };
+        }
+        if !reduce_only && amount * price < self.min_order_notional {
+            warn!(
+                "Order of {} at {} is below the minimum value of {:.2}, not sending",
+                self.fmt_sz(amount),
+                self.fmt_px(price),
+                self.min_order_notional
+            );
+            return OrderOutcome::NotSent {
+                reason: "min_notional",
+            };
+        }
+        // Exits may always be sent, they can only shrink the position
+        if !reduce_only && self.at_open_order_cap() {
+            return OrderOutcome::NotSent {
+                reason: "max_open_orders",
+            };
+        }
+        if !reduce_only && !self.daily_budget_allows(amount * price) {
+            return OrderOutcome::NotSent {
+                reason: "max_daily_notional",
+            };
+        }
+        if self
+            .circuit_breaker
+            .as_mut()
+            .is_some_and(|breaker| breaker.is_halted(Instant::now()))
+        {
+            debug!("Order circuit breaker is open, not sending");
+            return OrderOutcome::NotSent {
+                reason: "circuit_breaker",
+            };
+        }
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_order(is_buy, amount, price);
+        }
+        self.metrics.record(|values| values.orders += 1);
+        let jitter = self.next_order_jitter();
+        if !jitter.is_zero() {
+            tokio::time::sleep(jitter).await;
+        }
+        let outcome = self
+            .send_order(asset, amount, price, is_buy, reduce_only)
+            .await;
+        self.record_signal_latency();
+        let result = match &outcome {
+            OrderOutcome::Filled { size, oid } if *size > EPSILON => {
+                format!("filled {size} oid {oid}")
+            }
+            OrderOutcome::Resting { oid } => format!("resting oid {oid}"),
+            OrderOutcome::Rejected { reason } => format!("rejected: {reason}"),
+            OrderOutcome::TransportError => "transport error".to_string(),
+            OrderOutcome::NotSent { reason } => format!("not sent: {reason}"),
+            OrderOutcome::Filled { .. } => "unfilled".to_string(),
+        };
+        self.log_order(is_buy, amount, price, &result);
+        outcome
+    }
+
+    /// Random share of `order_jitter` to wait before the next order.
+    fn next_order_jitter(&mut self) -> Duration {
+        if self.order_jitter.is_zero() {
+            return Duration::ZERO;
+        }
+        self.order_jitter.mul_f64(self.jitter_rng.gen::<f64>())
+    }
+
+    /// Records the time from the signal being acted on to this order result, warning
+    /// about outliers. Orders not sent on a signal aren't measured.
+    fn record_signal_latency(&self) {
+        let Some((signal_at, reference_age)) = self.signal_at else {
+            return;
+        };
+        let latency = signal_at.elapsed();
+        self.metrics
+            .record(|values| values.order_latency.observe(latency));
+        if self
+            .latency_warn_threshold
+            .is_some_and(|threshold| latency > threshold)
+        {
+            warn!(
+                "Order result arrived {latency:?} after the signal, reference price was {reference_age:?} old at the decision"
+            );
+        }
+    }
+
+    const fn record_order_success(&mut self) {
+        if let Some(breaker) = self.circuit_breaker.as_mut() {
+            breaker.record_success();
+        }
+    }
+
+    fn record_order_failure(&mut self) {
+        let tripped = self
+            .circuit_breaker
+            .as_mut()
+            .is_some_and(|breaker| breaker.record_failure(Instant::now()));
+        if tripped {
+            let halted_until = self
+                .circuit_breaker
+                .as_ref()
+                .and_then(CircuitBreaker::halted_until);
+            self.metrics
+                .record(|values| values.halted_until = halted_until);
+            if let Some(observer) = self.observer.as_mut() {
+                observer.on_halt("circuit_breaker");
+            }
+        }
+    }
+
+    /// Takes a token from the order rate limiter, if one is configured. Returns false
+    /// if the order has to be skipped.
+    fn acquire_order_token(&mut self) -> bool {
+        let Some(limiter) = self.order_rate_limit.as_mut() else {
+            return true;
+        };
+        let now = Instant::now();
+        let acquired = limiter.try_acquire(now);
+        let utilization = limiter.utilization(now);
+        self.metrics
+            .record(|values| values.order_rate_utilization = utilization);
+        if !acquired {
+            warn!("Order rate limit reached, skipping order");
+        }
+        acquired
+    }
+
+    /// Share of the order rate limiter's burst in use, if one is configured.
+    #[must_use]
+    pub fn order_rate_utilization(&self) -> Option<f64> {
+        self.order_rate_limit
+            .as_ref()
+            .map(|limiter| limiter.utilization(Instant::now()))
+    }
+
+    /// Row in the trade log, if one is configured, for an order attempt on the
+    /// latest signal.
+    fn log_order(&mut self, is_buy: bool, sz: f64, px: f64, result: &str) {
+        info!(
+            is_buy,
+            size = sz,
+            price = px,
+            result,
+            "Order to {} {} at {}: {result}",
+            if is_buy { "buy" } else { "sell" },
+            self.fmt_sz(sz),
+            self.fmt_px(px)
+        );
+        if let Some(trade_log) = self.trade_log.as_mut() {
+            trade_log.order(
+                Utc::now().timestamp_millis(),
+                &self.asset,
+                is_buy,
+                sz,
+                px,
+                self.last_signal,
+                result,
+            );
+        }
+    }
+
+    /// Outcome of an order response, and whether the failure is safe to retry: only
+    /// failures the exchange is known not to have acted on are. A fill or resting
+    /// order counts as a success for the circuit breaker.
+    async fn order_outcome(
+        &mut self,
+        order: Result<ExchangeResponseStatus, crate::Error>,
+        amount: f64,
+    ) -> (OrderOutcome, bool) {
+        match order {
+            Ok(ExchangeResponseStatus::Ok(order_resp)) => {
+                match order_resp
+                    .data
+                    .and_then(|data| data.statuses.into_iter().next())
+                {
+                    Some(ExchangeDataStatus::Filled(o)) => {
+                        self.record_order_success();
+                        // An IOC can fill partially with the rest cancelled
+                        let filled = o.total_sz.parse::<f64>().map_or_else(
+                            |e| {
+                                warn!("Unparseable filled size {}: {e}", o.total_sz);
+                                amount
+                            },
+                            |filled| filled.min(amount),
+                        );
+                        let outcome = OrderOutcome::Filled {
+                            size: filled,
+                            oid: o.oid,
+                        };
+                        (outcome, false)
+                    }
+                    Some(ExchangeDataStatus::Resting(o)) => {
+                        // If it ended up resting, no immediate fill, but the order
+                        // is live until swept
+                        self.record_order_success();
+                        self.unfilled_orders.insert(o.oid, (Instant::now(), amount));
+                        (OrderOutcome::Resting { oid: o.oid }, false)
+                    }
+                    Some(ExchangeDataStatus::Error(e)) => {
+                        let category = self.record_reject(&e).await;
+                        error!("Order error ({}): {e}", category.as_str());
+                        let retryable = category == RejectCategory::RateLimit;
+                        (OrderOutcome::Rejected { reason: e }, retryable)
+                    }
+                    Some(status) => {
+                        let reason = format!("unexpected order status {status:?}");
+                        error!("{reason}");
+                        (OrderOutcome::Rejected { reason }, false)
+                    }
+                    None => {
+                        error!("Exchange response data is empty when placing order");
+                        let reason = "empty response".to_string();
+                        (OrderOutcome::Rejected { reason }, false)
+                    }
+                }
+            }
+            Ok(ExchangeResponseStatus::Err(e)) => {
+                let category = self.record_reject(&e).await;
+                error!("Error with placing order ({}): {e}", category.as_str());
+                if category == RejectCategory::Margin {
+                    error!("Not enough margin to place order, skipping trade.");
+                }
+                let retryable = category == RejectCategory::RateLimit;
+                (OrderOutcome::Rejected { reason: e }, retryable)
+            }
+            Err(e) => {
+                error!("Error with placing order: {e}");
+                let retryable = reject::never_reached_exchange(&e);
+                (OrderOutcome::TransportError, retryable)
+            }
+        }
+    }
+
+    /// Look up an order whose request failed without telling whether the exchange got
+    /// it by asking the exchange. Returns its outcome and whether resending is safe,
+    /// which it only is once the exchange is known not to have the order.
+    async fn resolve_order(&mut self, cloid: Uuid, amount: f64) -> (OrderOutcome, bool) {
+        let key = uuid_to_hex_string(cloid);
+        match self
+            .info_client
+            .query_order_by_cloid(self.user_address, cloid)
+            .await
+        {
+            Ok(status) => {
+                let Some(outcome) = orders::status_outcome(&status) else {
+                    warn!("Order {key} never reached the exchange, safe to resend");
+                    return (OrderOutcome::TransportError, true);
+                };
+                info!("Order {key} reached the exchange despite the error: {outcome:?}");
+                if let OrderOutcome::Resting { oid } = outcome {
+                    self.unfilled_orders.insert(oid, (Instant::now(), amount));
+                }
+                (outcome, false)
+            }
+            Err(e) => {
+                error!("Error looking up order {key}: {e}, not resending");
+                (OrderOutcome::TransportError, false)
+            }
+        }
+    }
+
+    async fn send_order(
+        &mut self,
+        asset: String,
+        amount: f64,
+        price: f64,
+        is_buy: bool,
+        reduce_only: bool,
+    ) -> OrderOutcome {
+        let notional = amount * price;
+        // A NaN notional from a broken price is refused too. Reduce-only orders can't
+        // add risk, so they may unwind a position of any size.
+        if notional.is_nan() || (!reduce_only && notional > self.max_order_notional) {
+            error!(
+                "Order of {} at {} is worth {notional:.2}, above the maximum of {:.2}, not sending",
+                self.fmt_sz(amount),
+                self.fmt_px(price),
+                self.max_order_notional
+            );
+            return OrderOutcome::NotSent {
+                reason: "max_notional",
+            };
+        }
+
+        let mut backoff = Backoff::new(
+            self.order_retry_delay,
+            self.order_retry_delay.saturating_mul(16),
+        );
+        loop {
+            if !self.acquire_order_token() {
+                return OrderOutcome::NotSent {
+                    reason: "rate_limit",
+                };
+            }
+            // A fresh client order id per attempt, to look the order up by if the
+            // request fails without telling whether the exchange got it
+            let cloid = self.dry_run.is_none().then(Uuid::new_v4);
+            let order = if let Some(fill_model) = self.dry_run.as_mut() {
+                fill_model.submit(amount, price)
+            } else {
+                self.exchange_client
+                    .order(
+                        ClientOrderRequest {
+                            asset: asset.clone(),
+                            is_buy,
+                            reduce_only: self.market_kind.reduce_only(reduce_only),
+                            limit_px: price,
+                            sz: amount,
+                            cloid,
+                            order_type: ClientOrder::Limit(ClientLimit {
+                                tif: self.order_tif.as_str().to_string(),
+                            }),
+                        },
+                        None,
+                    )
+                    .await
+            };
+
+            let (outcome, retryable) = match (self.order_outcome(order, amount).await, cloid) {
+                ((OrderOutcome::TransportError, false), Some(cloid)) => {
+                    self.resolve_order(cloid, amount).await
+                }
+                (result, _) => result,
+            };
+            if let Some(cloid) = cloid {
+                self.cloids
+                    .record(&uuid_to_hex_string(cloid), outcome.clone());
+            }
+            if matches!(
+                outcome,
+                OrderOutcome::Filled { .. } | OrderOutcome::Resting { .. }
+            ) {
+                return outcome;
+            }
+
+            if !retryable || backoff.attempts() >= self.max_order_retries {
+                self.record_order_failure();
+                return outcome;
+            }
+            let delay = backoff.next_delay();
+            warn!(
+                "Retrying order in {delay:?}, retry {} of {}",
+                backoff.attempts(),
+                self.max_order_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllMids, AllMidsData, FilledOrder, L2Book, Meta, RestingOrder, User, UserData};
+    use signal::z_score;
+
+    #[allow(clippy::too_many_lines)]
+    fn test_input() -> Input {
+        Input {
+            asset: "BTC".to_string(),
+            base_url: BaseUrl::Localhost,
+            market_kind: MarketKind::Perp,
+            window_size: 100,
+            min_correlation: None,
+            window_mode: WindowMode::Count,
+            stat_mode: StatMode::Window,
+            z_threshold: 2.0,
+            size_mode: SizeMode::Fixed(0.001),
+            balance_refresh: Duration::from_mins(1),
+            target_liquidity: 0.0002,
+            half_spread: 5,
+            max_bps_diff: 20,
+            max_bps_diff_band: false,
+            max_absolute_position_size: 0.004,
+            decimals: 0,
+            size_decimals: 5,
+            wallet: LocalWallet::new(&mut rand::thread_rng()),
+            one_sided_book_band_bps: None,
+            cross_buffer_bps: None,
+            hedge_delta_threshold: None,
+            hedge_trader: None,
+            window_prior: None,
+            fill_dedup_window: 1000,
+            reference_confirm_bps: None,
+            shutdown_timeout: Duration::from_secs(10),
+            standby_wallet: None,
+            signal_skew_weight: 0.0,
+            protective_stop_bps: None,
+            protective_take_profit_bps: None,
+            protective_reprice_bps: 10.0,
+            stop_loss_bps: None,
+            take_profit_bps: None,
+            min_hold: Duration::ZERO,
+            max_hold_time: None,
+            post_fill_quiet: Duration::ZERO,
+            long_window_size: None,
+            long_z_threshold: 1.0,
+            returns_window: 100,
+            return_interval: None,
+            stats_interval: None,
+            exposure: None,
+            z_threshold_schedule: Vec::new(),
+            session_end: None,
+            session_start: None,
+            volatility_scaling: None,
+            loss_penalty: 1.0,
+            loss_penalty_half_life: Duration::from_mins(5),
+            decision_history: 100,
+            reconcile_min_interval: Duration::from_secs(30),
+            entry_band_bps: None,
+            min_stddev_bps: None,
+            min_stddev_mean_fraction: None,
+            reconnect_limiter: None,
+            confirm_ticks: 1,
+            outlier_k: None,
+            outlier_confirm_ticks: 3,
+            message_timeout: None,
+            heartbeat_interval: None,
+            log_sample_rate: 1,
+            stale_orders: None,
+            quote_depth_fraction: None,
+            log_position_headroom: true,
+            latency_warn_threshold: None,
+            fair_value: None,
+            dry_run: None,
+            execution_style: ExecutionStyle::Cross,
+            post_only: false,
+            maker_rebate_bps: 0.0,
+            max_price_age: Duration::from_secs(5),
+            max_size_multiplier: 1.0,
+            inventory_skew: 0.0,
+            paper_trading: false,
+            simulated_latency: Duration::ZERO,
+            simulated_slippage_bps: 0.0,
+            min_trade_interval: Duration::ZERO,
+            resting_order_timeout: None,
+            max_open_orders: None,
+            order_tif: TimeInForce::Ioc,
+            strategy: StrategyMode::Arbitrage,
+            warmup_ticks: 0,
+            max_order_notional: f64::INFINITY,
+            max_daily_notional: None,
+            taker_fee_bps: 0.0,
+            min_edge_bps: 0.0,
+            funding_refresh: None,
+            expected_holding_time: Duration::from_hours(1),
+            max_order_retries: 0,
+            order_retry_delay: Duration::ZERO,
+            order_rate_limit: None,
+            order_jitter: Duration::ZERO,
+            jitter_seed: None,
+            circuit_breaker: None,
+            mid_source: MidSource::AllMids,
+            subscriptions: Subscriptions::default(),
+            reference_venue: ReferenceVenue::Binance,
+            mark_source: MarkSource::HlMid,
+            binance_price_source: BinancePriceSource::Trade,
+            recheck_edge: false,
+            imbalance_threshold: None,
+            imbalance_levels: 5,
+            trade_log_path: None,
+            state_path: None,
+            state_max_age: Duration::from_mins(10),
+            observer: None,
+            #[cfg(feature = "metrics")]
+            metrics_port: None,
+            #[cfg(feature = "admin")]
+            admin_addr: None,
+        }
+    }
+
+    async fn test_market_maker(input: Input) -> MarketMaker {
+        let info_client = InfoClient::new(None, Some(BaseUrl::Localhost))
+            .await
+            .unwrap();
+        let exchange_client = ExchangeClient::new(
+            None,
+            input.wallet.clone(),
+            Some(BaseUrl::Localhost),
+            Some(Meta { universe: vec![] }),
+            None,
+        )
+        .await
+        .unwrap();
+        MarketMaker::with_clients(input, info_client, exchange_client)
+    }
+
+    #[test]
+    fn bps_test() {
+        assert!((bps_to_price(50_000.0, 10.0) - 50.0).abs() < 1e-9);
+        assert!((price_to_bps(100.0, 99.5) + 50.0).abs() < 1e-9);
+        // The same 10 bps edge is the same signal on a 50k and a 2 dollar asset
+        assert!((price_to_bps(2.0, 2.0 + bps_to_price(2.0, 10.0)) - 10.0).abs() < 1e-9);
+        assert!((fraction_to_bps(bps_to_fraction(3.5)) - 3.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn validate_input_test() {
+        assert!(test_input().validate().is_ok());
+        let invalid: [fn() -> Input; 13] = [
+            || Input {
+                window_size: 1,
+                ..test_input()
+            },
+            || Input {
+                post_only: true,
+                ..test_input()
+            },
+            || Input {
+                session_start: Some(NaiveTime::MIN),
+                ..test_input()
+            },
+            || Input {
+                size_mode: SizeMode::Fixed(0.0),
+                ..test_input()
+            },
+            || Input {
+                z_threshold: f64::NAN,
+                ..test_input()
+            },
+            || Input {
+                max_absolute_position_size: -1.0,
+                ..test_input()
+            },
+            || Input {
+                asset: String::new(),
+                ..test_input()
+            },
+            || Input {
+                half_spread: 20,
+                max_bps_diff: 10,
+                ..test_input()
+            },
+            || Input {
+                decimals: 12,
+                ..test_input()
+            },
+            || Input {
+                size_decimals: 7,
+                ..test_input()
+            },
+            || Input {
+                stop_loss_bps: Some(-5.0),
+                ..test_input()
+            },
+            || Input {
+                cross_buffer_bps: Some(f64::INFINITY),
+                ..test_input()
+            },
+            || Input {
+                confirm_ticks: 0,
+                ..test_input()
+            },
+        ];
+        for input in invalid {
+            assert!(matches!(
+                input().validate(),
+                Err(MarketMakerError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn record_reject_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        market_maker
+            .record_reject("Insufficient margin to place order.")
+            .await;
+        market_maker
+            .record_reject("Insufficient margin to place order.")
+            .await;
+        market_maker.record_reject("Order has invalid price.").await;
+
+        let counts = market_maker.reject_counts();
+        assert_eq!(counts.get(&RejectCategory::Margin), Some(&2));
+        assert_eq!(counts.get(&RejectCategory::PricePrecision), Some(&1));
+        assert_eq!(counts.get(&RejectCategory::Unknown), None);
+        let rejects = market_maker.metrics().values().rejects;
+        assert_eq!(rejects[RejectCategory::Margin as usize], 2);
+        assert_eq!(rejects[RejectCategory::PricePrecision as usize], 1);
+    }
+
+    fn fill_message(fill: TradeInfo) -> Message {
+        Message::User(User {
+            data: UserData { fills: vec![fill] },
+        })
+    }
+
+    #[tokio::test]
+    async fn hedge_only_beyond_threshold_test() {
+        let mut market_maker = test_market_maker(Input {
+            hedge_delta_threshold: Some(0.01),
+            ..test_input()
+        })
+        .await;
+        for oid in 1..=2 {
+            market_maker
+                .process_message(fill_message(fills::fill("B", 0.004, oid, 0)))
+                .await;
+        }
+        assert!((market_maker.unhedged_delta() - 0.008).abs() < EPSILON);
+
+        // Crossing the threshold releases one batched hedge
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.004, 3, 0)))
+            .await;
+        assert!(market_maker.unhedged_delta().abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn hedge_on_binance_test() {
+        use crate::BinanceCredentials;
+        let trader = BinanceTrader::new(
+            "BTCUSDT",
+            BinanceCredentials {
+                api_key: "key".to_string(),
+                secret_key: "secret".to_string(),
+            },
+        )
+        .unwrap();
+        let mut market_maker = test_market_maker(Input {
+            hedge_trader: Some(trader),
+            paper_trading: true,
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        market_maker.latest_mid_price = 101.0;
+        let mut fill = fills::fill("B", 0.0025, 1, 0);
+        fill.px = "101".to_string();
+        market_maker.process_message(fill_message(fill)).await;
+
+        // The step size leaves 0.0005 to carry into the next hedge
+        assert!((market_maker.hedge_position() + 0.002).abs() < EPSILON);
+        assert!((market_maker.unhedged_delta() - 0.0005).abs() < EPSILON);
+
+        // The Binance leg gains what the Hyperliquid leg loses
+        market_maker.binance_price.set(99.0);
+        market_maker.latest_mid_price = 100.0;
+        assert!((market_maker.hedge_pnl() - 0.002).abs() < EPSILON);
+        assert!((market_maker.combined_pnl() + 0.0005).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn duplicate_fill_counted_once_test() {
+        let mut market_maker = test_market_maker(Input {
+            hedge_delta_threshold: Some(1.0),
+            ..test_input()
+        })
+        .await;
+        let fill = fills::fill("B", 0.004, 1, 0);
+        market_maker
+            .process_message(fill_message(fill.clone()))
+            .await;
+        market_maker.process_message(fill_message(fill)).await;
+        assert!((market_maker.unhedged_delta() - 0.004).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn auth_failure_rotates_to_standby_wallet_test() {
+        let standby_wallet = LocalWallet::new(&mut rand::thread_rng());
+        let standby_address = standby_wallet.address();
+        let mut market_maker = test_market_maker(Input {
+            standby_wallet: Some(standby_wallet),
+            ..test_input()
+        })
+        .await;
+
+        // Other rejections don't rotate
+        market_maker.record_reject("Order has invalid price.").await;
+        assert_ne!(
+            market_maker.exchange_client.wallet.address(),
+            standby_address
+        );
+
+        market_maker
+            .record_reject("User or API Wallet 0x1234 does not exist.")
+            .await;
+        assert_eq!(
+            market_maker.exchange_client.wallet.address(),
+            standby_address
+        );
+    }
+
+    #[tokio::test]
+    async fn per_side_average_fill_price_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let fill_at = |side, px: f64, sz, oid| {
+            let mut fill = fills::fill(side, sz, oid, 0);
+            fill.px = px.to_string();
+            fill_message(fill)
+        };
+        market_maker
+            .process_message(fill_at("B", 100.0, 1.0, 1))
+            .await;
+        market_maker
+            .process_message(fill_at("A", 104.0, 0.5, 2))
+            .await;
+        market_maker
+            .process_message(fill_at("B", 103.0, 2.0, 3))
+            .await;
+        market_maker
+            .process_message(fill_at("A", 101.0, 1.0, 4))
+            .await;
+
+        assert!((market_maker.avg_buy_px().unwrap() - 102.0).abs() < EPSILON);
+        assert!((market_maker.avg_sell_px().unwrap() - 102.0).abs() < EPSILON);
+
+        // Fills of other assets on the same account don't count
+        let mut other = fills::fill("B", 5.0, 5, 0);
+        other.coin = "ETH".to_string();
+        market_maker.process_message(fill_message(other)).await;
+        let exposure = market_maker.exposure();
+        assert!((exposure.position - 1.5).abs() < EPSILON);
+        assert!((exposure.gross_bought - 3.0).abs() < EPSILON);
+        assert!((exposure.gross_sold - 1.5).abs() < EPSILON);
+        assert!((exposure.bought_notional - 306.0).abs() < 1e-9);
+        assert!((exposure.sold_notional - 153.0).abs() < 1e-9);
+        assert!((exposure.buy_share().unwrap() - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn post_fill_quiet_window_test() {
+        let mut market_maker = test_market_maker(Input {
+            post_fill_quiet: Duration::from_secs(2),
+            ..test_input()
+        })
+        .await;
+        assert!(!market_maker.in_post_fill_quiet(Instant::now()));
+
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.001, 1, 0)))
+            .await;
+        let now = Instant::now();
+        assert!(market_maker.in_post_fill_quiet(now));
+        assert!(!market_maker.in_post_fill_quiet(now + Duration::from_secs(3)));
+    }
+
+    #[tokio::test]
+    async fn two_sided_quoting_test() {
+        let mut market_maker = test_market_maker(Input {
+            strategy: StrategyMode::Quoting,
+            decimals: 2,
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        market_maker.on_price_update().await;
+        assert_eq!(market_maker.quotes.orders, vec![(1, 0.0002), (2, 0.0002)]);
+
+        // Within max_bps_diff the quotes stay
+        market_maker.latest_mid_price = 100.1;
+        market_maker.quote().await;
+        assert_eq!(market_maker.quotes.orders, vec![(1, 0.0002), (2, 0.0002)]);
+
+        market_maker.latest_mid_price = 100.3;
+        market_maker.quote().await;
+        assert_eq!(market_maker.quotes.orders, vec![(3, 0.0002), (4, 0.0002)]);
+
+        // A filled quote is forgotten, the other keeps resting
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.0002, 3, 1)))
+            .await;
+        assert_eq!(market_maker.quotes.orders, vec![(4, 0.0002)]);
+        assert!(market_maker.decisions().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn one_sided_book_pulls_quotes_test() {
+        let mut market_maker = test_market_maker(Input {
+            strategy: StrategyMode::Quoting,
+            decimals: 2,
+            dry_run: Some(FillModel::new(1, 0.0)),
+            one_sided_book_band_bps: Some(50.0),
+            ..test_input()
+        })
+        .await;
+        let book = |asks| {
+            Message::L2Book(L2Book {
+                data: L2BookData {
+                    coin: "BTC".to_string(),
+                    time: 0,
+                    levels: vec![vec![book::level(99.9, 1.0)], asks],
+                },
+            })
+        };
+        market_maker.latest_mid_price = 100.0;
+        market_maker
+            .process_message(book(vec![book::level(100.1, 1.0)]))
+            .await;
+        market_maker.quote().await;
+        assert_eq!(market_maker.quotes.orders.len(), 2);
+
+        // The asks empty out: the quotes are cancelled and not replaced
+        market_maker.process_message(book(vec![])).await;
+        market_maker.quote().await;
+        assert!(market_maker.quotes.orders.is_empty());
+        market_maker.quote().await;
+        assert!(market_maker.quotes.orders.is_empty());
+
+        market_maker
+            .process_message(book(vec![book::level(100.1, 1.0)]))
+            .await;
+        market_maker.quote().await;
+        assert_eq!(market_maker.quotes.orders.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn resting_order_sweep_test() {
+        let mut market_maker = test_market_maker(Input {
+            resting_order_timeout: Some(Duration::from_secs(5)),
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        let placed_at = Instant::now();
+        market_maker.unfilled_orders.insert(1, (placed_at, 0.001));
+        market_maker
+            .unfilled_orders
+            .insert(2, (placed_at + Duration::from_secs(3), 0.001));
+
+        market_maker
+            .sweep_resting_orders(placed_at + Duration::from_secs(4))
+            .await;
+        assert_eq!(market_maker.unfilled_orders.len(), 2);
+        market_maker
+            .sweep_resting_orders(placed_at + Duration::from_secs(6))
+            .await;
+        assert!(market_maker.unfilled_orders.contains_key(&2));
+        assert!(!market_maker.unfilled_orders.contains_key(&1));
+    }
+
+    #[tokio::test]
+    async fn max_order_notional_test() {
+        let mut market_maker = test_market_maker(Input {
+            max_order_notional: 500.0,
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        assert_eq!(
+            market_maker
+                .place_order("BTC".to_string(), 0.01, 100_000.0, true, false)
+                .await,
+            OrderOutcome::NotSent {
+                reason: "max_notional"
+            }
+        );
+        // The refused order never reached the exchange, the next one gets the first oid
+        assert_eq!(
+            market_maker
+                .place_order("BTC".to_string(), 0.001, 100_000.0, true, false)
+                .await,
+            OrderOutcome::Filled {
+                size: 0.001,
+                oid: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn max_daily_notional_test() {
+        let mut market_maker = test_market_maker(Input {
+            max_daily_notional: Some(250.0),
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        let fill_at = |side, sz, oid| TradeInfo {
+            px: "100000".to_string(),
+            ..fills::fill(side, sz, oid, oid)
+        };
+        market_maker.apply_fill(&fill_at("B", 0.001, 1)).await;
+        // Only the 0.001 beyond closing the long opens a position and counts
+        market_maker.apply_fill(&fill_at("A", 0.002, 2)).await;
+        let today = Utc::now().date_naive();
+        let traded = market_maker.daily_budget.as_mut().unwrap().traded(today);
+        assert!((traded - 200.0).abs() < EPSILON);
+
+        assert_eq!(
+            market_maker
+                .place_order("BTC".to_string(), 0.001, 100_000.0, false, false)
+                .await,
+            OrderOutcome::NotSent {
+                reason: "max_daily_notional"
+            }
+        );
+        // Exits are always sent
+        assert!(matches!(
+            market_maker
+                .place_order("BTC".to_string(), 0.001, 100_000.0, true, true)
+                .await,
+            OrderOutcome::Filled { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn warmup_ticks_test() {
+        let mut market_maker = test_market_maker(Input {
+            window_size: 10,
+            warmup_ticks: 30,
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..29 {
+            if i == 11 {
+                // The window is full, but a dislocation before 30 ticks isn't traded
+                assert!(market_maker.is_warm());
+                market_maker.latest_mid_price = 110.0;
+                market_maker.on_price_update().await;
+                assert_eq!(
+                    market_maker.decisions().last().unwrap().outcome,
+                    DecisionOutcome::Blocked("warmup")
+                );
+            } else {
+                alternating_update(&mut market_maker, i).await;
+            }
+        }
+
+        // A big dislocation on the 30th tick is traded
+        market_maker.latest_mid_price = 110.0;
+        market_maker.on_price_update().await;
+        assert!(matches!(
+            market_maker.decisions().last().unwrap().outcome,
+            DecisionOutcome::Traded { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn trade_cooldown_test() {
+        let mut market_maker = test_market_maker(Input {
+            min_trade_interval: Duration::from_secs(1),
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        let outcome = market_maker
+            .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 10.0)
+            .await;
+        assert!(matches!(outcome, DecisionOutcome::Traded { .. }));
+        // Still extreme on the next tick
+        let outcome = market_maker
+            .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 10.0)
+            .await;
+        assert_eq!(outcome, DecisionOutcome::Blocked("trade_cooldown"));
+
+        let now = Instant::now();
+        assert!(!market_maker.in_trade_cooldown(now + Duration::from_secs(1)));
+    }
+
+    #[tokio::test]
+    async fn cloid_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let fill = TradeInfo {
+            cloid: Some("0xabc".to_string()),
+            ..fills::fill("B", 0.001, 5, 1)
+        };
+        market_maker.apply_fill(&fill).await;
+        assert_eq!(
+            market_maker.order_by_cloid("0xabc"),
+            Some(&OrderOutcome::Filled {
+                size: 0.001,
+                oid: 5
+            })
+        );
+
+        // Without an answer from the exchange the order may have landed, don't resend
+        assert_eq!(
+            market_maker.resolve_order(Uuid::new_v4(), 0.001).await,
+            (OrderOutcome::TransportError, false)
+        );
+    }
+
+    #[tokio::test]
+    async fn act_on_intent_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        // The decision is a plain value, e.g. for a backtest to inspect or replay
+        let intent = market_maker.trade_intent(z_score(-3.0, 2.0)).unwrap();
+        assert_eq!(
+            intent,
+            TradeIntent {
+                is_buy: true,
+                size: 0.001,
+                signal: z_score(-3.0, 2.0)
+            }
+        );
+        assert_eq!(market_maker.decisions().count(), 0);
+        assert_eq!(market_maker.open_orders(), 0);
+
+        let outcome = market_maker.act_on_intent(intent, None, 100.0, 10.0).await;
+        assert!(matches!(
+            outcome,
+            DecisionOutcome::Traded {
+                is_buy: true,
+                filled: true,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn min_correlation_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            window_size: 4,
+            min_correlation: Some(0.5),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        // The prices move against each other
+        for (hl, reference) in [
+            (100.0, 100.0),
+            (101.0, 99.0),
+            (100.0, 100.0),
+            (101.0, 99.0),
+            (100.0, 100.0),
+        ] {
+            market_maker.correlation.push(hl, reference);
+        }
+        assert!(market_maker
+            .heartbeat(Instant::now())
+            .contains("correlation -"));
+        let intent = market_maker.trade_intent(z_score(-3.0, 2.0)).unwrap();
+        let outcome = market_maker.act_on_intent(intent, None, 100.0, 10.0).await;
+        assert_eq!(outcome, DecisionOutcome::Blocked("correlation"));
+
+        for (hl, reference) in [
+            (101.0, 105.0),
+            (102.0, 106.0),
+            (103.0, 107.0),
+            (102.0, 106.0),
+        ] {
+            market_maker.correlation.push(hl, reference);
+        }
+        let outcome = market_maker.act_on_intent(intent, None, 100.0, 10.0).await;
+        assert!(matches!(outcome, DecisionOutcome::Traded { .. }));
+    }
+
+    #[tokio::test]
+    async fn signal_only_test() {
+        let mut market_maker = test_market_maker(Input {
+            subscriptions: Subscriptions::signal_only(),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        let intent = market_maker.trade_intent(z_score(-3.0, 2.0)).unwrap();
+        let outcome = market_maker.act_on_intent(intent, None, 100.0, 10.0).await;
+        assert_eq!(outcome, DecisionOutcome::Blocked("signal_only"));
+        assert_eq!(market_maker.open_orders(), 0);
+
+        // Paper fills are booked without UserEvents, so paper trading still trades
+        market_maker.dry_run = Some(FillModel::new(1, 0.0));
+        let outcome = market_maker.act_on_intent(intent, None, 100.0, 10.0).await;
+        assert!(matches!(outcome, DecisionOutcome::Traded { .. }));
+
+        assert!(Input {
+            subscriptions: Subscriptions {
+                user_events: true,
+                all_mids: false,
+            },
+            ..test_input()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn recheck_edge_test() {
+        let mut market_maker = test_market_maker(Input {
+            recheck_edge: true,
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        market_maker.last_signal = Some(trade_log::SignalSnapshot {
+            z: Some(3.0),
+            mean: 0.0,
+            stddev: 0.001,
+            funding_rate: None,
+        });
+        // Hyperliquid fell back below the reference before the sell went out
+        market_maker.latest_mid_price = 99.9;
+        let outcome = market_maker
+            .act_on_signal(z_score(3.0, 2.0), None, 100.0, 10.0)
+            .await;
+        assert_eq!(outcome, DecisionOutcome::Blocked("edge_collapsed"));
+
+        market_maker.latest_mid_price = 100.5;
+        let outcome = market_maker
+            .act_on_signal(z_score(3.0, 2.0), None, 100.0, 10.0)
+            .await;
+        assert!(matches!(outcome, DecisionOutcome::Traded { .. }));
+    }
+
+    #[tokio::test]
+    async fn long_window_z_test() {
+        let mut market_maker = test_market_maker(Input {
+            long_window_size: Some(4),
+            ..test_input()
+        })
+        .await;
+        assert_eq!(market_maker.update_long_window(0.001), None);
+        assert_eq!(market_maker.update_long_window(-0.001), None);
+        assert_eq!(market_maker.update_long_window(0.001), None);
+        let long_z = market_maker.update_long_window(0.004).unwrap();
+        assert!(long_z > 1.0);
+
+        // Only the last four diffs are kept
+        assert_eq!(market_maker.long_diffs.len(), 4);
+        assert!(market_maker.update_long_window(-0.004).unwrap() < 0.0);
+    }
+
+    #[tokio::test]
+    async fn realized_return_series_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let fill_at = |side, px: f64, oid| {
+            let mut fill = fills::fill(side, 1.0, oid, 0);
+            fill.px = px.to_string();
+            fill_message(fill)
+        };
+        // Round trips realizing +2 and -1
+        for (side, px, oid) in [("B", 100.0, 1), ("A", 102.0, 2), ("B", 100.0, 3)] {
+            market_maker.process_message(fill_at(side, px, oid)).await;
+        }
+        assert_eq!(market_maker.sharpe_ratio(), None);
+        market_maker.process_message(fill_at("A", 99.0, 4)).await;
+
+        let sharpe = market_maker.sharpe_ratio().unwrap();
+        assert!((sharpe - 0.5 / 4.5f64.sqrt()).abs() < EPSILON);
+
+        let mut interval_market_maker = test_market_maker(Input {
+            return_interval: Some(Duration::from_mins(1)),
+            ..test_input()
+        })
+        .await;
+        let start = interval_market_maker.interval_started_at;
+        interval_market_maker.record_realized_pnl(1.0, start);
+        interval_market_maker.record_realized_pnl(2.0, start + Duration::from_secs(30));
+        interval_market_maker.record_realized_pnl(-1.0, start + Duration::from_secs(61));
+        interval_market_maker.roll_return_interval(start + Duration::from_secs(121));
+        // Intervals returned 3 and -1
+        let sharpe = interval_market_maker.sharpe_ratio().unwrap();
+        assert!((sharpe - 1.0 / 8.0f64.sqrt()).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn strategy_stats_test() {
+        let mut market_maker = test_market_maker(Input {
+            return_interval: Some(Duration::from_mins(1)),
+            ..test_input()
+        })
+        .await;
+        let now = Instant::now();
+        // Trades count individually even when returns are aggregated by interval
+        for pnl in [2.0, -1.0, 0.5] {
+            market_maker.record_realized_pnl(pnl, now);
+        }
+        let stats = market_maker.stats();
+        assert_eq!(stats.trades, 3);
+        assert!((stats.win_rate.unwrap() - 2.0 / 3.0).abs() < EPSILON);
+        assert!((stats.avg_pnl.unwrap() - 0.5).abs() < EPSILON);
+        assert_eq!(market_maker.sharpe_ratio(), None);
+    }
+
+    #[tokio::test]
+    async fn replay_capture_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let mids =
+            |px: &str| format!(r#"{{"channel":"allMids","data":{{"mids":{{"BTC":"{px}"}}}}}}"#);
+        let fill = serde_json::json!({
+            "channel": "user",
+            "data": {"fills": [{
+                "coin": "BTC", "side": "B", "px": "101", "sz": "0.5", "time": 3,
+                "hash": "0x1", "startPosition": "0", "dir": "Open Long",
+                "closedPnl": "0", "oid": 1, "crossed": true, "fee": "0"
+            }]}
+        });
+        let frames = [
+            (1, ReplaySource::Binance, r#"{"p":"100"}"#.to_string()),
+            (2, ReplaySource::Hyperliquid, mids("101")),
+            (3, ReplaySource::Hyperliquid, fill.to_string()),
+            (4, ReplaySource::Binance, r#"{"p":"99"}"#.to_string()),
+            (5, ReplaySource::Hyperliquid, mids("100")),
+        ];
+        let feed = ReplayFeed::new(
+            frames
+                .into_iter()
+                .map(|(time, source, frame)| ReplayFrame {
+                    time,
+                    source,
+                    frame,
+                })
+                .collect(),
+        )
+        .with_speed(10.0);
+
+        market_maker.replay(feed).await;
+
+        // Each mid was compared against the reference price seen before it
+        assert_eq!(market_maker.diffs.len(), 2);
+        assert!((market_maker.diffs.values()[0] - 0.01).abs() < EPSILON);
+        assert!((market_maker.diffs.values()[1] - 1.0 / 99.0).abs() < EPSILON);
+        assert!((market_maker.latest_mid_price - 100.0).abs() < EPSILON);
+        assert!((market_maker.inventory.position() - 0.5).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn aggregate_exposure_test() {
+        let exposure = Arc::new(Mutex::new(ExposureTracker::new(100.0)));
+        let mut btc = test_market_maker(Input {
+            exposure: Some(exposure.clone()),
+            ..test_input()
+        })
+        .await;
+        btc.latest_mid_price = 10_000.0;
+        assert!(btc.exposure_allows(true, 0.001));
+
+        // Another asset of the pool uses up most of the budget
+        let mut eth = test_market_maker(Input {
+            asset: "ETH".to_string(),
+            exposure: Some(exposure.clone()),
+            ..test_input()
+        })
+        .await;
+        let mut mids = HashMap::new();
+        mids.insert("ETH".to_string(), "100".to_string());
+        eth.process_message(Message::AllMids(crate::AllMids {
+            data: crate::AllMidsData { mids },
+        }))
+        .await;
+        let mut fill = fills::fill("B", 0.95, 1, 0);
+        fill.coin = "ETH".to_string();
+        eth.process_message(fill_message(fill)).await;
+
+        assert!((exposure.lock().unwrap().aggregate_notional() - 95.0).abs() < EPSILON);
+        assert!(!btc.exposure_allows(true, 0.001));
+        assert!(btc.exposure_allows(true, 0.0005));
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_feeds_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let (flushed_sender, mut flushed) = unbounded_channel();
+        market_maker.attach_feed(FeedTask::spawn(|mut cancellation| async move {
+            cancellation.cancelled().await;
+            flushed_sender.send(()).unwrap();
+        }));
+
+        market_maker.shutdown().await;
+        assert!(flushed.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn shutdown_cancels_working_orders_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.quotes.orders = vec![(1, 0.001), (2, 0.001)];
+        market_maker
+            .unfilled_orders
+            .insert(3, (Instant::now(), 0.001));
+        market_maker.passive_order = Some(execution::PassiveOrder {
+            oid: 4,
+            is_buy: true,
+            remaining: 0.001,
+            placed_at: Instant::now(),
+        });
+
+        let mut oids = market_maker.take_working_orders();
+        oids.sort_unstable();
+        assert_eq!(oids, vec![1, 2, 3, 4]);
+        assert!(market_maker.take_working_orders().is_empty());
+        assert!(market_maker.passive_order.is_none());
+    }
+
+    #[tokio::test]
+    async fn z_threshold_schedule_test() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let market_maker = test_market_maker(Input {
+            z_threshold_schedule: vec![ZThresholdWindow {
+                start: at(0, 0),
+                end: at(8, 0),
+                z_threshold: 3.0,
+            }],
+            ..test_input()
+        })
+        .await;
+        assert!((market_maker.z_threshold_at(at(7, 59)) - 3.0).abs() < EPSILON);
+        assert!((market_maker.z_threshold_at(at(8, 0)) - 2.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn volatility_scaling_test() {
+        let mut market_maker = test_market_maker(Input {
+            volatility_scaling: Some(VolatilityScaling {
+                recent_window: 2,
+                min_multiplier: 0.5,
+                max_multiplier: 2.0,
+            }),
+            ..test_input()
+        })
+        .await;
+        let now = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        // Too few recent diffs to measure, the base threshold applies
+        let (_, z_threshold) = market_maker
+            .entry_signal(1.0, 0.0, 1.0, now)
+            .unwrap()
+            .level();
+        assert!((z_threshold - 2.0).abs() < EPSILON);
+
+        // Recent diffs three times as noisy as the window: clamped to twice the base
+        market_maker.recent_diffs.push_back(-3.0);
+        market_maker.recent_diffs.push_back(3.0);
+        let window_stddev = market_maker.recent_diffs.stddev() / 3.0;
+        let (_, z_threshold) = market_maker
+            .entry_signal(1.0, 0.0, window_stddev, now)
+            .unwrap()
+            .level();
+        assert!((z_threshold - 4.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn loss_penalty_test() {
+        let mut market_maker = test_market_maker(Input {
+            loss_penalty: 2.0,
+            ..test_input()
+        })
+        .await;
+        let now = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        // A profit leaves the threshold alone, a loss doubles it
+        market_maker.record_realized_pnl(1.0, Instant::now());
+        let (_, z_threshold) = market_maker
+            .entry_signal(1.0, 0.0, 1.0, now)
+            .unwrap()
+            .level();
+        assert!((z_threshold - 2.0).abs() < EPSILON);
+        market_maker.record_realized_pnl(-1.0, Instant::now());
+        let (_, z_threshold) = market_maker
+            .entry_signal(1.0, 0.0, 1.0, now)
+            .unwrap()
+            .level();
+        assert!((z_threshold - 4.0).abs() < 1e-3);
+
+        // and decays back to the base over the half-life
+        market_maker.last_loss_at = Instant::now().checked_sub(Duration::from_mins(5));
+        let (_, z_threshold) = market_maker
+            .entry_signal(1.0, 0.0, 1.0, now)
+            .unwrap()
+            .level();
+        assert!((z_threshold - 3.0).abs() < 1e-3);
+    }
+
+    #[tokio::test]
+    async fn decision_history_test() {
+        let mut market_maker = test_market_maker(Input {
+            window_prior: Some(WindowPrior {
+                mean_bps: 0.0,
+                stddev_bps: 100.0,
+                weight: 1.0,
+            }),
+            decision_history: 2,
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        for mid in [100.0, 100.1, 100.2] {
+            market_maker.latest_mid_price = mid;
+            market_maker.on_price_update().await;
+        }
+
+        let decisions: Vec<_> = market_maker.decisions().collect();
+        assert_eq!(decisions.len(), 2);
+        assert!((decisions[0].hl_mid - 100.1).abs() < EPSILON);
+        assert!((decisions[1].hl_mid - 100.2).abs() < EPSILON);
+        assert_eq!(decisions[1].outcome, DecisionOutcome::NoSignal);
+    }
+
+    #[tokio::test]
+    async fn invalid_mid_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let mids_message = |px: &str| {
+            let mut mids = HashMap::new();
+            mids.insert("BTC".to_string(), px.to_string());
+            Message::AllMids(crate::AllMids {
+                data: crate::AllMidsData { mids },
+            })
+        };
+        market_maker.process_message(mids_message("100")).await;
+        for px in ["NaN", "0", "-100", "inf"] {
+            market_maker.process_message(mids_message(px)).await;
+            assert!((market_maker.latest_mid_price - 100.0).abs() < EPSILON);
+        }
+
+        // An invalid reference price doesn't enter the window either
+        market_maker.binance_price.set(f64::NAN);
+        market_maker.on_price_update().await;
+        assert!(market_maker.diffs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reconnect_reconciles_once_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let now = Instant::now();
+        // A flapping connection reconnects twice in quick succession
+        assert!(market_maker.should_reconcile(now));
+        assert!(!market_maker.should_reconcile(now + Duration::from_secs(1)));
+        assert!(market_maker.should_reconcile(now + Duration::from_secs(31)));
+    }
+
+    #[tokio::test]
+    async fn signal_only_reconnect_test() {
+        let mut market_maker = test_market_maker(Input {
+            subscriptions: Subscriptions::signal_only(),
+            protective_stop_bps: Some(50.0),
+            ..test_input()
+        })
+        .await;
+        market_maker.inventory.reset(0.002, 100_000.0);
+        market_maker.on_reconnect().await;
+        // Neither the position nor protective orders are touched
+        assert!((market_maker.inventory.position() - 0.002).abs() < EPSILON);
+        assert!(market_maker.protective_orders.is_none());
+        assert_eq!(market_maker.metrics().values().orders, 0);
+    }
+
+    #[tokio::test]
+    async fn spot_reconcile_test() {
+        let mut market_maker = test_market_maker(Input {
+            asset: "@107".to_string(),
+            market_kind: MarketKind::Spot,
+            max_absolute_position_size: 10.0,
+            ..test_input()
+        })
+        .await;
+        market_maker.spot_base = Some("HYPE".to_string());
+        market_maker.inventory.reset(5.0, 20.0);
+        let balances = [crate::UserTokenBalance {
+            coin: "HYPE".to_string(),
+            hold: "0.0".to_string(),
+            total: "3.0".to_string(),
+            entry_ntl: "60.0".to_string(),
+        }];
+        let (position, entry_px) = fills::spot_position(&balances, "HYPE");
+        market_maker.reconcile_position(position, entry_px);
+        assert!((market_maker.inventory.position() - 3.0).abs() < EPSILON);
+        // Sells stay capped by the reconciled balance
+        let headroom = market_maker.position_headroom(false, 5.0);
+        assert!((headroom.clamped_size - 3.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn entry_band_test() {
+        let now = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        let sigma = test_market_maker(test_input()).await;
+        let (z, threshold) = sigma
+            .entry_signal(0.0016, 0.001, 0.0002, now)
+            .unwrap()
+            .level();
+        assert!((z - 3.0).abs() < 1e-9);
+        assert!((threshold - 2.0).abs() < EPSILON);
+        assert_eq!(sigma.entry_signal(0.0016, 0.001, 0.0, now), None);
+
+        let band = test_market_maker(Input {
+            entry_band_bps: Some(5.0),
+            ..test_input()
+        })
+        .await;
+        // 6 bps above the mean crosses the band, 4 bps below doesn't
+        let signal = band.entry_signal(0.0016, 0.001, 0.0, now).unwrap();
+        assert_eq!(signal.z(), None, "a band signal isn't a z-score");
+        let (bps, threshold_bps) = signal.level();
+        assert!(bps > threshold_bps);
+        assert!((bps - 6.0).abs() < 1e-9);
+        let (bps, threshold_bps) = band
+            .entry_signal(0.0006, 0.001, 0.0002, now)
+            .unwrap()
+            .level();
+        assert!(bps.abs() < threshold_bps);
+        assert_eq!(
+            band.entry_signal(0.0016, 0.001, 0.0, now)
+                .unwrap()
+                .to_string(),
+            "6.00 bps from the mean, band 5.00 bps"
+        );
+
+        // The time-of-day schedule is in sigma and leaves the band alone, a loss
+        // penalty widens it
+        let mut band = test_market_maker(Input {
+            entry_band_bps: Some(5.0),
+            z_threshold_schedule: vec![ZThresholdWindow {
+                start: NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+                z_threshold: 4.0,
+            }],
+            loss_penalty: 2.0,
+            ..test_input()
+        })
+        .await;
+        let (_, threshold_bps) = band.entry_signal(0.0016, 0.001, 0.0, now).unwrap().level();
+        assert!((threshold_bps - 5.0).abs() < EPSILON);
+        band.record_realized_pnl(-1.0, Instant::now());
+        let (_, threshold_bps) = band.entry_signal(0.0016, 0.001, 0.0, now).unwrap().level();
+        assert!((threshold_bps - 10.0).abs() < 1e-3);
+    }
+
+    #[tokio::test]
+    async fn min_stddev_test() {
+        let now = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        let floored = test_market_maker(Input {
+            min_stddev_bps: Some(0.5),
+            min_stddev_mean_fraction: Some(0.1),
+            ..test_input()
+        })
+        .await;
+        // A 0.1 bps stddev would turn a 1 bps move into a z-score of 10
+        assert_eq!(floored.entry_signal(0.0001, 0.0, 0.000_01, now), None);
+        // Above the absolute floor, but under a tenth of a 10 bps mean
+        assert_eq!(floored.entry_signal(0.0011, 0.001, 0.000_08, now), None);
+        let (z, _) = floored
+            .entry_signal(0.0013, 0.001, 0.000_1, now)
+            .unwrap()
+            .level();
+        assert!((z - 3.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn reconnect_cooldown_pauses_trading_test() {
+        let limiter = Arc::new(Mutex::new(ReconnectLimiter::new(
+            1,
+            Duration::from_mins(1),
+            Duration::from_mins(5),
+        )));
+        let market_maker = test_market_maker(Input {
+            reconnect_limiter: Some(limiter.clone()),
+            ..test_input()
+        })
+        .await;
+        let now = Instant::now();
+        assert!(limiter.lock().unwrap().try_attempt(now).is_ok());
+        assert!(!market_maker.reconnect_cooling_down(now));
+
+        assert!(limiter.lock().unwrap().try_attempt(now).is_err());
+        assert!(market_maker.reconnect_cooling_down(now + Duration::from_mins(1)));
+    }
+
+    #[tokio::test]
+    async fn max_active_assets_test() {
+        let exposure = Arc::new(Mutex::new(
+            ExposureTracker::new(f64::MAX).with_max_active_assets(1),
+        ));
+        let mut eth = test_market_maker(Input {
+            asset: "ETH".to_string(),
+            exposure: Some(exposure.clone()),
+            ..test_input()
+        })
+        .await;
+        let btc = test_market_maker(Input {
+            exposure: Some(exposure),
+            ..test_input()
+        })
+        .await;
+        assert!(btc.pool_allows_entry());
+
+        let mut fill = fills::fill("B", 1.0, 1, 0);
+        fill.coin = "ETH".to_string();
+        eth.process_message(fill_message(fill)).await;
+        assert!(eth.pool_allows_entry());
+        assert!(!btc.pool_allows_entry());
+
+        // A reconnect finding the position closed frees the slot
+        eth.reconcile_position(0.0, 0.0);
+        assert!(btc.pool_allows_entry());
+    }
+
+    #[tokio::test]
+    async fn stale_orders_test() {
+        let open_orders = [
+            orders::open_order("BTC", 1, "B", "99"),
+            orders::open_order("ETH", 2, "B", "2000"),
+            orders::open_order("BTC", 3, "A", "101"),
+        ];
+
+        let mut market_maker = test_market_maker(Input {
+            resting_order_timeout: Some(Duration::from_secs(5)),
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        let cancels =
+            market_maker.apply_stale_order_policy(StaleOrderPolicy::CancelAll, &open_orders);
+        let oids: Vec<_> = cancels.iter().map(|cancel| cancel.oid).collect();
+        assert_eq!(oids, vec![1, 3]);
+        assert_eq!(market_maker.open_orders(), 0);
+
+        let cancels = market_maker.apply_stale_order_policy(StaleOrderPolicy::Adopt, &open_orders);
+        assert!(cancels.is_empty());
+        assert_eq!(market_maker.open_orders(), 2);
+        assert!((market_maker.unfilled_orders[&3].1 - 0.01).abs() < EPSILON);
+        // Adopted orders are swept like those this run placed
+        market_maker
+            .sweep_resting_orders(Instant::now() + Duration::from_secs(6))
+            .await;
+        assert_eq!(market_maker.open_orders(), 0);
+
+        // And cancelled on shutdown
+        market_maker.apply_stale_order_policy(StaleOrderPolicy::Adopt, &open_orders);
+        let mut oids = market_maker.take_working_orders();
+        oids.sort_unstable();
+        assert_eq!(oids, vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn depth_capped_quote_size_test() {
+        let mut market_maker = test_market_maker(Input {
+            target_liquidity: 1.0,
+            quote_depth_fraction: Some(0.5),
+            ..test_input()
+        })
+        .await;
+        // No book yet, quote the fixed size
+        assert!((market_maker.quote_size(true, 99.9) - 1.0).abs() < EPSILON);
+
+        market_maker.latest_book = Some(L2BookData {
+            coin: "BTC".to_string(),
+            time: 0,
+            levels: vec![vec![book::level(99.9, 0.4)], vec![book::level(100.1, 5.0)]],
+        });
+        assert!((market_maker.quote_size(true, 99.9) - 0.2).abs() < EPSILON);
+        assert!((market_maker.quote_size(false, 100.1) - 1.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn clamped_trade_headroom_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.0035, 1, 0)))
+            .await;
+
+        let headroom = market_maker.position_headroom(true, 0.001);
+        assert!((headroom.position - 0.0035).abs() < EPSILON);
+        assert!((headroom.cap - 0.004).abs() < EPSILON);
+        assert!((headroom.headroom - 0.0005).abs() < EPSILON);
+        assert!((headroom.clamped_size - 0.0005).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn fair_value_source_test() {
+        let model_price = Arc::new(Mutex::new(0.0));
+        let mut market_maker = test_market_maker(Input {
+            window_prior: Some(WindowPrior {
+                mean_bps: 0.0,
+                stddev_bps: 10.0,
+                weight: 1.0,
+            }),
+            fair_value: Some(Box::new(model_price.clone())),
+            ..test_input()
+        })
+        .await;
+        // The Binance price is ignored in favour of the model
+        market_maker.binance_price.set(50.0);
+        market_maker.latest_mid_price = 100.0;
+        market_maker.on_price_update().await;
+        assert!(market_maker.diffs.is_empty());
+
+        *model_price.lock().unwrap() = 100.0;
+        market_maker.on_price_update().await;
+        assert_eq!(market_maker.diffs.len(), 1);
+        let decision = market_maker.decisions().last().unwrap();
+        assert!((decision.reference_price - 100.0).abs() < EPSILON);
+        assert_eq!(decision.outcome, DecisionOutcome::NoSignal);
+    }
+
+    #[tokio::test]
+    async fn inline_and_user_event_fill_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        // The IOC response reports the fill inline first
+        market_maker.inline_fills.acknowledge(7, 0.001);
+        assert!(market_maker.inventory.position().abs() < EPSILON);
+
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.001, 7, 0)))
+            .await;
+        assert!((market_maker.inventory.position() - 0.001).abs() < EPSILON);
+        assert_eq!(market_maker.inline_fills.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn order_retry_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(5, 1.0)),
+            max_order_retries: 3,
+            ..test_input()
+        })
+        .await;
+        let (filled, _) = market_maker
+            .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+            .await
+            .filled();
+        assert!(filled < EPSILON);
+        // The first attempt and three retries
+        assert_eq!(market_maker.reject_counts()[&RejectCategory::RateLimit], 4);
+
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(5, 0.5)),
+            max_order_retries: 20,
+            ..test_input()
+        })
+        .await;
+        for _ in 0..10 {
+            let (filled, _) = market_maker
+                .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+                .await
+                .filled();
+            assert!((filled - 0.001).abs() < EPSILON);
+        }
+    }
+
+    #[tokio::test]
+    async fn order_rate_limit_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            order_rate_limit: Some(TokenBucket::new(0.001, 2.0)),
+            ..test_input()
+        })
+        .await;
+        assert_eq!(market_maker.order_rate_utilization(), Some(0.0));
+        for expected in [
+            OrderOutcome::Filled {
+                size: 0.001,
+                oid: 1,
+            },
+            OrderOutcome::Filled {
+                size: 0.001,
+                oid: 2,
+            },
+            OrderOutcome::NotSent {
+                reason: "rate_limit",
+            },
+        ] {
+            assert_eq!(
+                market_maker
+                    .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+                    .await,
+                expected
+            );
+        }
+        assert!(market_maker.order_rate_utilization().unwrap() > 0.99);
+    }
+
+    #[tokio::test]
+    async fn signal_latency_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        let count = "market_maker_order_latency_seconds_count{asset=\"BTC\"}";
+        // Orders not sent on a signal aren't measured
+        market_maker
+            .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+            .await;
+        assert!(market_maker
+            .metrics()
+            .render()
+            .contains(&format!("{count} 0\n")));
+
+        market_maker.signal_at = Some((Instant::now(), None));
+        market_maker
+            .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+            .await;
+        assert!(market_maker
+            .metrics()
+            .render()
+            .contains(&format!("{count} 1\n")));
+    }
+
+    #[tokio::test]
+    async fn circuit_breaker_halts_orders_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 1.0)),
+            circuit_breaker: Some(CircuitBreaker::new(2, Duration::from_mins(1))),
+            ..test_input()
+        })
+        .await;
+        for _ in 0..5 {
+            market_maker
+                .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+                .await;
+        }
+        // Only the two orders that tripped the breaker reached the exchange, or count
+        assert_eq!(market_maker.reject_counts()[&RejectCategory::RateLimit], 2);
+        assert_eq!(market_maker.metrics().values().orders, 2);
+    }
+
+    #[tokio::test]
+    async fn dry_run_rate_limit_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(42, 0.25)),
+            ..test_input()
+        })
+        .await;
+        let mut filled = 0;
+        for _ in 0..400 {
+            let (amount, _) = market_maker
+                .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+                .await
+                .filled();
+            if amount > EPSILON {
+                filled += 1;
+            }
+        }
+        let rate_limited = market_maker.reject_counts()[&RejectCategory::RateLimit];
+        assert_eq!(filled + rate_limited, 400);
+        assert!(
+            (70..130).contains(&rate_limited),
+            "{rate_limited} rate limited"
+        );
+    }
+
+    async fn alternating_update(market_maker: &mut MarketMaker, i: usize) {
+        market_maker.latest_mid_price = if i.is_multiple_of(2) { 100.0 } else { 100.01 };
+        market_maker.on_price_update().await;
+    }
+
+    #[tokio::test]
+    async fn resize_window_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..100 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        assert!(market_maker.is_warm());
+        let decisions = market_maker.decisions().count();
+
+        // Shrinking keeps trading on the newest samples
+        market_maker.resize_window(10);
+        assert_eq!(market_maker.diffs.len(), 10);
+        assert!(market_maker.is_warm());
+        alternating_update(&mut market_maker, 0).await;
+        assert_eq!(market_maker.diffs.len(), 10);
+        assert_eq!(market_maker.decisions().count(), decisions + 1);
+
+        // Growing waits for the larger window before deciding again
+        market_maker.resize_window(20);
+        assert!(!market_maker.is_warm());
+        for i in 0..9 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        assert_eq!(market_maker.decisions().count(), decisions + 1);
+        alternating_update(&mut market_maker, 9).await;
+        assert!(market_maker.is_warm());
+        assert_eq!(market_maker.decisions().count(), decisions + 2);
+    }
+
+    #[tokio::test]
+    async fn passive_order_escalates_test() {
+        let wait = Duration::from_secs(1);
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            execution_style: ExecutionStyle::PostThenCross { wait },
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        market_maker.post_passive_order(true, 0.001).await;
+        let passive = market_maker.passive_order.unwrap();
+        assert!(passive.is_buy);
+
+        // Not filled yet, but still within the wait
+        market_maker.escalate_passive_order(passive.placed_at).await;
+        assert!(market_maker.passive_order.is_some());
+        assert_eq!(market_maker.inline_fills.pending(), 0);
+
+        market_maker
+            .escalate_passive_order(passive.placed_at + wait)
+            .await;
+        assert!(market_maker.passive_order.is_none());
+        // The cross filled inline
+        assert_eq!(market_maker.inline_fills.pending(), 1);
+    }
+
+    #[tokio::test]
+    async fn session_test() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            paper_trading: true,
+            session_end: Some(at(20, 0)),
+            session_start: Some(at(8, 0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        market_maker
+            .apply_fill(&fills::fill("B", 0.002, 1, 0))
+            .await;
+        market_maker.check_session(at(19, 59)).await;
+        assert!(!market_maker.session_parked());
+
+        market_maker.check_session(at(20, 0)).await;
+        assert!(market_maker.session_parked());
+        assert!(market_maker.inventory.position().abs() < EPSILON);
+        let intent = market_maker.trade_intent(z_score(3.0, 2.0)).unwrap();
+        assert_eq!(
+            market_maker.act_on_intent(intent, None, 100.0, 10.0).await,
+            DecisionOutcome::Blocked("session_closed")
+        );
+
+        market_maker.check_session(at(8, 0)).await;
+        assert!(!market_maker.session_parked());
+    }
+
+    #[tokio::test]
+    async fn post_only_test() {
+        let wait = Duration::from_secs(1);
+        let would_cross = "Post only order would have immediately matched, bbo was 99@100.";
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0).with_responses([Ok(sim::response(
+                ExchangeDataStatus::Error(would_cross.to_string()),
+            ))])),
+            execution_style: ExecutionStyle::PostThenCross { wait },
+            post_only: true,
+            maker_rebate_bps: 1.0,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        assert!((market_maker.round_trip_fee_bps() + 2.0).abs() < 1e-9);
+
+        // Rejected for crossing: nothing rests and nothing is crossed instead
+        market_maker.post_passive_order(true, 0.001).await;
+        assert!(market_maker.passive_order.is_none());
+        assert_eq!(market_maker.reject_counts()[&RejectCategory::WouldCross], 1);
+
+        market_maker.post_passive_order(true, 0.001).await;
+        let passive = market_maker.passive_order.unwrap();
+        market_maker
+            .escalate_passive_order(passive.placed_at + wait)
+            .await;
+        assert!(market_maker.passive_order.is_none());
+        assert_eq!(market_maker.inline_fills.pending(), 0);
+
+        // A maker fill of 0.001 at 100 earns 1 bps of its notional
+        let mut maker_fill = fills::fill("B", 0.001, 1, 0);
+        maker_fill.crossed = false;
+        market_maker.apply_fill(&maker_fill).await;
+        assert!((market_maker.realized_pnl - 0.000_01).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn position_cap_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.004, 1, 0)))
+            .await;
+        assert!((market_maker.position() - 0.004).abs() < EPSILON);
+
+        // At the cap buys are skipped, sells still go through
+        assert!(!market_maker.execute_immediate_trade(true, 0.001).await);
+        assert_eq!(market_maker.inline_fills.pending(), 0);
+        assert!(market_maker.execute_immediate_trade(false, 0.001).await);
+        assert_eq!(market_maker.inline_fills.pending(), 1);
+    }
+
+    #[tokio::test]
+    async fn pnl_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let fill = |side, sz, px: &str, fee: &str, oid| {
+            let mut fill = fills::fill(side, sz, oid, 0);
+            fill.px = px.to_string();
+            fill.fee = fee.to_string();
+            fill_message(fill)
+        };
+        market_maker
+            .process_message(fill("B", 1.0, "100", "0.1", 1))
+            .await;
+        // Flips from long 1 to short 1 in a single fill
+        market_maker
+            .process_message(fill("A", 2.0, "105", "0.2", 2))
+            .await;
+        assert!((market_maker.realized_pnl() - 4.7).abs() < EPSILON);
+
+        market_maker.latest_mid_price = 103.0;
+        assert!((market_maker.unrealized_pnl() - 2.0).abs() < EPSILON);
+        market_maker.binance_price.set(101.0);
+        assert!((market_maker.unrealized_pnl() - 2.0).abs() < EPSILON);
+        market_maker.mark_source = MarkSource::Reference;
+        assert!((market_maker.unrealized_pnl() - 4.0).abs() < EPSILON);
+        market_maker.mark_source = MarkSource::MidOfBoth;
+        assert!((market_maker.unrealized_pnl() - 3.0).abs() < EPSILON);
+        market_maker.mark_source = MarkSource::HlMid;
+
+        // Other assets are ignored
+        let mut other = fills::fill("B", 5.0, 3, 0);
+        other.coin = "ETH".to_string();
+        market_maker.process_message(fill_message(other)).await;
+        assert!((market_maker.position() + 1.0).abs() < EPSILON);
+        assert!((market_maker.realized_pnl() - 4.7).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn stale_reference_price_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        market_maker.latest_mid_price = 100.0;
+        market_maker.binance_price.store(ReferencePrice {
+            price: 100.0,
+            updated_at: Instant::now().checked_sub(Duration::from_secs(6)),
+        });
+        market_maker.on_price_update().await;
+        assert!(market_maker.diffs.is_empty());
+
+        market_maker.binance_price.set(100.0);
+        market_maker.on_price_update().await;
+        assert_eq!(market_maker.diffs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn multi_asset_dispatch_test() {
+        let btc = test_market_maker(test_input()).await;
+        let eth_config = AssetConfig {
+            asset: "ETH".to_string(),
+            window_size: 50,
+            z_threshold: 1.5,
+            trade_size: 0.01,
+            binance_symbol: "ethusdt".to_string(),
+            wallet: None,
+        };
+        let eth = test_market_maker(eth_config.apply(Input {
+            wallet: btc.exchange_client.wallet.clone(),
+            ..test_input()
+        }))
+        .await;
+        assert_eq!(eth.window_size, 50);
+        // SOL trades from a sub-account of its own
+        let sol_config = AssetConfig {
+            asset: "SOL".to_string(),
+            binance_symbol: "solusdt".to_string(),
+            wallet: Some(LocalWallet::new(&mut rand::thread_rng())),
+            ..eth_config
+        };
+        let sol = test_market_maker(sol_config.apply(test_input())).await;
+        let (main_user, sol_user) = (btc.user_address, sol.user_address);
+        assert_eq!(eth.user_address, main_user);
+        assert_ne!(sol_user, main_user);
+        let info_client = InfoClient::new(None, Some(BaseUrl::Localhost))
+            .await
+            .unwrap();
+        let mut multi =
+            MultiAssetMarketMaker::with_info_client(vec![btc, eth, sol], info_client).unwrap();
+
+        let mut mids = HashMap::new();
+        mids.insert("BTC".to_string(), "100".to_string());
+        mids.insert("ETH".to_string(), "10".to_string());
+        multi
+            .dispatch(
+                main_user,
+                Message::AllMids(crate::AllMids {
+                    data: crate::AllMidsData { mids },
+                }),
+            )
+            .await;
+        assert!((multi.maker("BTC").unwrap().latest_mid_price - 100.0).abs() < EPSILON);
+        assert!((multi.maker("ETH").unwrap().latest_mid_price - 10.0).abs() < EPSILON);
+
+        let eth_fill = TradeInfo {
+            coin: "ETH".to_string(),
+            ..fills::fill("B", 0.01, 1, 1)
+        };
+        multi.dispatch(main_user, fill_message(eth_fill)).await;
+        assert!((multi.maker("ETH").unwrap().position() - 0.01).abs() < EPSILON);
+        assert!(multi.maker("BTC").unwrap().position().abs() < EPSILON);
+
+        // A fill on the main wallet's stream never reaches the sub-account's asset
+        let sol_fill = TradeInfo {
+            coin: "SOL".to_string(),
+            ..fills::fill("B", 0.01, 2, 2)
+        };
+        multi
+            .dispatch(main_user, fill_message(sol_fill.clone()))
+            .await;
+        assert!(multi.maker("SOL").unwrap().position().abs() < EPSILON);
+        multi.dispatch(sol_user, fill_message(sol_fill)).await;
+        assert!((multi.maker("SOL").unwrap().position() - 0.01).abs() < EPSILON);
+
+        // A disabled asset stops trading but keeps tracking prices
+        assert!(multi.set_enabled("ETH", false));
+        assert!(!multi.set_enabled("DOGE", false));
+        assert_eq!(multi.is_enabled("ETH"), Some(false));
+        assert_eq!(multi.is_enabled("BTC"), Some(true));
+        assert!(multi.control_handle("ETH").is_some());
+        let mut mids = HashMap::new();
+        mids.insert("BTC".to_string(), "101".to_string());
+        mids.insert("ETH".to_string(), "11".to_string());
+        multi
+            .dispatch(
+                main_user,
+                Message::AllMids(crate::AllMids {
+                    data: crate::AllMidsData { mids },
+                }),
+            )
+            .await;
+        let eth = multi.maker("ETH").unwrap();
+        assert!(eth.is_paused());
+        assert!((eth.latest_mid_price - 11.0).abs() < EPSILON);
+        assert!(multi.set_enabled("ETH", true));
+        assert_eq!(multi.is_enabled("ETH"), Some(true));
+    }
+
+    #[tokio::test]
+    async fn dump_decisions_command_test() {
+        let mut market_maker = test_market_maker(Input {
+            window_size: 2,
+            ..test_input()
+        })
+        .await;
+        let handle = market_maker.control_handle();
+        let mut control = market_maker.control_receiver.take().unwrap();
+        market_maker.binance_price.set(100.0);
+        for mid in [100.0, 100.1, 100.2] {
+            market_maker.latest_mid_price = mid;
+            market_maker.on_price_update().await;
+        }
+        let before = market_maker.decisions().count();
+        assert!(before > 0);
+
+        assert!(handle.dump_decisions());
+        let command = control.recv().await.unwrap();
+        assert_eq!(command, ControlCommand::DumpDecisions);
+        // Logging the history leaves it, and trading, as they were
+        market_maker.on_control(command).await;
+        assert_eq!(market_maker.decisions().count(), before);
+        assert!(!market_maker.is_paused());
+    }
+
+    #[tokio::test]
+    async fn heartbeat_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let heartbeat = market_maker.heartbeat(Instant::now());
+        assert!(heartbeat.starts_with("BTC alive: last tick never, position 0"));
+        assert!(heartbeat.ends_with("Hyperliquid mid never, Binance price never"));
+
+        market_maker.binance_price.set(100.0);
+        market_maker.process_message(mids_message(100.0)).await;
+        market_maker.on_control(ControlCommand::Pause).await;
+        let heartbeat = market_maker.heartbeat(Instant::now());
+        assert!(!heartbeat.contains("never"), "{heartbeat}");
+        assert!(heartbeat.ends_with(", paused"));
+    }
+
+    #[tokio::test]
+    async fn snapshot_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let state = market_maker.snapshot();
+        assert_eq!(state.asset, "BTC");
+        assert_eq!(state.z_score, None);
+        assert_eq!(state.window_len, 0);
+        assert_eq!(state.mid_age_seconds, None);
+        assert!(!state.halted);
+
+        market_maker.binance_price.set(100.0);
+        market_maker.process_message(mids_message(100.0)).await;
+        market_maker
+            .process_message(fill_message(fills::fill("B", 0.01, 1, 1)))
+            .await;
+        market_maker.last_signal = Some(trade_log::SignalSnapshot {
+            z: Some(-2.5),
+            mean: 0.0001,
+            stddev: 0.0002,
+            funding_rate: None,
+        });
+        let state = market_maker.snapshot();
+        assert!((state.position - 0.01).abs() < EPSILON);
+        assert_eq!(state.z_score, Some(-2.5));
+        assert!(state.mid_age_seconds.is_some());
+        assert!(state.reference_price_age_seconds.is_some());
+
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["asset"], "BTC");
+        assert_eq!(json["z_score"], -2.5);
+        assert!(json["last_trade_age_seconds"].is_null());
+    }
+
+    #[tokio::test]
+    async fn log_sample_rate_test() {
+        let mut market_maker = test_market_maker(Input {
+            log_sample_rate: 3,
+            ..test_input()
+        })
+        .await;
+        let sampled: Vec<bool> = (0..6)
+            .map(|_| market_maker.sample_tick_log(z_score(0.5, 2.0)))
+            .collect();
+        assert_eq!(sampled, [true, false, false, true, false, false]);
+        // A tick that signals a trade is always logged and doesn't count
+        assert!(market_maker.sample_tick_log(z_score(-2.5, 2.0)));
+        assert!(market_maker.sample_tick_log(z_score(0.5, 2.0)));
+
+        let error = Input {
+            log_sample_rate: 0,
+            ..test_input()
+        }
+        .validate();
+        assert!(matches!(error, Err(MarketMakerError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn missing_mid_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        let eth_only = || {
+            let mids = std::iter::once(("ETH".to_string(), "10".to_string())).collect();
+            Message::AllMids(AllMids {
+                data: AllMidsData { mids },
+            })
+        };
+        market_maker.process_message(eth_only()).await;
+        assert!(market_maker.mid_missing);
+        market_maker.process_message(eth_only()).await;
+        assert!(market_maker.mid_missing);
+
+        market_maker.process_message(mids_message(100.0)).await;
+        assert!(!market_maker.mid_missing);
+        assert!((market_maker.latest_mid_price - 100.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn log_precision_test() {
+        let market_maker = test_market_maker(Input {
+            asset: "PEPE".to_string(),
+            decimals: 7,
+            size_decimals: 0,
+            ..test_input()
+        })
+        .await;
+        assert_eq!(market_maker.fmt_px(0.000_012_34), "0.0000123");
+        assert_eq!(market_maker.fmt_sz(1_000_000.0), "1000000");
+    }
+
+    #[tokio::test]
+    async fn paper_trading_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        assert!(market_maker.execute_immediate_trade(true, 0.001).await);
+        assert!((market_maker.position() - 0.001).abs() < EPSILON);
+        assert_eq!(market_maker.inline_fills.pending(), 0);
+
+        market_maker.latest_mid_price = 101_000.0;
+        assert!(market_maker.execute_immediate_trade(false, 0.001).await);
+        assert!(market_maker.position().abs() < EPSILON);
+        // Bought at 100200, sold at 100798
+        assert!((market_maker.realized_pnl() - 0.598).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn order_jitter_test() {
+        let jitter_maker = |seed| {
+            test_market_maker(Input {
+                order_jitter: Duration::from_millis(100),
+                jitter_seed: Some(seed),
+                ..test_input()
+            })
+        };
+        let (mut first, mut second, mut other) = (
+            jitter_maker(7).await,
+            jitter_maker(7).await,
+            jitter_maker(8).await,
+        );
+        let jitters = |market_maker: &mut MarketMaker| {
+            (0..10)
+                .map(|_| market_maker.next_order_jitter())
+                .collect::<Vec<_>>()
+        };
+        let sequence = jitters(&mut first);
+        // The same seed waits the same before every order
+        assert_eq!(sequence, jitters(&mut second));
+        assert_ne!(sequence, jitters(&mut other));
+        assert!(sequence
+            .iter()
+            .all(|jitter| *jitter < Duration::from_millis(100)));
+
+        let mut market_maker = test_market_maker(test_input()).await;
+        assert_eq!(market_maker.next_order_jitter(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn simulated_friction_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            simulated_latency: Duration::from_millis(50),
+            simulated_slippage_bps: 10.0,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        let start = Instant::now();
+        assert!(market_maker.execute_immediate_trade(true, 0.001).await);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        // Ordered at 100200 and filled 10 bps higher
+        assert!((market_maker.inventory.avg_entry_px() - 100_300.2).abs() < 1e-6);
+
+        assert!(matches!(
+            Input {
+                simulated_slippage_bps: -1.0,
+                ..test_input()
+            }
+            .validate(),
+            Err(MarketMakerError::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn order_response_test() {
+        let filled = |oid| {
+            sim::response(ExchangeDataStatus::Filled(FilledOrder {
+                total_sz: "0.001".to_string(),
+                avg_px: "100200".to_string(),
+                oid,
+            }))
+        };
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            dry_run: Some(FillModel::new(1, 0.0).with_responses([
+                Ok(filled(5)),
+                Ok(sim::response(ExchangeDataStatus::Resting(RestingOrder {
+                    oid: 6,
+                }))),
+                Ok(sim::response(ExchangeDataStatus::Error(
+                    "Insufficient margin to place order.".to_string(),
+                ))),
+                Ok(ExchangeResponseStatus::Err("Too many requests".to_string())),
+                Err(crate::Error::GenericRequest(
+                    "operation timed out".to_string(),
+                )),
+                Ok(filled(7)),
+            ])),
+            max_order_retries: 0,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        assert!(market_maker.execute_immediate_trade(true, 0.001).await);
+        assert!((market_maker.position() - 0.001).abs() < EPSILON);
+
+        // A resting order is tracked until swept, but nothing is booked yet
+        assert!(!market_maker.execute_immediate_trade(false, 0.001).await);
+        assert!(market_maker.unfilled_orders.contains_key(&6));
+        // Neither rejects nor transport errors book anything
+        for _ in 0..3 {
+            assert!(!market_maker.execute_immediate_trade(false, 0.001).await);
+        }
+        assert!((market_maker.position() - 0.001).abs() < EPSILON);
+        assert_eq!(market_maker.reject_counts()[&RejectCategory::Margin], 1);
+        assert_eq!(market_maker.reject_counts()[&RejectCategory::RateLimit], 1);
+
+        market_maker.latest_mid_price = 101_000.0;
+        assert!(market_maker.execute_immediate_trade(false, 0.001).await);
+        assert!(market_maker.position().abs() < EPSILON);
+        // Bought at 100200, sold at 100798
+        assert!((market_maker.realized_pnl() - 0.598).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn order_outcome_test() {
+        let margin = "Insufficient margin to place order.".to_string();
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0).with_responses([
+                Ok(sim::response(ExchangeDataStatus::Resting(RestingOrder {
+                    oid: 6,
+                }))),
+                Ok(sim::response(ExchangeDataStatus::Error(margin.clone()))),
+                Err(crate::Error::GenericRequest(
+                    "operation timed out".to_string(),
+                )),
+            ])),
+            max_order_retries: 0,
+            ..test_input()
+        })
+        .await;
+        let mut place = async || {
+            market_maker
+                .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+                .await
+        };
+        let resting = place().await;
+        assert_eq!(resting, OrderOutcome::Resting { oid: 6 });
+        assert_eq!(resting.filled(), (0.0, 0));
+        assert_eq!(place().await, OrderOutcome::Rejected { reason: margin });
+        assert_eq!(place().await, OrderOutcome::TransportError);
+    }
+
+    #[tokio::test]
+    async fn max_open_orders_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0).with_responses([Ok(sim::response(
+                ExchangeDataStatus::Resting(RestingOrder { oid: 6 }),
+            ))])),
+            max_open_orders: Some(1),
+            ..test_input()
+        })
+        .await;
+        let resting = market_maker
+            .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+            .await;
+        assert_eq!(resting, OrderOutcome::Resting { oid: 6 });
+        assert_eq!(market_maker.open_orders(), 1);
+
+        // At the cap only exits are sent
+        assert_eq!(
+            market_maker
+                .place_order("BTC".to_string(), 0.001, 100.0, true, false)
+                .await,
+            OrderOutcome::NotSent {
+                reason: "max_open_orders"
+            }
+        );
+        let (filled, _) = market_maker
+            .place_order("BTC".to_string(), 0.001, 100.0, false, true)
+            .await
+            .filled();
+        assert!((filled - 0.001).abs() < EPSILON);
+
+        // A partial fill leaves the order open, the rest of it closes it
+        market_maker
+            .apply_fill(&fills::fill("B", 0.000_4, 6, 1))
+            .await;
+        assert_eq!(market_maker.open_orders(), 1);
+        market_maker
+            .apply_fill(&fills::fill("B", 0.000_6, 6, 2))
+            .await;
+        assert_eq!(market_maker.open_orders(), 0);
+    }
+
+    #[tokio::test]
+    async fn partial_fill_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            dry_run: Some(FillModel::new(1, 0.0).with_responses([Ok(sim::response(
+                ExchangeDataStatus::Filled(FilledOrder {
+                    total_sz: "0.0003".to_string(),
+                    avg_px: "100200".to_string(),
+                    oid: 5,
+                }),
+            ))])),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        assert!(market_maker.execute_immediate_trade(true, 0.001).await);
+        // Only the filled part is booked, the rest was cancelled
+        assert!((market_maker.position() - 0.0003).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn pause_resume_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            window_size: 20,
+            ..test_input()
+        })
+        .await;
+        let handle = market_maker.control_handle();
+        let mut control = market_maker.control_receiver.take().unwrap();
+        market_maker.binance_price.set(100.0);
+
+        assert!(handle.pause());
+        market_maker.on_control(control.recv().await.unwrap()).await;
+        assert!(market_maker.is_paused());
+        for i in 0..20 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        market_maker.latest_mid_price = 101.0;
+        market_maker.on_price_update().await;
+        // The window kept filling while paused, but the signal wasn't traded
+        assert!(market_maker.is_warm());
+        assert_eq!(
+            market_maker.decisions().last().unwrap().outcome,
+            DecisionOutcome::Blocked("paused")
+        );
+
+        assert!(handle.resume());
+        market_maker.on_control(control.recv().await.unwrap()).await;
+        assert!(!market_maker.is_paused());
+        for i in 0..20 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        market_maker.latest_mid_price = 101.0;
+        market_maker.on_price_update().await;
+        let outcome = market_maker.decisions().last().unwrap().outcome;
+        assert!(!matches!(
+            outcome,
+            DecisionOutcome::NoSignal | DecisionOutcome::Blocked("paused")
+        ));
+    }
+
+    #[tokio::test]
+    async fn funding_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            funding_refresh: Some(Duration::from_hours(1)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        // 0.1% an hour: longs pay 10 bps over the expected hour
+        market_maker.funding_rate = Some(0.001);
+        market_maker.funding_checked_at = Some(Instant::now());
+        assert_eq!(
+            market_maker
+                .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 5.0)
+                .await,
+            DecisionOutcome::Blocked("funding")
+        );
+        market_maker.last_trade_at = None;
+        assert!(matches!(
+            market_maker
+                .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 12.0)
+                .await,
+            DecisionOutcome::Traded { is_buy: true, .. }
+        ));
+        // Shorts earn it
+        market_maker.last_trade_at = None;
+        assert!(matches!(
+            market_maker
+                .act_on_signal(z_score(3.0, 2.0), None, 100.0, 1.0)
+                .await,
+            DecisionOutcome::Traded { is_buy: false, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn taker_fee_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            taker_fee_bps: 4.5,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        assert_eq!(
+            market_maker
+                .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 8.0)
+                .await,
+            DecisionOutcome::Blocked("fees")
+        );
+        assert!(matches!(
+            market_maker
+                .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 9.5)
+                .await,
+            DecisionOutcome::Traded { is_buy: true, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn resubscribe_respects_reconnect_limit_test() {
+        let limiter = Arc::new(Mutex::new(ReconnectLimiter::new(
+            0,
+            Duration::from_mins(1),
+            Duration::from_mins(5),
+        )));
+        let mut market_maker = test_market_maker(Input {
+            reconnect_limiter: Some(limiter.clone()),
+            ..test_input()
+        })
+        .await;
+        market_maker.subscription_ids = vec![1, 2];
+        assert!(market_maker.resubscribe().await.is_none());
+        // The old subscriptions are left alone during the cooldown
+        assert_eq!(market_maker.subscription_ids, [1, 2]);
+        assert!(limiter.lock().unwrap().is_cooling_down(Instant::now()));
+    }
+
+    #[tokio::test]
+    async fn lot_size_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            ..test_input()
+        })
+        .await;
+        let meta: Meta =
+            serde_json::from_str(r#"{"universe":[{"name":"BTC","szDecimals":3}]}"#).unwrap();
+        market_maker.apply_asset_meta(&meta).unwrap();
+        assert_eq!(market_maker.size_decimals, 3);
+        assert_eq!(market_maker.decimals, 3);
+
+        // Floored to the lot size, never rounded up
+        let (filled, _) = market_maker
+            .place_order("BTC".to_string(), 0.199, 100.0, true, false)
+            .await
+            .filled();
+        assert!((filled - 0.199).abs() < EPSILON);
+        let (filled, _) = market_maker
+            .place_order("BTC".to_string(), 0.109_9, 100.0, true, false)
+            .await
+            .filled();
+        assert!((filled - 0.109).abs() < EPSILON);
+        // Below the exchange's minimum value, unless it only reduces the position
+        assert_eq!(
+            market_maker
+                .place_order("BTC".to_string(), 0.09, 100.0, true, false)
+                .await,
+            OrderOutcome::NotSent {
+                reason: "min_notional"
+            }
+        );
+        let (filled, _) = market_maker
+            .place_order("BTC".to_string(), 0.09, 100.0, false, true)
+            .await
+            .filled();
+        assert!((filled - 0.09).abs() < EPSILON);
+
+        let unknown: Meta = serde_json::from_str(r#"{"universe":[]}"#).unwrap();
+        assert!(matches!(
+            market_maker.apply_asset_meta(&unknown),
+            Err(MarketMakerError::UnknownAsset(asset)) if asset == "BTC"
+        ));
+    }
+
+    #[tokio::test]
+    async fn round_px_test() {
+        let market_maker = test_market_maker(Input {
+            decimals: 5,
+            ..test_input()
+        })
+        .await;
+        // Five significant figures, integer prices always allowed
+        assert!((market_maker.round_px(1.234_567) - 1.2346).abs() < EPSILON);
+        assert!((market_maker.round_px(123_456.7) - 123_457.0).abs() < EPSILON);
+        assert!((market_maker.round_px(0.012_345_67) - 0.012_35).abs() < EPSILON);
+
+        // Crossing prices round away from the book, prices on a tick stay put
+        assert!((market_maker.round_cross_px(1.234_51, true) - 1.2346).abs() < EPSILON);
+        assert!((market_maker.round_cross_px(1.234_59, false) - 1.2345).abs() < EPSILON);
+        assert!((market_maker.round_cross_px(1.2345, true) - 1.2345).abs() < EPSILON);
+        assert!((market_maker.round_cross_px(1.2345, false) - 1.2345).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn min_edge_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            min_edge_bps: 3.0,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        // Well past the z-score threshold, but too small a deviation to be worth it
+        assert_eq!(
+            market_maker
+                .act_on_signal(z_score(-6.0, 2.0), None, 100.0, 2.0)
+                .await,
+            DecisionOutcome::Blocked("min_edge")
+        );
+        assert!(matches!(
+            market_maker
+                .act_on_signal(z_score(-6.0, 2.0), None, 100.0, 3.5)
+                .await,
+            DecisionOutcome::Traded { is_buy: true, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn max_bps_diff_band_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            max_bps_diff_band: true,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        assert_eq!(
+            market_maker
+                .act_on_signal(z_score(-6.0, 2.0), None, 100.0, 25.0)
+                .await,
+            DecisionOutcome::Blocked("max_bps_diff")
+        );
+        assert!(matches!(
+            market_maker
+                .act_on_signal(z_score(-6.0, 2.0), None, 100.0, 20.0)
+                .await,
+            DecisionOutcome::Traded { is_buy: true, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn equity_size_mode_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            size_mode: SizeMode::FractionOfEquity(0.1),
+            max_order_notional: 200.0,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        // Nothing is traded before the balance is known
+        assert_eq!(
+            market_maker
+                .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 10.0)
+                .await,
+            DecisionOutcome::Blocked("size")
+        );
+
+        // 10% of 10000 is capped at 200 of notional
+        market_maker.withdrawable = Some(10_000.0);
+        assert!((market_maker.base_trade_size() - 2.0).abs() < EPSILON);
+        assert!(Input {
+            size_mode: SizeMode::FractionOfEquity(0.0),
+            ..test_input()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn confirm_ticks_test() {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            confirm_ticks: 2,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+        // A flip to the other side restarts the count
+        for z in [-3.0, 3.0, -3.0] {
+            assert_eq!(
+                market_maker
+                    .act_on_signal(z_score(z, 2.0), None, 100.0, 10.0)
+                    .await,
+                DecisionOutcome::Blocked("confirm_ticks")
+            );
+        }
+        assert!(matches!(
+            market_maker
+                .act_on_signal(z_score(-3.0, 2.0), None, 100.0, 10.0)
+                .await,
+            DecisionOutcome::Traded { is_buy: true, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn outlier_rejection_test() {
+        let mut market_maker = test_market_maker(Input {
+            window_size: 10,
+            outlier_k: Some(10.0),
+            outlier_confirm_ticks: 3,
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..10 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        let ticks = market_maker.ticks;
+
+        // A garbled mid is kept out of the window
+        market_maker.latest_mid_price = 150.0;
+        market_maker.on_price_update().await;
+        assert_eq!(market_maker.ticks, ticks);
+        assert!(market_maker
+            .diffs
+            .values()
+            .iter()
+            .all(|diff| diff.abs() < 0.01));
+        alternating_update(&mut market_maker, 10).await;
+        let ticks = market_maker.ticks;
+
+        // A shift that persists is let in
+        for _ in 0..3 {
+            market_maker.latest_mid_price = 101.0;
+            market_maker.on_price_update().await;
+        }
+        assert_eq!(market_maker.ticks, ticks + 1);
+        assert!((market_maker.diffs.values().back().unwrap() - 0.01).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn flatten_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        assert!(market_maker.flatten().await);
+
+        assert!(market_maker.execute_immediate_trade(false, 0.003).await);
+        assert!(market_maker.flatten().await);
+        assert!(market_maker.position().abs() < EPSILON);
+
+        // Every order rejected, nothing is ever filled
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(3, 1.0)),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        market_maker.inventory.reset(0.002, 100_000.0);
+        assert!(!market_maker.flatten().await);
+        assert_eq!(
+            market_maker.reject_counts()[&RejectCategory::RateLimit],
+            FLATTEN_ATTEMPTS as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn stop_loss_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            stop_loss_bps: Some(50.0),
+            ..test_input()
+        })
+        .await;
+        market_maker.inventory.reset(0.002, 100_000.0);
+        market_maker.latest_mid_price = 99_600.0;
+        assert!(!market_maker.check_position_exit().await);
+
+        market_maker.latest_mid_price = 99_400.0;
+        assert!(market_maker.check_position_exit().await);
+        assert!(market_maker.position().abs() < EPSILON);
+        assert!(market_maker.realized_pnl() < 0.0);
+    }
+
+    #[tokio::test]
+    async fn take_profit_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            take_profit_bps: Some(50.0),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100_000.0);
+        market_maker.inventory.reset(-0.002, 100_000.0);
+        assert!(!market_maker.check_position_exit().await);
+
+        // The reverted spread is closed at a profit from the price update
+        market_maker.on_mid(99_000.0).await;
+        assert!(market_maker.position().abs() < EPSILON);
+        assert!(market_maker.realized_pnl() > 0.0);
+    }
+
+    #[tokio::test]
+    async fn min_hold_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            stop_loss_bps: Some(50.0),
+            min_hold: Duration::from_hours(1),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        market_maker
+            .apply_fill(&fills::fill("B", 0.002, 1, 1))
+            .await;
+
+        // Freshly opened, so the loss doesn't stop it out yet
+        market_maker.latest_mid_price = 99.0;
+        assert!(!market_maker.check_position_exit().await);
+        assert!((market_maker.position() - 0.002).abs() < EPSILON);
+
+        market_maker.opened_at = Instant::now().checked_sub(Duration::from_hours(2));
+        assert!(market_maker.check_position_exit().await);
+        assert!(market_maker.position().abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn max_hold_time_test() {
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            max_hold_time: Some(Duration::from_hours(1)),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        market_maker.latest_mid_price = 100.0;
+        market_maker
+            .apply_fill(&fills::fill("B", 0.002, 1, 1))
+            .await;
+        assert!(!market_maker.check_position_exit().await);
+
+        // Adding to the position restarts the clock
+        market_maker.opened_at = Instant::now().checked_sub(Duration::from_mins(59));
+        market_maker
+            .apply_fill(&fills::fill("B", 0.001, 2, 2))
+            .await;
+        assert!(market_maker.opened_at.unwrap().elapsed() < Duration::from_mins(1));
+        // Reducing it doesn't
+        market_maker.opened_at = Instant::now().checked_sub(Duration::from_mins(59));
+        market_maker
+            .apply_fill(&fills::fill("A", 0.001, 3, 3))
+            .await;
+        assert!(!market_maker.check_position_exit().await);
+
+        // Held past the limit, flattened at no loss or profit
+        market_maker.opened_at = Instant::now().checked_sub(Duration::from_hours(2));
+        assert!(market_maker.check_position_exit().await);
+        assert!(market_maker.position().abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn restore_state_test() {
+        let path = std::env::temp_dir().join(format!("mm_state_{}.json", std::process::id()));
+        let input = || Input {
+            state_path: Some(path.clone()),
+            ..test_input()
+        };
+        let mut market_maker = test_market_maker(input()).await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..100 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        market_maker.inventory.reset(0.002, 100.0);
+        market_maker.realized_pnl = 1.5;
+        market_maker.save_state();
+
+        // A restart resumes warm, with the position and profit
+        let mut restarted = test_market_maker(input()).await;
+        restarted.restore_state();
+        std::fs::remove_file(&path).unwrap();
+        assert!(restarted.is_warm());
+        assert_eq!(restarted.diffs.values(), market_maker.diffs.values());
+        assert!((restarted.position() - 0.002).abs() < EPSILON);
+        assert!((restarted.realized_pnl() - 1.5).abs() < EPSILON);
+
+        // Without the file it starts fresh
+        let mut fresh = test_market_maker(input()).await;
+        fresh.restore_state();
+        assert!(!fresh.is_warm());
+    }
+
+    #[tokio::test]
+    async fn inventory_skew_test() {
+        let mut market_maker = test_market_maker(Input {
+            inventory_skew: 0.5,
+            ..test_input()
+        })
+        .await;
+        // Long half the 0.004 cap: sells fire from 1.5, buys only past 2.5
+        market_maker.inventory.reset(0.002, 100.0);
+        assert!(!market_maker.trade_intent(z_score(1.6, 2.0)).unwrap().is_buy);
+        assert_eq!(market_maker.trade_intent(z_score(-2.4, 2.0)), None);
+        assert!(
+            market_maker
+                .trade_intent(z_score(-2.6, 2.0))
+                .unwrap()
+                .is_buy
+        );
+
+        market_maker.inventory.reset(0.0, 100.0);
+        assert_eq!(market_maker.trade_intent(z_score(1.6, 2.0)), None);
+    }
+
+    #[tokio::test]
+    async fn scaled_trade_size_test() {
+        let size = |market_maker: &MarketMaker, z| {
+            market_maker.trade_intent(z_score(z, 2.0)).unwrap().size
+        };
+        let market_maker = test_market_maker(test_input()).await;
+        assert!((size(&market_maker, 6.0) - 0.001).abs() < EPSILON);
+
+        let market_maker = test_market_maker(Input {
+            max_size_multiplier: 2.5,
+            ..test_input()
+        })
+        .await;
+        assert!((size(&market_maker, 2.01) - 0.001_005).abs() < EPSILON);
+        assert!((size(&market_maker, -4.0) - 0.002).abs() < EPSILON);
+        assert!((size(&market_maker, 6.0) - 0.0025).abs() < EPSILON);
+
+        // The scaled size is still clamped to the position cap
+        let market_maker = test_market_maker(Input {
+            max_size_multiplier: 10.0,
+            ..test_input()
+        })
+        .await;
+        let headroom = market_maker.position_headroom(true, size(&market_maker, 20.0));
+        assert!((headroom.clamped_size - 0.004).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn cross_price_test() {
+        let mut market_maker = test_market_maker(test_input()).await;
+        market_maker.latest_mid_price = 100_000.0;
+        assert!((market_maker.cross_price(true) - 100_200.0).abs() < EPSILON);
+        assert!((market_maker.cross_price(false) - 99_800.0).abs() < EPSILON);
+
+        // 20 bps of a $2 token is less than a tick, cross by one tick instead
+        let mut market_maker = test_market_maker(Input {
+            decimals: 2,
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 2.0;
+        assert!((market_maker.cross_price(true) - 2.01).abs() < EPSILON);
+        assert!((market_maker.cross_price(false) - 1.99).abs() < EPSILON);
+
+        // A tick off a fractional mid rounds up for a buy and down for a sell, never
+        // back toward the book
+        let mut market_maker = test_market_maker(test_input()).await;
+        market_maker.latest_mid_price = 100.37;
+        assert!((market_maker.cross_price(true) - 102.0).abs() < EPSILON);
+        assert!((market_maker.cross_price(false) - 99.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn book_cross_price_test() {
+        let mut market_maker = test_market_maker(Input {
+            cross_buffer_bps: Some(5.0),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        // Without a book the mid offset is used
+        assert!((market_maker.cross_price(true) - 100_200.0).abs() < EPSILON);
+
+        // A fast market has moved the touch past max_bps_diff from the stale mid
+        market_maker.latest_book = Some(L2BookData {
+            coin: "BTC".to_string(),
+            time: 0,
+            levels: vec![
+                vec![book::level(100_290.0, 1.0)],
+                vec![book::level(100_300.0, 1.0)],
+            ],
+        });
+        // 5 bps through the ask is 100_350.15, rounded up to keep crossing
+        assert!((market_maker.cross_price(true) - 100_351.0).abs() < EPSILON);
+        // A touch on the wrong side of the mid still crosses the mid
+        assert!((market_maker.cross_price(false) - 99_999.0).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn order_rounding_test() {
+        let market_maker = test_market_maker(Input {
+            asset: "SOL".to_string(),
+            decimals: 2,
+            size_decimals: 2,
+            ..test_input()
+        })
+        .await;
+        assert!((market_maker.round_px(142.346) - 142.35).abs() < EPSILON);
+        assert!((market_maker.round_sz(0.019) - 0.01).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn one_sided_book_suppresses_trading_test() {
+        let mut market_maker = test_market_maker(Input {
+            one_sided_book_band_bps: Some(50.0),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100.0;
+
+        // No book yet: AllMids-only mode allows trading
+        assert!(market_maker.book_allows_trading());
+
+        let book = |asks| {
+            Message::L2Book(L2Book {
+                data: L2BookData {
+                    coin: "BTC".to_string(),
+                    time: 0,
+                    levels: vec![vec![book::level(99.9, 1.0)], asks],
+                },
+            })
+        };
+        market_maker.process_message(book(vec![])).await;
+        assert!(!market_maker.book_allows_trading());
+
+        market_maker
+            .process_message(book(vec![book::level(100.1, 1.0)]))
+            .await;
+        assert!(market_maker.book_allows_trading());
+    }
+
+    #[tokio::test]
+    async fn book_mid_source_test() {
+        let mut market_maker = test_market_maker(Input {
+            mid_source: MidSource::Microprice,
+            ..test_input()
+        })
+        .await;
+        assert!(market_maker.needs_book());
+
+        market_maker
+            .process_message(Message::L2Book(L2Book {
+                data: L2BookData {
+                    coin: "BTC".to_string(),
+                    time: 0,
+                    levels: vec![vec![book::level(99.0, 3.0)], vec![book::level(101.0, 1.0)]],
+                },
+            }))
+            .await;
+        assert!((market_maker.latest_mid_price - 100.5).abs() < EPSILON);
+
+        // AllMids no longer moves the mid
+        let mids = std::iter::once(("BTC".to_string(), "90".to_string())).collect();
+        market_maker
+            .process_message(Message::AllMids(AllMids {
+                data: AllMidsData { mids },
+            }))
+            .await;
+        assert!((market_maker.latest_mid_price - 100.5).abs() < EPSILON);
+    }
+
+    #[tokio::test]
+    async fn imbalance_filter_test() {
+        let mut market_maker = test_market_maker(Input {
+            imbalance_threshold: Some(0.5),
+            imbalance_levels: 1,
+            ..test_input()
+        })
+        .await;
+        // No book yet
+        assert!(market_maker.imbalance_allows(false));
+
+        market_maker.latest_book = Some(L2BookData {
+            coin: "BTC".to_string(),
+            time: 0,
+            levels: vec![vec![book::level(99.9, 9.0)], vec![book::level(100.1, 1.0)]],
+        });
+        // Heavily bid: no selling into it, buying is fine
+        assert!(!market_maker.imbalance_allows(false));
+        assert!(market_maker.imbalance_allows(true));
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver(Arc<Mutex<Vec<String>>>);
+
+    impl MarketMakerObserver for RecordingObserver {
+        fn on_order(&mut self, is_buy: bool, size: f64, price: f64) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("order {is_buy} {size} {price}"));
+        }
+
+        fn on_fill(&mut self, is_buy: bool, size: f64, price: f64) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("fill {is_buy} {size} {price}"));
+        }
+
+        fn on_halt(&mut self, reason: &str) {
+            self.0.lock().unwrap().push(format!("halt {reason}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn observer_test() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut market_maker = test_market_maker(Input {
+            paper_trading: true,
+            observer: Some(Box::new(RecordingObserver(events.clone()))),
+            ..test_input()
+        })
+        .await;
+        market_maker.latest_mid_price = 100_000.0;
+        assert!(market_maker.execute_immediate_trade(true, 0.001).await);
+        market_maker.shutdown().await;
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "order true 0.001 100200",
+                "fill true 0.001 100200",
+                "halt shutdown"
+            ]
+        );
+    }
+
+    fn mids_message(mid: f64) -> Message {
+        let mids = std::iter::once(("BTC".to_string(), mid.to_string())).collect();
+        Message::AllMids(AllMids {
+            data: AllMidsData { mids },
+        })
+    }
+
+    /// Bot warmed up on a window of diffs alternating around zero through
+    /// `process_message`, recording its orders and fills into `events`.
+    async fn warmed_up_market_maker(events: &Arc<Mutex<Vec<String>>>) -> MarketMaker {
+        let mut market_maker = test_market_maker(Input {
+            dry_run: Some(FillModel::new(1, 0.0)),
+            observer: Some(Box::new(RecordingObserver(events.clone()))),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..100 {
+            let mid = if i % 2 == 0 { 99.99 } else { 100.01 };
+            market_maker.process_message(mids_message(mid)).await;
+        }
+        assert!(market_maker.is_warm());
+        market_maker
+    }
+
+    #[tokio::test]
+    async fn process_message_no_trade_test() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut market_maker = warmed_up_market_maker(&events).await;
+        // Within the usual noise
+        market_maker.process_message(mids_message(100.01)).await;
+        assert!(events.lock().unwrap().is_empty());
+        assert_eq!(
+            market_maker.decisions().last().unwrap().outcome,
+            DecisionOutcome::NoSignal
+        );
+    }
+
+    #[tokio::test]
+    async fn process_message_trade_test() {
+        for (spike, is_buy, side) in [(99.5, true, "B"), (100.5, false, "A")] {
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let mut market_maker = warmed_up_market_maker(&events).await;
+            market_maker.process_message(mids_message(spike)).await;
+            let orders = events.lock().unwrap().clone();
+            assert_eq!(orders.len(), 1);
+            assert!(orders[0].starts_with(&format!("order {is_buy} 0.001 ")));
+            // Not booked until the fill arrives on UserEvents
+            assert!(market_maker.position().abs() < EPSILON);
+            market_maker
+                .process_message(fill_message(fills::fill(side, 0.001, 1, 1)))
+                .await;
+            let expected = if is_buy { 0.001 } else { -0.001 };
+            assert!((market_maker.position() - expected).abs() < EPSILON);
+        }
+    }
+
+    #[tokio::test]
+    async fn time_window_test() {
+        let mut market_maker = test_market_maker(Input {
+            window_size: 2,
+            window_mode: WindowMode::Time(Duration::from_mins(1)),
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..5 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        // The size doesn't bound a time window, and it isn't warm a minute in yet
+        assert_eq!(market_maker.diffs.len(), 5);
+        assert!(!market_maker.is_warm());
+
+        market_maker.window_started_at = Instant::now().checked_sub(Duration::from_mins(1));
+        assert!(market_maker.is_warm());
+    }
+
+    #[tokio::test]
+    async fn ewma_stat_mode_test() {
+        let mut market_maker = test_market_maker(Input {
+            stat_mode: StatMode::Ewma {
+                alpha: 0.5,
+                min_samples: 3,
+            },
+            ..test_input()
+        })
+        .await;
+        market_maker.binance_price.set(100.0);
+        for i in 0..3 {
+            alternating_update(&mut market_maker, i).await;
+        }
+        // Signals start after three samples instead of a full window of 100
+        assert_eq!(market_maker.decisions().count(), 1);
+
+        assert!(Input {
+            stat_mode: StatMode::Ewma {
+                alpha: 0.0,
+                min_samples: 3,
+            },
+            ..test_input()
+        }
+        .validate()
+        .is_err());
+        assert!(Input {
+            stat_mode: StatMode::Decay { half_life: 0.0 },
+            ..test_input()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn backtest_test() {
+        use std::fmt::Write as _;
+
+        let mut market_maker = test_market_maker(Input {
+            window_size: 10,
+            paper_trading: true,
+            ..test_input()
+        })
+        .await;
+        let mut csv = "time,hl_mid,binance_price\n".to_string();
+        for i in 0..10 {
+            let mid = if i % 2 == 0 { "100" } else { "100.01" };
+            writeln!(csv, "{i},{mid},100").unwrap();
+        }
+        // Hyperliquid spikes rich against Binance
+        csv.push_str("10,110,100\n11,100,100\n");
+
+        let report = market_maker.backtest(Backtest::parse(&csv).unwrap()).await;
+        assert_eq!(report.pnl_curve.len(), 12);
+        assert_eq!(report.trades.len(), 1);
+        assert_eq!(report.trades[0].time, 10);
+        assert!(!report.trades[0].is_buy);
+        assert!((report.trades[0].size - 0.001).abs() < EPSILON);
+        // Short from the spike as it reverts
+        assert!(report.final_pnl() > 0.0);
+
+        let mut live = test_market_maker(test_input()).await;
+        assert!(live
+            .backtest(Backtest::parse(&csv).unwrap())
+            .await
+            .pnl_curve
+            .is_empty());
+    }
+}
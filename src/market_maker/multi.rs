@@ -0,0 +1,321 @@
+use chrono::Utc;
+use ethers::signers::LocalWallet;
+use ethers::types::H160;
+use futures_util::future::select_all;
+use gxhash::{HashMap, HashMapExt};
+use std::time::Instant;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::{error, info};
+
+use super::{
+    reconnect, shutdown, ControlCommand, ControlHandle, Input, MarketMaker, MarketMakerError,
+    SizeMode, SESSION_CHECK_INTERVAL, SUBSCRIBE_ATTEMPTS, SUBSCRIBE_BACKOFF,
+};
+use crate::{BaseUrl, InfoClient, Message, Subscription};
+
+/// Parameters that differ between the assets of a [`MultiAssetMarketMaker`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetConfig {
+    pub asset: String,
+    pub window_size: usize,
+    pub z_threshold: f64,
+    pub trade_size: f64,
+    /// Binance symbol the reference price is taken from, e.g. `btcusdt`.
+    pub binance_symbol: String,
+    /// Sub-account wallet trading the asset, or `None` for the shared input's.
+    pub wallet: Option<LocalWallet>,
+}
+
+impl AssetConfig {
+    /// Overrides the per-asset fields of `input`, keeping the shared ones.
+    #[must_use]
+    pub fn apply(&self, input: Input) -> Input {
+        Input {
+            asset: self.asset.clone(),
+            window_size: self.window_size,
+            z_threshold: self.z_threshold,
+            size_mode: SizeMode::Fixed(self.trade_size),
+            wallet: self.wallet.clone().unwrap_or(input.wallet),
+            ..input
+        }
+    }
+}
+
+/// Trades several assets, of one wallet or split over sub-accounts.
+///
+/// Each asset keeps its own [`MarketMaker`] with its window, position and orders, and
+/// orders from its own wallet. Mids are fanned out from a shared `AllMids`
+/// subscription, and fills from a `UserEvents` subscription per wallet to the assets
+/// traded from it. The exchange allows one `UserEvents` subscription per connection,
+/// so every wallet after the first opens a connection of its own.
+pub struct MultiAssetMarketMaker {
+    info_client: InfoClient,
+    /// Wallets the assets trade from, the first one's fills arriving on `info_client`.
+    users: Vec<H160>,
+    makers: HashMap<String, MarketMaker>,
+    subscription_ids: Vec<u32>,
+    /// Connections of the wallets after the first, with their subscription.
+    user_clients: Vec<(InfoClient, u32)>,
+}
+
+impl MultiAssetMarketMaker {
+    /// # Errors
+    ///
+    /// Returns `Err` if there are no makers, they trade on different APIs, or the info
+    /// client can't be created.
+    pub async fn new(makers: Vec<MarketMaker>) -> Result<Self, MarketMakerError> {
+        let base_url = makers
+            .first()
+            .map_or(BaseUrl::Mainnet, |maker| maker.base_url);
+        let info_client = InfoClient::new(None, Some(base_url))
+            .await
+            .map_err(MarketMakerError::InfoClientInit)?;
+        Self::with_info_client(makers, info_client)
+    }
+
+    #[allow(clippy::result_large_err)]
+    pub(super) fn with_info_client(
+        makers: Vec<MarketMaker>,
+        info_client: InfoClient,
+    ) -> Result<Self, MarketMakerError> {
+        let invalid = |reason: String| MarketMakerError::InvalidInput(reason);
+        let api = makers
+            .first()
+            .ok_or_else(|| invalid("No assets configured".to_string()))?
+            .base_url
+            .get_url();
+        if makers.iter().any(|maker| maker.base_url.get_url() != api) {
+            return Err(invalid("All assets must trade on the same API".to_string()));
+        }
+        let mut users: Vec<H160> = Vec::new();
+        for maker in &makers {
+            if !users.contains(&maker.user_address) {
+                users.push(maker.user_address);
+            }
+        }
+
+        let mut by_asset = HashMap::with_capacity(makers.len());
+        for maker in makers {
+            if by_asset.contains_key(&maker.asset) {
+                return Err(invalid(format!("Asset {} configured twice", maker.asset)));
+            }
+            by_asset.insert(maker.asset.clone(), maker);
+        }
+        Ok(Self {
+            info_client,
+            users,
+            makers: by_asset,
+            subscription_ids: Vec::new(),
+            user_clients: Vec::new(),
+        })
+    }
+
+    #[must_use]
+    pub fn maker(&self, asset: &str) -> Option<&MarketMaker> {
+        self.makers.get(asset)
+    }
+
+    pub fn maker_mut(&mut self, asset: &str) -> Option<&mut MarketMaker> {
+        self.makers.get_mut(asset)
+    }
+
+    /// Stop or resume trading one asset, e.g. around its exchange maintenance, while
+    /// its statistics keep updating. Returns false for an asset that isn't traded.
+    pub fn set_enabled(&mut self, asset: &str, enabled: bool) -> bool {
+        let Some(maker) = self.makers.get_mut(asset) else {
+            return false;
+        };
+        maker.set_paused(!enabled);
+        true
+    }
+
+    /// Whether the asset is trading, `None` if it isn't configured.
+    #[must_use]
+    pub fn is_enabled(&self, asset: &str) -> Option<bool> {
+        self.makers.get(asset).map(|maker| !maker.is_paused())
+    }
+
+    /// Handle to disable and enable one asset from another task while
+    /// [`MultiAssetMarketMaker::start`] runs.
+    #[must_use]
+    pub fn control_handle(&self, asset: &str) -> Option<ControlHandle> {
+        self.makers.get(asset).map(MarketMaker::control_handle)
+    }
+
+    pub async fn start(&mut self) {
+        for maker in self.makers.values_mut() {
+            maker.handle_stale_orders().await;
+            maker.seed_position().await;
+        }
+        let Some(mut receivers) = self.subscribe().await else {
+            return;
+        };
+        info!(
+            "Trading {} assets from {} wallets",
+            self.makers.len(),
+            self.users.len()
+        );
+
+        let mut controls: Vec<(String, UnboundedReceiver<ControlCommand>)> = self
+            .makers
+            .values_mut()
+            .filter_map(|maker| Some((maker.asset.clone(), maker.control_receiver.take()?)))
+            .collect();
+        if controls.len() != self.makers.len() {
+            error!("Market maker is already running");
+            return;
+        }
+        let sessions = self.makers.values().any(|maker| maker.session.is_some());
+        let mut last_session_check = Instant::now();
+        loop {
+            let session_deadline = last_session_check + SESSION_CHECK_INTERVAL;
+            let control = select_all(controls.iter_mut().map(|(asset, control)| {
+                Box::pin(async move { (asset.clone(), control.recv().await) })
+            }));
+            let message =
+                select_all(receivers.iter_mut().map(|(user, receiver)| {
+                    Box::pin(async move { (*user, receiver.recv().await) })
+                }));
+            tokio::select! {
+                ((user, message), _, _) = message => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    self.dispatch(user, message).await;
+                }
+                ((asset, Some(command)), _, _) = control => match command {
+                    ControlCommand::Pause | ControlCommand::Resume => {
+                        self.set_enabled(&asset, command == ControlCommand::Resume);
+                    }
+                    ControlCommand::Flatten | ControlCommand::DumpDecisions => {
+                        if let Some(maker) = self.makers.get_mut(&asset) {
+                            maker.on_control(command).await;
+                        }
+                    }
+                },
+                () = tokio::time::sleep_until(session_deadline.into()), if sessions => {
+                    last_session_check = Instant::now();
+                    let now = Utc::now().time();
+                    for maker in self.makers.values_mut() {
+                        maker.check_session(now).await;
+                    }
+                }
+            }
+        }
+        for (asset, control) in controls {
+            if let Some(maker) = self.makers.get_mut(&asset) {
+                maker.control_receiver = Some(control);
+            }
+        }
+    }
+
+    /// Subscribes the shared feeds and the first wallet's fills on `info_client`, and
+    /// every other wallet's fills on a connection of its own. Returns the receivers
+    /// with the wallet whose fills arrive on each, `None` if a subscription failed.
+    async fn subscribe(&mut self) -> Option<Vec<(H160, UnboundedReceiver<Message>)>> {
+        let (first_user, other_users) = self.users.split_first()?;
+        let (sender, receiver) = unbounded_channel();
+        let mut subscriptions = vec![
+            Subscription::UserEvents { user: *first_user },
+            Subscription::AllMids,
+        ];
+        subscriptions.extend(self.makers.values().filter(|maker| maker.needs_book()).map(
+            |maker| Subscription::L2Book {
+                coin: maker.asset.clone(),
+            },
+        ));
+        for subscription in subscriptions {
+            let subscription_id = reconnect::subscribe_with_retry(
+                &mut self.info_client,
+                &subscription,
+                &sender,
+                SUBSCRIBE_BACKOFF,
+                SUBSCRIBE_ATTEMPTS,
+            )
+            .await?;
+            self.subscription_ids.push(subscription_id);
+        }
+
+        let mut receivers = vec![(*first_user, receiver)];
+        let base_url = self.makers.values().next()?.base_url;
+        for &user in other_users {
+            let mut info_client = match InfoClient::new(None, Some(base_url)).await {
+                Ok(info_client) => info_client,
+                Err(e) => {
+                    error!("Error connecting for the fills of {user:?}: {e}");
+                    return None;
+                }
+            };
+            let (sender, receiver) = unbounded_channel();
+            let subscription_id = reconnect::subscribe_with_retry(
+                &mut info_client,
+                &Subscription::UserEvents { user },
+                &sender,
+                SUBSCRIBE_BACKOFF,
+                SUBSCRIBE_ATTEMPTS,
+            )
+            .await?;
+            self.user_clients.push((info_client, subscription_id));
+            receivers.push((user, receiver));
+        }
+        Some(receivers)
+    }
+
+    /// Routes a book to its asset, fans mids out to every asset and fills of the wallet
+    /// `user` to every asset traded from it, which each pick out their own.
+    pub(super) async fn dispatch(&mut self, user: H160, message: Message) {
+        match &message {
+            Message::L2Book(l2_book) => {
+                if let Some(maker) = self.makers.get_mut(&l2_book.data.coin) {
+                    maker.process_message(message).await;
+                }
+            }
+            Message::AllMids(_) => {
+                for maker in self.makers.values_mut() {
+                    maker.process_message(message.clone()).await;
+                }
+            }
+            Message::User(_) => {
+                for maker in self
+                    .makers
+                    .values_mut()
+                    .filter(|maker| maker.user_address == user)
+                {
+                    maker.process_message(message.clone()).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Unsubscribes the shared feeds, then shuts down every asset.
+    pub async fn shutdown(&mut self) {
+        let timeout = self
+            .makers
+            .values()
+            .map(|maker| maker.shutdown_timeout)
+            .max()
+            .unwrap_or_default();
+        let info_client = &mut self.info_client;
+        let subscription_ids = std::mem::take(&mut self.subscription_ids);
+        let mut user_clients = std::mem::take(&mut self.user_clients);
+        shutdown::ShutdownDeadline::new(timeout)
+            .run("unsubscribe shared feeds", async {
+                for subscription_id in subscription_ids {
+                    if let Err(e) = info_client.unsubscribe(subscription_id).await {
+                        error!("Error unsubscribing {subscription_id}: {e}");
+                    }
+                }
+                for (info_client, subscription_id) in &mut user_clients {
+                    if let Err(e) = info_client.unsubscribe(*subscription_id).await {
+                        error!("Error unsubscribing {subscription_id}: {e}");
+                    }
+                }
+            })
+            .await;
+
+        for maker in self.makers.values_mut() {
+            maker.shutdown().await;
+        }
+    }
+}
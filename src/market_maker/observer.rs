@@ -0,0 +1,27 @@
+use super::TradeIntent;
+
+/// Hooks for monitoring a [`MarketMaker`](super::MarketMaker) from outside the crate.
+///
+/// Every method defaults to doing nothing, so an implementation only overrides the
+/// events it cares about.
+///
+/// Hooks run inline on the event loop and should return quickly, handing anything
+/// slow off to a task of their own.
+pub trait MarketMakerObserver {
+    /// A z-score was computed against the rolling mean and stddev of the diffs. Not
+    /// called when entering on a band, which has no z-score.
+    fn on_signal(&mut self, _z: f64, _mean: f64, _stddev: f64) {}
+
+    /// A signal called for a trade, before the risk and execution checks decide
+    /// whether it is sent.
+    fn on_intent(&mut self, _intent: &TradeIntent) {}
+
+    /// An order is about to be sent.
+    fn on_order(&mut self, _is_buy: bool, _size: f64, _price: f64) {}
+
+    /// A fill was booked.
+    fn on_fill(&mut self, _is_buy: bool, _size: f64, _price: f64) {}
+
+    /// Trading stopped.
+    fn on_halt(&mut self, _reason: &str) {}
+}
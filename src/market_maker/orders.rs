@@ -0,0 +1,193 @@
+use std::collections::VecDeque;
+
+use crate::{ExchangeDataStatus, ExchangeResponseStatus, OrderStatusResponse, EPSILON};
+
+/// What to do on startup with orders left resting by a previous run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleOrderPolicy {
+    /// Cancel every open order on the asset.
+    CancelAll,
+    /// Track the orders as if this run had placed them.
+    Adopt,
+}
+
+/// What became of an order the bot tried to place, so callers can tell a rejection
+/// from an order that is resting or one that never reached the exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderOutcome {
+    /// Filled `size`, possibly less than asked for when an IOC was partly cancelled.
+    Filled { size: f64, oid: u64 },
+    /// Live on the book without an immediate fill, until filled or swept.
+    Resting { oid: u64 },
+    /// Refused by the exchange.
+    Rejected { reason: String },
+    /// The request failed before the exchange answered.
+    TransportError,
+    /// Refused by the bot before sending, e.g. by a size or rate limit.
+    NotSent { reason: &'static str },
+}
+
+impl OrderOutcome {
+    /// Size filled and oid, zero for an order that didn't fill.
+    #[must_use]
+    pub const fn filled(&self) -> (f64, u64) {
+        match *self {
+            Self::Filled { size, oid } => (size, oid),
+            _ => (0.0, 0),
+        }
+    }
+}
+
+/// Outcomes of the most recent orders by client order id, kept up to date by their
+/// fills so looking one up doesn't take a request to the exchange.
+#[derive(Debug, Clone, Default)]
+pub(super) struct CloidLog {
+    outcomes: VecDeque<(String, OrderOutcome)>,
+}
+
+/// Orders remembered by the [`CloidLog`], oldest forgotten first.
+const CLOID_LOG_CAPACITY: usize = 256;
+
+impl CloidLog {
+    pub(super) fn record(&mut self, cloid: &str, outcome: OrderOutcome) {
+        if let Some((_, known)) = self.outcomes.iter_mut().find(|(known, _)| known == cloid) {
+            *known = outcome;
+            return;
+        }
+        if self.outcomes.len() >= CLOID_LOG_CAPACITY {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back((cloid.to_string(), outcome));
+    }
+
+    /// Adds a fill of `size` to what is known of the order.
+    pub(super) fn record_fill(&mut self, cloid: &str, size: f64, oid: u64) {
+        let filled = match self.get(cloid) {
+            Some(OrderOutcome::Filled { size: before, .. }) => before + size,
+            _ => size,
+        };
+        self.record(cloid, OrderOutcome::Filled { size: filled, oid });
+    }
+
+    pub(super) fn get(&self, cloid: &str) -> Option<&OrderOutcome> {
+        self.outcomes
+            .iter()
+            .find(|(known, _)| known == cloid)
+            .map(|(_, outcome)| outcome)
+    }
+}
+
+/// What became of an order according to its status, `None` if the exchange never
+/// received it.
+pub(super) fn status_outcome(status: &OrderStatusResponse) -> Option<OrderOutcome> {
+    let info = status.order.as_ref().filter(|_| status.status == "order")?;
+    let oid = info.order.oid;
+    if info.status == "open" {
+        return Some(OrderOutcome::Resting { oid });
+    }
+    let size = |sz: &str| sz.parse::<f64>().unwrap_or(0.0);
+    let filled = size(&info.order.orig_sz) - size(&info.order.sz);
+    Some(if filled > EPSILON {
+        OrderOutcome::Filled { size: filled, oid }
+    } else {
+        OrderOutcome::Rejected {
+            reason: info.status.clone(),
+        }
+    })
+}
+
+/// Number of cancels in a bulk cancel response that succeeded.
+pub(super) fn cancelled_count(response: &ExchangeResponseStatus) -> usize {
+    let ExchangeResponseStatus::Ok(response) = response else {
+        return 0;
+    };
+    response.data.as_ref().map_or(0, |data| {
+        data.statuses
+            .iter()
+            .filter(|status| matches!(status, ExchangeDataStatus::Success))
+            .count()
+    })
+}
+
+#[cfg(test)]
+pub(super) fn open_order(coin: &str, oid: u64, side: &str, px: &str) -> crate::OpenOrdersResponse {
+    crate::OpenOrdersResponse {
+        coin: coin.to_string(),
+        limit_px: px.to_string(),
+        oid,
+        side: side.to_string(),
+        sz: "0.01".to_string(),
+        timestamp: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloid_log_test() {
+        let mut log = CloidLog::default();
+        log.record("0x1", OrderOutcome::TransportError);
+        log.record_fill("0x1", 0.001, 7);
+        log.record_fill("0x1", 0.002, 7);
+        assert_eq!(
+            log.get("0x1"),
+            Some(&OrderOutcome::Filled {
+                size: 0.003,
+                oid: 7
+            })
+        );
+        assert_eq!(log.get("0x2"), None);
+
+        for i in 0..CLOID_LOG_CAPACITY {
+            log.record(&format!("0x{i:x}ff"), OrderOutcome::TransportError);
+        }
+        assert_eq!(log.get("0x1"), None);
+    }
+
+    #[test]
+    fn status_outcome_test() {
+        let status = |status: &str, sz: &str| -> OrderStatusResponse {
+            serde_json::from_str(&format!(
+                r#"{{"status":"order","order":{{"order":{{"coin":"BTC","side":"B","limitPx":"100","sz":"{sz}","oid":9,"timestamp":1,"origSz":"0.01","cloid":"0x1"}},"status":"{status}","statusTimestamp":2}}}}"#
+            ))
+            .unwrap()
+        };
+        assert_eq!(
+            status_outcome(&status("filled", "0.0")),
+            Some(OrderOutcome::Filled { size: 0.01, oid: 9 })
+        );
+        assert_eq!(
+            status_outcome(&status("open", "0.01")),
+            Some(OrderOutcome::Resting { oid: 9 })
+        );
+        // An IOC that partly filled before the rest was cancelled
+        assert!(matches!(
+            status_outcome(&status("canceled", "0.004")),
+            Some(OrderOutcome::Filled { oid: 9, .. })
+        ));
+        assert_eq!(
+            status_outcome(&status("rejected", "0.01")),
+            Some(OrderOutcome::Rejected {
+                reason: "rejected".to_string()
+            })
+        );
+        let unknown: OrderStatusResponse =
+            serde_json::from_str(r#"{"status":"unknownOid"}"#).unwrap();
+        assert_eq!(status_outcome(&unknown), None);
+    }
+
+    #[test]
+    fn cancelled_count_test() {
+        let response: ExchangeResponseStatus = serde_json::from_str(
+            r#"{"status":"ok","response":{"type":"cancel","data":{"statuses":["success",{"error":"Order was never placed, already canceled, or filled."},"success"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(cancelled_count(&response), 2);
+        assert_eq!(
+            cancelled_count(&ExchangeResponseStatus::Err("rate limited".to_string())),
+            0
+        );
+    }
+}
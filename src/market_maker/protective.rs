@@ -0,0 +1,83 @@
+/// Protective (stop-loss / take-profit) trigger orders resting on the exchange, and the
+/// position they were sized and priced for.
+pub(super) struct ProtectiveOrders {
+    pub(super) entry_px: f64,
+    pub(super) position: f64,
+    pub(super) oids: Vec<u64>,
+}
+
+/// Trigger price `bps` away from `entry_px`: below the entry for a long's stop and a
+/// short's take-profit, above it otherwise.
+pub(super) fn trigger_px(entry_px: f64, is_long: bool, is_stop: bool, bps: f64) -> f64 {
+    let below = is_long == is_stop;
+    let offset = super::bps_to_price(entry_px, bps);
+    if below {
+        entry_px - offset
+    } else {
+        entry_px + offset
+    }
+}
+
+/// Unrealized profit of `position` entered at `entry_px`, in bps of its notional at
+/// `mid`'s move from the entry. Positive when the market moved in the position's
+/// favour.
+pub(super) fn unrealized_bps(position: f64, entry_px: f64, mid: f64) -> f64 {
+    if entry_px <= 0.0 || position.abs() < f64::EPSILON {
+        return 0.0;
+    }
+    position.signum() * super::price_to_bps(entry_px, mid)
+}
+
+/// Returns true if the resting protective orders no longer match the position: it has
+/// changed size, or its average entry has moved by more than `reprice_bps`.
+pub(super) fn needs_reprice(
+    current: Option<&ProtectiveOrders>,
+    position: f64,
+    entry_px: f64,
+    reprice_bps: f64,
+) -> bool {
+    current.map_or_else(
+        || position.abs() > f64::EPSILON,
+        |orders| {
+            (orders.position - position).abs() > f64::EPSILON
+                || super::price_to_bps(orders.entry_px, entry_px).abs() > reprice_bps
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trigger_px_test() {
+        assert!((trigger_px(100.0, true, true, 50.0) - 99.5).abs() < 1e-9);
+        assert!((trigger_px(100.0, true, false, 50.0) - 100.5).abs() < 1e-9);
+        assert!((trigger_px(100.0, false, true, 50.0) - 100.5).abs() < 1e-9);
+        assert!((trigger_px(100.0, false, false, 50.0) - 99.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unrealized_bps_test() {
+        assert!((unrealized_bps(1.0, 100.0, 99.0) + 100.0).abs() < 1e-9);
+        assert!((unrealized_bps(-1.0, 100.0, 99.0) - 100.0).abs() < 1e-9);
+        assert!(unrealized_bps(0.0, 100.0, 99.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn add_shifting_entry_reprices_test() {
+        assert!(!needs_reprice(None, 0.0, 0.0, 10.0));
+        assert!(needs_reprice(None, 1.0, 100.0, 10.0));
+
+        let orders = ProtectiveOrders {
+            entry_px: 100.0,
+            position: 1.0,
+            oids: vec![1, 2],
+        };
+        assert!(!needs_reprice(Some(&orders), 1.0, 100.05, 10.0));
+
+        // Adding 1.0 at 102 moves the average entry to 101
+        assert!(needs_reprice(Some(&orders), 2.0, 101.0, 10.0));
+        assert!(needs_reprice(Some(&orders), 1.0, 101.0, 10.0));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::BookLevel;
+
+/// What the market maker does on each price update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrategyMode {
+    /// Trade the z-score of the Hyperliquid mid against the reference price.
+    #[default]
+    Arbitrage,
+    /// Rest a bid and an ask `half_spread` bps around the mid, sized to
+    /// `target_liquidity`, and requote when the mid moves more than `max_bps_diff`.
+    Quoting,
+}
+
+/// Resting two-sided quotes and the mid they were placed around.
+#[derive(Debug, Default)]
+pub(super) struct TwoSidedQuotes {
+    pub(super) mid: Option<f64>,
+    /// Oid and unfilled size of each resting quote.
+    pub(super) orders: Vec<(u64, f64)>,
+}
+
+impl TwoSidedQuotes {
+    /// Returns true if there are no quotes yet, or the mid has moved more than
+    /// `max_bps_diff` since they were placed.
+    pub(super) fn needs_requote(&self, mid: f64, max_bps_diff: u16) -> bool {
+        self.mid.is_none_or(|quoted_mid| {
+            super::price_to_bps(quoted_mid, mid).abs() > f64::from(max_bps_diff)
+        })
+    }
+
+    /// Reduces a quote by a fill, forgetting it once fully filled.
+    pub(super) fn on_fill(&mut self, oid: u64, sz: f64) {
+        if let Some(index) = self
+            .orders
+            .iter()
+            .position(|(quote_oid, _)| *quote_oid == oid)
+        {
+            self.orders[index].1 -= sz;
+            if self.orders[index].1 <= f64::EPSILON {
+                self.orders.remove(index);
+            }
+        }
+    }
+}
+
+/// Bid and ask around `mid`, `half_spread_bps` either side of a center that is shifted
+/// against the predicted drift: a positive `z` (Hyperliquid rich, expected to fall)
+/// moves the center down by `signal_skew_weight * z` bps.
+pub(super) fn skewed_quotes(
+    mid: f64,
+    half_spread_bps: f64,
+    z: f64,
+    signal_skew_weight: f64,
+) -> (f64, f64) {
+    let center = mid - super::bps_to_price(mid, signal_skew_weight * z);
+    let half_spread = super::bps_to_price(center, half_spread_bps);
+    (center - half_spread, center + half_spread)
+}
+
+/// Caps `size` to `fraction` of the same-side depth resting at `px` or better.
+/// `levels` follows the Hyperliquid layout: bids first, then asks.
+pub(super) fn depth_capped_size(
+    levels: &[Vec<BookLevel>],
+    is_buy: bool,
+    px: f64,
+    size: f64,
+    fraction: f64,
+) -> f64 {
+    let same_side = if is_buy {
+        levels.first()
+    } else {
+        levels.get(1)
+    };
+    let depth: f64 = same_side
+        .into_iter()
+        .flatten()
+        .filter_map(|level| {
+            let level_px = level.px.parse::<f64>().ok()?;
+            let at_or_better = if is_buy {
+                level_px >= px
+            } else {
+                level_px <= px
+            };
+            at_or_better.then(|| level.sz.parse::<f64>().unwrap_or(0.0))
+        })
+        .sum();
+    size.min(depth * fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_maker::book::level;
+
+    #[test]
+    fn depth_capped_size_test() {
+        let levels = vec![
+            vec![level(99.9, 0.5), level(99.8, 2.0)],
+            vec![level(100.1, 0.2), level(100.2, 0.3)],
+        ];
+        // A thin book caps the size at the fraction of depth
+        assert!((depth_capped_size(&levels, true, 99.9, 1.0, 0.5) - 0.25).abs() < 1e-9);
+        assert!((depth_capped_size(&levels, false, 100.2, 1.0, 0.5) - 0.25).abs() < 1e-9);
+        // Deep enough to post the full size
+        assert!((depth_capped_size(&levels, true, 99.8, 1.0, 0.5) - 1.0).abs() < 1e-9);
+        assert!(depth_capped_size(&[], true, 99.9, 1.0, 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn requote_test() {
+        let mut quotes = TwoSidedQuotes::default();
+        assert!(quotes.needs_requote(100.0, 20));
+
+        quotes.mid = Some(100.0);
+        quotes.orders = vec![(1, 0.5), (2, 0.5)];
+        assert!(!quotes.needs_requote(100.1, 20));
+        assert!(quotes.needs_requote(99.7, 20));
+
+        quotes.on_fill(1, 0.25);
+        assert_eq!(quotes.orders, vec![(1, 0.25), (2, 0.5)]);
+        quotes.on_fill(1, 0.25);
+        quotes.on_fill(3, 1.0);
+        assert_eq!(quotes.orders, vec![(2, 0.5)]);
+    }
+
+    #[test]
+    fn skewed_quotes_test() {
+        let (bid, ask) = skewed_quotes(100.0, 5.0, 0.0, 1.0);
+        assert!((bid - 99.95).abs() < 1e-9);
+        assert!((ask - 100.05).abs() < 1e-9);
+
+        // z of 2 with a weight of 1.5 moves the center down 3 bps
+        let (bid, ask) = skewed_quotes(100.0, 5.0, 2.0, 1.5);
+        let center = 99.97;
+        assert!((bid - (center - center * 0.0005)).abs() < 1e-9);
+        assert!((ask - (center + center * 0.0005)).abs() < 1e-9);
+
+        // No weight, no skew
+        let (bid, ask) = skewed_quotes(100.0, 5.0, 2.0, 0.0);
+        assert!((bid - 99.95).abs() < 1e-9);
+        assert!((ask - 100.05).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Instant;
+
+/// Token bucket limiting how fast orders are sent, so a persistent signal can't burst
+/// past the exchange's API rate limits.
+///
+/// Holds up to `burst` tokens and refills at `per_second`. Each order takes one; an
+/// order finding the bucket empty is skipped rather than waited for.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    per_second: f64,
+    burst: f64,
+    tokens: f64,
+    updated_at: Option<Instant>,
+}
+
+impl TokenBucket {
+    /// Starts full.
+    #[must_use]
+    pub const fn new(per_second: f64, burst: f64) -> Self {
+        Self {
+            per_second,
+            burst,
+            tokens: burst,
+            updated_at: None,
+        }
+    }
+
+    /// Takes a token at `now`. Returns false if the bucket is empty.
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Share of the burst in use at `now`, from 0 when full to 1 when empty.
+    #[must_use]
+    pub fn utilization(&self, now: Instant) -> f64 {
+        if self.burst <= 0.0 {
+            return 1.0;
+        }
+        1.0 - self.tokens_at(now) / self.burst
+    }
+
+    fn refill(&mut self, now: Instant) {
+        self.tokens = self.tokens_at(now);
+        self.updated_at = Some(now);
+    }
+
+    fn tokens_at(&self, now: Instant) -> f64 {
+        self.updated_at.map_or(self.tokens, |updated_at| {
+            let elapsed = now.saturating_duration_since(updated_at).as_secs_f64();
+            elapsed
+                .mul_add(self.per_second, self.tokens)
+                .min(self.burst)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn token_bucket_test() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, 3.0);
+        assert!(bucket.utilization(start).abs() < 1e-9);
+
+        assert!((0..3).all(|_| bucket.try_acquire(start)));
+        assert!(!bucket.try_acquire(start));
+        assert!((bucket.utilization(start) - 1.0).abs() < 1e-9);
+
+        // Half a second refills one token
+        let later = start + Duration::from_millis(500);
+        assert!(bucket.try_acquire(later));
+        assert!(!bucket.try_acquire(later));
+
+        // Never refills past the burst
+        let much_later = later + Duration::from_mins(1);
+        assert!(bucket.utilization(much_later).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::{Duration, Instant};
+
+/// Rate limit for the reconciliation that follows a reconnect, so a flapping
+/// connection can't hammer the info endpoint.
+pub(super) struct ReconcileLimiter {
+    min_interval: Duration,
+    last_reconciled_at: Option<Instant>,
+}
+
+impl ReconcileLimiter {
+    pub(super) const fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_reconciled_at: None,
+        }
+    }
+
+    /// Returns true, and starts a new interval, if a reconciliation may run at `now`.
+    pub(super) fn try_acquire(&mut self, now: Instant) -> bool {
+        if self
+            .last_reconciled_at
+            .is_some_and(|last| now.duration_since(last) < self.min_interval)
+        {
+            return false;
+        }
+        self.last_reconciled_at = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconcile_rate_limit_test() {
+        let mut limiter = ReconcileLimiter::new(Duration::from_secs(30));
+        let now = Instant::now();
+        assert!(limiter.try_acquire(now));
+        assert!(!limiter.try_acquire(now + Duration::from_secs(29)));
+        assert!(limiter.try_acquire(now + Duration::from_secs(30)));
+    }
+}
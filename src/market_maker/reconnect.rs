@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{error, warn};
+
+use crate::{InfoClient, Message, Subscription};
+
+/// Global limit on reconnect attempts across all feeds and subscriptions.
+///
+/// Past `max_attempts` within `window`, reconnection and trading pause for `cooldown`,
+/// so a persistent fault can't get the IP or key banned.
+#[derive(Debug)]
+pub struct ReconnectLimiter {
+    max_attempts: usize,
+    window: Duration,
+    cooldown: Duration,
+    attempts: VecDeque<Instant>,
+    cooldown_until: Option<Instant>,
+}
+
+impl ReconnectLimiter {
+    #[must_use]
+    pub fn new(max_attempts: usize, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            max_attempts,
+            window,
+            cooldown,
+            attempts: VecDeque::with_capacity(max_attempts),
+            cooldown_until: None,
+        }
+    }
+
+    /// Register a reconnect attempt at `now`. Returns `Err` with the time the attempt
+    /// may be retried if the rate is exceeded or a cooldown is running.
+    ///
+    /// # Errors
+    ///
+    /// Returns the end of the cooldown when the attempt is not allowed.
+    pub fn try_attempt(&mut self, now: Instant) -> Result<(), Instant> {
+        if let Some(until) = self.cooldown_until {
+            if now < until {
+                return Err(until);
+            }
+            self.cooldown_until = None;
+            self.attempts.clear();
+        }
+
+        while self
+            .attempts
+            .front()
+            .is_some_and(|attempt| now.duration_since(*attempt) >= self.window)
+        {
+            self.attempts.pop_front();
+        }
+        if self.attempts.len() >= self.max_attempts {
+            let until = now + self.cooldown;
+            error!(
+                "{} reconnect attempts within {:?}, pausing reconnection and trading for {:?}",
+                self.attempts.len(),
+                self.window,
+                self.cooldown
+            );
+            self.cooldown_until = Some(until);
+            return Err(until);
+        }
+        self.attempts.push_back(now);
+        Ok(())
+    }
+
+    /// Returns true while a cooldown is running at `now`.
+    #[must_use]
+    pub fn is_cooling_down(&self, now: Instant) -> bool {
+        self.cooldown_until.is_some_and(|until| now < until)
+    }
+}
+
+/// Exponential backoff between reconnect attempts, doubling from `initial` up to `max`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+    attempts: u32,
+}
+
+impl Backoff {
+    #[must_use]
+    pub const fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+            attempts: 0,
+        }
+    }
+
+    /// Delay before the next attempt, doubling the one after it.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        self.attempts += 1;
+        delay
+    }
+
+    /// Start over from the initial delay, e.g. after a connection that held up.
+    pub const fn reset(&mut self) {
+        self.next = self.initial;
+        self.attempts = 0;
+    }
+
+    /// Attempts since the last reset.
+    #[must_use]
+    pub const fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    #[must_use]
+    pub const fn max(&self) -> Duration {
+        self.max
+    }
+}
+
+/// Subscribe, waiting out `backoff` between failed attempts so a briefly unreachable
+/// exchange isn't fatal. Returns the subscription id, or `None` once `attempts`
+/// attempts have failed.
+pub(super) async fn subscribe_with_retry(
+    info_client: &mut InfoClient,
+    subscription: &Subscription,
+    sender: &UnboundedSender<Message>,
+    mut backoff: Backoff,
+    attempts: u32,
+) -> Option<u32> {
+    loop {
+        let error = match info_client
+            .subscribe(subscription.clone(), sender.clone())
+            .await
+        {
+            Ok(subscription_id) => return Some(subscription_id),
+            Err(e) => e,
+        };
+        if backoff.attempts() + 1 >= attempts {
+            error!("Error subscribing to {subscription:?} after {attempts} attempts, giving up: {error:?}");
+            return None;
+        }
+        let delay = backoff.next_delay();
+        warn!(
+            "Error subscribing to {subscription:?}, attempt {} of {attempts}, retrying in {delay:?}: {error:?}",
+            backoff.attempts()
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_test() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        let delays: Vec<_> = (0..7).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(backoff.attempts(), 7);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn subscribe_with_retry_test() {
+        // Nothing listens on localhost, so every attempt fails
+        let mut info_client = InfoClient::new(None, Some(crate::BaseUrl::Localhost))
+            .await
+            .unwrap();
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(20));
+        let start = Instant::now();
+        let subscription_id = subscribe_with_retry(
+            &mut info_client,
+            &Subscription::AllMids,
+            &sender,
+            backoff,
+            3,
+        )
+        .await;
+        assert_eq!(subscription_id, None);
+        // Waited 10ms and 20ms between the three attempts
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn reconnect_cooldown_test() {
+        let mut limiter = ReconnectLimiter::new(2, Duration::from_mins(1), Duration::from_mins(5));
+        let now = Instant::now();
+        assert!(limiter.try_attempt(now).is_ok());
+        assert!(limiter.try_attempt(now + Duration::from_secs(1)).is_ok());
+
+        // Third attempt within the window starts the cooldown
+        let until = now + Duration::from_secs(302);
+        assert_eq!(
+            limiter.try_attempt(now + Duration::from_secs(2)),
+            Err(until)
+        );
+        assert!(limiter.is_cooling_down(now + Duration::from_secs(100)));
+        assert_eq!(
+            limiter.try_attempt(now + Duration::from_secs(100)),
+            Err(until)
+        );
+
+        assert!(limiter.try_attempt(until).is_ok());
+        assert!(!limiter.is_cooling_down(until));
+    }
+
+    #[test]
+    fn attempts_outside_window_expire_test() {
+        let mut limiter = ReconnectLimiter::new(1, Duration::from_secs(10), Duration::from_mins(5));
+        let now = Instant::now();
+        assert!(limiter.try_attempt(now).is_ok());
+        assert!(limiter.try_attempt(now + Duration::from_secs(10)).is_ok());
+    }
+}
@@ -0,0 +1,173 @@
+use tracing::warn;
+
+/// Stable classification of order rejection reasons reported by the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RejectCategory {
+    Margin,
+    PricePrecision,
+    SizePrecision,
+    MinNotional,
+    RateLimit,
+    Auth,
+    /// A post-only order that would have crossed, a no-fill rather than an error.
+    WouldCross,
+    Unknown,
+}
+
+impl RejectCategory {
+    /// Every category, in declaration order so `category as usize` indexes it.
+    pub(super) const ALL: [Self; 8] = [
+        Self::Margin,
+        Self::PricePrecision,
+        Self::SizePrecision,
+        Self::MinNotional,
+        Self::RateLimit,
+        Self::Auth,
+        Self::WouldCross,
+        Self::Unknown,
+    ];
+
+    /// Classify the raw error text returned by the exchange. Unrecognized text is
+    /// logged so new cases can be added here.
+    #[must_use]
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| error.contains(p));
+
+        if matches(&["insufficient margin", "margin requirement"]) {
+            Self::Margin
+        } else if matches(&["minimum value", "min notional", "minimum order"]) {
+            Self::MinNotional
+        } else if matches(&["tick size", "invalid price", "price must be"]) {
+            Self::PricePrecision
+        } else if matches(&["invalid size", "size must be", "sz decimals", "lot size"]) {
+            Self::SizePrecision
+        } else if matches(&["post only order would have immediately matched"]) {
+            Self::WouldCross
+        } else if matches(&["signature", "unauthorized", "not authorized"])
+            || (error.contains("user or api wallet") && error.contains("does not exist"))
+        {
+            // Checked before the rate limit, so an auth failure is never retried
+            Self::Auth
+        } else if matches(&["rate limit", "too many requests"]) {
+            Self::RateLimit
+        } else {
+            warn!("Unclassified order rejection: {error}");
+            Self::Unknown
+        }
+    }
+
+    /// Tag used when reporting this category as a metric.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Margin => "margin",
+            Self::PricePrecision => "price_precision",
+            Self::SizePrecision => "size_precision",
+            Self::MinNotional => "min_notional",
+            Self::RateLimit => "rate_limit",
+            Self::Auth => "auth",
+            Self::WouldCross => "would_cross",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Returns true if a request failed before it could have reached the matching
+/// engine, so resending it can't double an order: the connection was never made, or
+/// the API turned it away for its rate limit.
+///
+/// Timeouts and dropped connections are ambiguous, an IOC order may have filled
+/// without the response arriving, and are never considered safe.
+pub(super) fn never_reached_exchange(error: &crate::Error) -> bool {
+    match error {
+        crate::Error::ClientRequest {
+            status_code: 429, ..
+        } => true,
+        crate::Error::GenericRequest(message) => {
+            let message = message.to_lowercase();
+            message.contains("error trying to connect") || message.contains("dns error")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_test() {
+        assert_eq!(
+            RejectCategory::classify("Insufficient margin to place order."),
+            RejectCategory::Margin
+        );
+        assert_eq!(
+            RejectCategory::classify("Order has invalid price."),
+            RejectCategory::PricePrecision
+        );
+        assert_eq!(
+            RejectCategory::classify("Order has invalid size."),
+            RejectCategory::SizePrecision
+        );
+        assert_eq!(
+            RejectCategory::classify("Order must have minimum value of $10."),
+            RejectCategory::MinNotional
+        );
+        assert_eq!(
+            RejectCategory::classify("Too many requests"),
+            RejectCategory::RateLimit
+        );
+        assert_eq!(
+            RejectCategory::classify("User or API Wallet 0x1234 does not exist."),
+            RejectCategory::Auth
+        );
+        assert_eq!(
+            RejectCategory::classify(
+                "Post only order would have immediately matched, bbo was 100.5@100.6. asset=0"
+            ),
+            RejectCategory::WouldCross
+        );
+        // Digits of an oid or price, or another thing that doesn't exist, are neither
+        // a rate limit nor an auth failure
+        assert_eq!(
+            RejectCategory::classify(
+                "Order 4291 was never placed, already canceled, or does not exist."
+            ),
+            RejectCategory::Unknown
+        );
+        assert_eq!(
+            RejectCategory::classify("Asset does not exist."),
+            RejectCategory::Unknown
+        );
+        assert_eq!(
+            RejectCategory::classify("Invalid signature for user 0x4290, rate limit not reached"),
+            RejectCategory::Auth
+        );
+        assert_eq!(
+            RejectCategory::classify("Something new went wrong"),
+            RejectCategory::Unknown
+        );
+        for (i, category) in RejectCategory::ALL.into_iter().enumerate() {
+            assert_eq!(category as usize, i);
+        }
+    }
+
+    #[test]
+    fn never_reached_exchange_test() {
+        let request = |message: &str| crate::Error::GenericRequest(message.to_string());
+        assert!(never_reached_exchange(&request(
+            "error sending request for url: error trying to connect: Connection refused"
+        )));
+        assert!(!never_reached_exchange(&request(
+            "error sending request for url: operation timed out"
+        )));
+        assert!(!never_reached_exchange(&request(
+            "connection closed before message completed"
+        )));
+        assert!(!never_reached_exchange(&crate::Error::ServerRequest {
+            status_code: 502,
+            error_message: String::new(),
+        }));
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+use crate::{Error, Message};
+
+/// Venue a recorded frame was captured from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplaySource {
+    Binance,
+    Hyperliquid,
+}
+
+/// A single raw WebSocket text frame with its capture timestamp in milliseconds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayFrame {
+    pub time: u64,
+    pub source: ReplaySource,
+    pub frame: String,
+}
+
+/// Event decoded from a recorded frame, in the form the live loops consume it.
+#[derive(Debug)]
+pub(super) enum ReplayEvent {
+    BinancePrice(f64),
+    Hyperliquid(Box<Message>),
+}
+
+/// Recorded WebSocket capture replayed in timestamp order, either paced at a
+/// multiple of real time or as fast as possible.
+#[derive(Debug, Clone)]
+pub struct ReplayFeed {
+    frames: Vec<ReplayFrame>,
+    speed: Option<f64>,
+}
+
+impl ReplayFeed {
+    #[must_use]
+    pub fn new(mut frames: Vec<ReplayFrame>) -> Self {
+        frames.sort_by_key(|frame| frame.time);
+        Self {
+            frames,
+            speed: None,
+        }
+    }
+
+    /// Parse a capture with one JSON encoded [`ReplayFrame`] per line.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::JsonParse`] if any line is not a valid frame.
+    #[allow(clippy::result_large_err)]
+    pub fn parse(capture: &str) -> Result<Self, Error> {
+        let frames = capture
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| Error::JsonParse(e.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(frames))
+    }
+
+    /// Pace frames at `speed` times real time, e.g. 1.0 for the original timing.
+    #[must_use]
+    pub const fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Decode the frames in order onto a single channel, waiting out the recorded gaps
+    /// when paced. Frames that fail to decode are skipped, as the live feeds do.
+    pub(super) async fn run(self, sender: UnboundedSender<ReplayEvent>) {
+        let mut previous_time = self.frames.first().map_or(0, |frame| frame.time);
+
+        for frame in self.frames {
+            if let Some(speed) = self.speed.filter(|speed| *speed > 0.0) {
+                let gap = Duration::from_millis(frame.time - previous_time);
+                tokio::time::sleep(gap.div_f64(speed)).await;
+            }
+            previous_time = frame.time;
+
+            let Some(event) = decode(&frame) else {
+                warn!("Skipping undecodable {:?} replay frame", frame.source);
+                continue;
+            };
+            if sender.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn decode(frame: &ReplayFrame) -> Option<ReplayEvent> {
+    match frame.source {
+        ReplaySource::Binance => {
+            #[derive(Deserialize)]
+            struct Trade {
+                p: String,
+            }
+            let trade = serde_json::from_str::<Trade>(&frame.frame).ok()?;
+            trade
+                .p
+                .parse::<f64>()
+                .ok()
+                .filter(|price| price.is_finite() && *price > 0.0)
+                .map(ReplayEvent::BinancePrice)
+        }
+        ReplaySource::Hyperliquid => serde_json::from_str(&frame.frame)
+            .ok()
+            .map(|message| ReplayEvent::Hyperliquid(Box::new(message))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replay_order_test() {
+        let capture = r#"
+{"time":2,"source":"hyperliquid","frame":"{\"channel\":\"allMids\",\"data\":{\"mids\":{\"BTC\":\"100.5\"}}}"}
+{"time":1,"source":"binance","frame":"{\"e\":\"trade\",\"p\":\"100.0\"}"}
+{"time":3,"source":"binance","frame":"not json"}
+{"time":4,"source":"binance","frame":"{\"e\":\"trade\",\"p\":\"-1\"}"}
+"#;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        ReplayFeed::parse(capture).unwrap().run(sender).await;
+        let mut events = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], ReplayEvent::BinancePrice(px) if (px - 100.0).abs() < 1e-12));
+        assert!(matches!(&events[1], ReplayEvent::Hyperliquid(message)
+            if matches!(**message, Message::AllMids(_))));
+
+        assert!(ReplayFeed::parse("{").is_err());
+    }
+}
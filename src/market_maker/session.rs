@@ -0,0 +1,76 @@
+use chrono::NaiveTime;
+
+use super::signal;
+
+/// Daily UTC trading session. The bot parks at `end`, taking no new entries, and
+/// with a `start` resumes there, wrapping past midnight. Without one it stays parked
+/// until restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TradingSession {
+    start: Option<NaiveTime>,
+    end: NaiveTime,
+    parked: bool,
+}
+
+impl TradingSession {
+    pub(super) const fn new(start: Option<NaiveTime>, end: NaiveTime) -> Self {
+        Self {
+            start,
+            end,
+            parked: false,
+        }
+    }
+
+    pub(super) const fn parked(&self) -> bool {
+        self.parked
+    }
+
+    pub(super) const fn end(&self) -> NaiveTime {
+        self.end
+    }
+
+    /// Moves the session to the UTC time of day `now`, returning whether it is parked
+    /// when that changed.
+    pub(super) fn update(&mut self, now: NaiveTime) -> Option<bool> {
+        let parked = match self.start {
+            Some(start) => !signal::in_window(start, self.end, now),
+            None => self.parked || now >= self.end,
+        };
+        (parked != self.parked).then(|| {
+            self.parked = parked;
+            parked
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn session_test() {
+        let mut session = TradingSession::new(Some(at(8, 0)), at(20, 0));
+        assert_eq!(session.update(at(12, 0)), None);
+        assert_eq!(session.update(at(20, 0)), Some(true));
+        assert_eq!(session.update(at(23, 0)), None);
+        assert_eq!(session.update(at(7, 59)), None);
+        assert_eq!(session.update(at(8, 0)), Some(false));
+
+        // Started after the end, parked right away
+        let mut session = TradingSession::new(Some(at(22, 0)), at(6, 0));
+        assert_eq!(session.update(at(12, 0)), Some(true));
+        assert_eq!(session.update(at(22, 0)), Some(false));
+        assert_eq!(session.update(at(5, 0)), None);
+
+        // No start: parked for good once the end passed, even after midnight
+        let mut session = TradingSession::new(None, at(20, 0));
+        assert_eq!(session.update(at(19, 0)), None);
+        assert_eq!(session.update(at(21, 0)), Some(true));
+        assert_eq!(session.update(at(1, 0)), None);
+        assert!(session.parked());
+    }
+}
@@ -0,0 +1,53 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+use tracing::{error, info};
+
+/// Shared deadline for all steps of a shutdown sequence, so a hung exchange can't
+/// block the process from exiting.
+pub(super) struct ShutdownDeadline {
+    deadline: Instant,
+}
+
+impl ShutdownDeadline {
+    pub(super) fn new(timeout: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + timeout,
+        }
+    }
+
+    /// Runs one shutdown step, abandoning it if the overall deadline passes first.
+    pub(super) async fn run<F: Future>(&self, step: &str, fut: F) -> Option<F::Output> {
+        let Ok(output) = timeout_at(self.deadline, fut).await else {
+            error!("Shutdown: {step} did not confirm before the shutdown timeout");
+            return None;
+        };
+        info!("Shutdown: {step} done");
+        Some(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    #[tokio::test]
+    async fn slow_step_is_abandoned_test() {
+        let deadline = ShutdownDeadline::new(Duration::from_millis(20));
+        assert_eq!(deadline.run("quick step", async { 1 }).await, Some(1));
+
+        let slow_flatten = async {
+            sleep(Duration::from_secs(5)).await;
+        };
+        let started = Instant::now();
+        assert_eq!(deadline.run("flatten", slow_flatten).await, None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // Later steps share the already expired deadline
+        assert_eq!(
+            deadline.run("cancel", sleep(Duration::from_secs(5))).await,
+            None
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use chrono::NaiveTime;
+use std::fmt;
+use std::time::Duration;
+
+/// What an entry is decided on, with the threshold it has to cross in the same unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntrySignal {
+    /// Z-score of the diff against the rolling window.
+    ZScore { z: f64, threshold: f64 },
+    /// Distance of the diff from the rolling mean in bps, see
+    /// [`Input::entry_band_bps`](super::Input::entry_band_bps).
+    Band { bps: f64, threshold_bps: f64 },
+}
+
+impl EntrySignal {
+    /// The signal and its threshold in the signal's unit, positive when the diff is
+    /// above its mean.
+    #[must_use]
+    pub const fn level(self) -> (f64, f64) {
+        match self {
+            Self::ZScore { z, threshold } => (z, threshold),
+            Self::Band { bps, threshold_bps } => (bps, threshold_bps),
+        }
+    }
+
+    /// The z-score, `None` for a band signal.
+    #[must_use]
+    pub const fn z(self) -> Option<f64> {
+        match self {
+            Self::ZScore { z, .. } => Some(z),
+            Self::Band { .. } => None,
+        }
+    }
+
+    /// The same signal held to `threshold` instead, in its unit.
+    #[must_use]
+    pub const fn with_threshold(self, threshold: f64) -> Self {
+        match self {
+            Self::ZScore { z, .. } => Self::ZScore { z, threshold },
+            Self::Band { bps, .. } => Self::Band {
+                bps,
+                threshold_bps: threshold,
+            },
+        }
+    }
+}
+
+impl fmt::Display for EntrySignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::ZScore { z, threshold } => write!(f, "z={z:.2} threshold={threshold:.2}"),
+            Self::Band { bps, threshold_bps } => {
+                write!(f, "{bps:.2} bps from the mean, band {threshold_bps:.2} bps")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) const fn z_score(z: f64, threshold: f64) -> EntrySignal {
+    EntrySignal::ZScore { z, threshold }
+}
+
+/// Requires a signal to be seen on two consecutive reference updates that agree with
+/// each other before it may fire, so a single anomalous reference print can't trade.
+pub(super) struct ReferenceConfirmation {
+    max_divergence_bps: f64,
+    pending: Option<(bool, f64)>,
+}
+
+impl ReferenceConfirmation {
+    pub(super) const fn new(max_divergence_bps: f64) -> Self {
+        Self {
+            max_divergence_bps,
+            pending: None,
+        }
+    }
+
+    /// Returns true if the signal on `is_buy` side is confirmed by `reference`.
+    /// The first reference that produces a signal is only remembered.
+    pub(super) fn confirm(&mut self, is_buy: bool, reference: f64) -> bool {
+        match self.pending {
+            // Same reference print as before: not a new update, keep waiting
+            Some((_, pending_reference))
+                if (pending_reference - reference).abs() < f64::EPSILON =>
+            {
+                false
+            }
+            Some((pending_side, pending_reference))
+                if pending_side == is_buy
+                    && super::price_to_bps(pending_reference, reference).abs()
+                        <= self.max_divergence_bps =>
+            {
+                self.pending = None;
+                true
+            }
+            _ => {
+                self.pending = Some((is_buy, reference));
+                false
+            }
+        }
+    }
+
+    /// Forget any pending signal, e.g. once the signal falls back inside the threshold.
+    pub(super) const fn reset(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// Requires a signal to stay beyond the threshold on the same side for
+/// `confirm_ticks` consecutive updates before it may fire, so a one-tick spike that
+/// reverts right away isn't traded.
+pub(super) struct SignalDebounce {
+    confirm_ticks: usize,
+    side: Option<bool>,
+    streak: usize,
+}
+
+impl SignalDebounce {
+    pub(super) const fn new(confirm_ticks: usize) -> Self {
+        Self {
+            confirm_ticks,
+            side: None,
+            streak: 0,
+        }
+    }
+
+    /// Counts a tick with a signal on `is_buy` side and returns true once the signal
+    /// has persisted for `confirm_ticks`. A flip to the other side restarts the count.
+    pub(super) fn observe(&mut self, is_buy: bool) -> bool {
+        if self.side == Some(is_buy) {
+            self.streak = self.streak.saturating_add(1);
+        } else {
+            self.side = Some(is_buy);
+            self.streak = 1;
+        }
+        self.streak >= self.confirm_ticks
+    }
+
+    /// Restart the count once the signal falls back inside the threshold.
+    pub(super) const fn reset(&mut self) {
+        self.side = None;
+        self.streak = 0;
+    }
+}
+
+/// Z-score threshold override for a UTC time of day range. Ranges with `end` before
+/// `start` wrap past midnight.
+#[derive(Debug, Clone, Copy)]
+pub struct ZThresholdWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub z_threshold: f64,
+}
+
+impl ZThresholdWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        in_window(self.start, self.end, time)
+    }
+}
+
+/// Whether `time` is in `[start, end)`, wrapping past midnight when `end` is earlier.
+pub(super) fn in_window(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+/// Threshold of the first window in `schedule` containing `time`, else `base`.
+pub(super) fn z_threshold_at(schedule: &[ZThresholdWindow], time: NaiveTime, base: f64) -> f64 {
+    schedule
+        .iter()
+        .find(|window| window.contains(time))
+        .map_or(base, |window| window.z_threshold)
+}
+
+/// Scales the z-score threshold with how noisy the diff series has been recently.
+///
+/// The threshold becomes `base_z * clamp(recent_stddev / window_stddev, min_multiplier,
+/// max_multiplier)`, where `recent_stddev` is the standard deviation of the last
+/// `recent_window` diffs and `window_stddev` the one the z-score is computed with. A
+/// burst of volatility demands a larger z before acting, a quiet spell a smaller one.
+#[derive(Debug, Clone, Copy)]
+pub struct VolatilityScaling {
+    pub recent_window: usize,
+    pub min_multiplier: f64,
+    pub max_multiplier: f64,
+}
+
+impl VolatilityScaling {
+    /// Factor to multiply the base threshold with. One while either deviation is
+    /// too small to compare.
+    pub(super) fn multiplier(&self, recent_stddev: f64, window_stddev: f64) -> f64 {
+        if recent_stddev < f64::EPSILON || window_stddev < f64::EPSILON {
+            return 1.0;
+        }
+        (recent_stddev / window_stddev).clamp(self.min_multiplier, self.max_multiplier)
+    }
+}
+
+/// Threshold multiplier `since_loss` after a losing trade: `penalty` right after it,
+/// decaying back to one with a half-life of `half_life`.
+pub(super) fn loss_penalty(penalty: f64, half_life: Duration, since_loss: Duration) -> f64 {
+    if half_life.is_zero() {
+        return 1.0;
+    }
+    let halvings = since_loss.as_secs_f64() / half_life.as_secs_f64();
+    (penalty - 1.0).mul_add(0.5f64.powf(halvings), 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_threshold_schedule_test() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let schedule = [
+            ZThresholdWindow {
+                start: at(22, 0),
+                end: at(6, 0),
+                z_threshold: 3.0,
+            },
+            ZThresholdWindow {
+                start: at(13, 30),
+                end: at(20, 0),
+                z_threshold: 1.5,
+            },
+        ];
+        assert!((z_threshold_at(&schedule, at(23, 0), 2.0) - 3.0).abs() < 1e-12);
+        assert!((z_threshold_at(&schedule, at(5, 59), 2.0) - 3.0).abs() < 1e-12);
+        assert!((z_threshold_at(&schedule, at(6, 0), 2.0) - 2.0).abs() < 1e-12);
+        assert!((z_threshold_at(&schedule, at(13, 29), 2.0) - 2.0).abs() < 1e-12);
+        assert!((z_threshold_at(&schedule, at(13, 30), 2.0) - 1.5).abs() < 1e-12);
+        assert!((z_threshold_at(&[], at(13, 30), 2.0) - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn volatility_scaling_test() {
+        let scaling = VolatilityScaling {
+            recent_window: 10,
+            min_multiplier: 0.5,
+            max_multiplier: 2.0,
+        };
+        assert!((scaling.multiplier(1.5, 1.0) - 1.5).abs() < 1e-12);
+        assert!((scaling.multiplier(5.0, 1.0) - 2.0).abs() < 1e-12);
+        assert!((scaling.multiplier(0.1, 1.0) - 0.5).abs() < 1e-12);
+        assert!((scaling.multiplier(0.0, 1.0) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn loss_penalty_test() {
+        let half_life = Duration::from_mins(5);
+        assert!((loss_penalty(2.0, half_life, Duration::ZERO) - 2.0).abs() < 1e-12);
+        assert!((loss_penalty(2.0, half_life, half_life) - 1.5).abs() < 1e-12);
+        assert!((loss_penalty(2.0, half_life, Duration::from_hours(10)) - 1.0).abs() < 1e-9);
+        assert!((loss_penalty(2.0, Duration::ZERO, Duration::ZERO) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reference_confirmation_test() {
+        let mut confirmation = ReferenceConfirmation::new(5.0);
+
+        // An outlier print gives a signal, the next normal print doesn't agree with it
+        assert!(!confirmation.confirm(true, 105.0));
+        confirmation.reset();
+        assert!(!confirmation.confirm(false, 100.0));
+
+        // Two consistent updates with the same signal confirm it
+        confirmation.reset();
+        assert!(!confirmation.confirm(true, 100.0));
+        assert!(!confirmation.confirm(true, 100.0));
+        assert!(confirmation.confirm(true, 100.02));
+
+        // Consecutive updates that diverge too much don't
+        assert!(!confirmation.confirm(true, 100.0));
+        assert!(!confirmation.confirm(true, 101.0));
+    }
+
+    #[test]
+    fn signal_debounce_test() {
+        let mut debounce = SignalDebounce::new(3);
+        assert!(!debounce.observe(true));
+        assert!(!debounce.observe(true));
+        assert!(debounce.observe(true));
+        assert!(debounce.observe(true));
+
+        // A flip restarts the count, as does the signal going away
+        assert!(!debounce.observe(false));
+        assert!(!debounce.observe(false));
+        debounce.reset();
+        assert!(!debounce.observe(false));
+
+        // One tick is the undebounced behaviour
+        assert!(SignalDebounce::new(1).observe(true));
+    }
+}
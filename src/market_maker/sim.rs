@@ -0,0 +1,158 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+use crate::{
+    Error, ExchangeDataStatus, ExchangeDataStatuses, ExchangeResponse, ExchangeResponseStatus,
+    FilledOrder, RestingOrder,
+};
+
+/// Dry-run stand-in for the exchange.
+///
+/// IOC orders fill in full at their limit price, except for a `rate_limit_frequency`
+/// share that is rejected like an exchange rate limit, so backoff paths can be
+/// exercised without a live venue. Seeded so a run is reproducible.
+///
+/// Responses queued with [`FillModel::with_responses`] are given first, in order,
+/// to script exactly what the exchange answers in tests.
+#[derive(Debug)]
+pub struct FillModel {
+    rate_limit_frequency: f64,
+    rng: StdRng,
+    next_oid: u64,
+    scripted: VecDeque<Result<ExchangeResponseStatus, Error>>,
+}
+
+impl FillModel {
+    #[must_use]
+    pub fn new(seed: u64, rate_limit_frequency: f64) -> Self {
+        Self {
+            rate_limit_frequency: rate_limit_frequency.clamp(0.0, 1.0),
+            rng: StdRng::seed_from_u64(seed),
+            next_oid: 1,
+            scripted: VecDeque::new(),
+        }
+    }
+
+    /// Answer the next orders with `responses` before simulating any.
+    #[must_use]
+    pub fn with_responses(
+        mut self,
+        responses: impl IntoIterator<Item = Result<ExchangeResponseStatus, Error>>,
+    ) -> Self {
+        self.scripted.extend(responses);
+        self
+    }
+
+    /// Response the exchange would have given to an IOC order of `sz` at `limit_px`.
+    #[allow(clippy::result_large_err)]
+    pub(super) fn submit(
+        &mut self,
+        sz: f64,
+        limit_px: f64,
+    ) -> Result<ExchangeResponseStatus, Error> {
+        self.respond(|oid| {
+            ExchangeDataStatus::Filled(FilledOrder {
+                total_sz: sz.to_string(),
+                avg_px: limit_px.to_string(),
+                oid,
+            })
+        })
+    }
+
+    /// Response to a post-only order, which rests without filling.
+    #[allow(clippy::result_large_err)]
+    pub(super) fn post(&mut self) -> Result<ExchangeResponseStatus, Error> {
+        self.respond(|oid| ExchangeDataStatus::Resting(RestingOrder { oid }))
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn respond(
+        &mut self,
+        status: impl FnOnce(u64) -> ExchangeDataStatus,
+    ) -> Result<ExchangeResponseStatus, Error> {
+        if let Some(response) = self.scripted.pop_front() {
+            return response;
+        }
+        if self.rng.gen_bool(self.rate_limit_frequency) {
+            return Ok(ExchangeResponseStatus::Err(
+                "Too many requests (simulated)".to_string(),
+            ));
+        }
+
+        let oid = self.next_oid;
+        self.next_oid += 1;
+        Ok(response(status(oid)))
+    }
+}
+
+/// `px` moved `slippage_bps` against a trade, up for a buy and down for a sell.
+pub(super) fn slipped_px(px: f64, is_buy: bool, slippage_bps: f64) -> f64 {
+    let slippage = super::bps_to_price(px, slippage_bps);
+    if is_buy {
+        px + slippage
+    } else {
+        px - slippage
+    }
+}
+
+/// Successful order response carrying `status`.
+pub(super) fn response(status: ExchangeDataStatus) -> ExchangeResponseStatus {
+    ExchangeResponseStatus::Ok(ExchangeResponse {
+        response_type: "order".to_string(),
+        data: Some(ExchangeDataStatuses {
+            statuses: vec![status],
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_frequency_test() {
+        let rejected = |model: &mut FillModel| {
+            (0..10_000)
+                .filter(|_| matches!(model.submit(1.0, 100.0), Ok(ExchangeResponseStatus::Err(_))))
+                .count()
+        };
+        let mut model = FillModel::new(7, 0.2);
+        let count = rejected(&mut model);
+        assert!((1_800..2_200).contains(&count), "{count} rejections");
+
+        // Same seed, same sequence
+        assert_eq!(rejected(&mut FillModel::new(7, 0.2)), count);
+        assert_eq!(rejected(&mut FillModel::new(7, 0.0)), 0);
+    }
+
+    #[test]
+    fn slipped_px_test() {
+        assert!((slipped_px(100.0, true, 25.0) - 100.25).abs() < 1e-9);
+        assert!((slipped_px(100.0, false, 25.0) - 99.75).abs() < 1e-9);
+        assert!((slipped_px(100.0, false, 0.0) - 100.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn scripted_responses_test() {
+        let mut model = FillModel::new(1, 0.0).with_responses([
+            Ok(response(ExchangeDataStatus::Resting(RestingOrder {
+                oid: 9,
+            }))),
+            Err(Error::GenericRequest("connection reset".to_string())),
+        ]);
+        assert!(matches!(
+            model.submit(1.0, 100.0),
+            Ok(ExchangeResponseStatus::Ok(_))
+        ));
+        assert!(matches!(model.post(), Err(Error::GenericRequest(_))));
+        // Simulated again once the script is used up, with oids from 1
+        let Ok(ExchangeResponseStatus::Ok(filled)) = model.submit(1.0, 100.0) else {
+            panic!("expected a simulated fill");
+        };
+        assert!(matches!(
+            filled.data.unwrap().statuses[..],
+            [ExchangeDataStatus::Filled(FilledOrder { oid: 1, .. })]
+        ));
+    }
+}
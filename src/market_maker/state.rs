@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use super::stats::Ewma;
+
+/// Everything about a running bot in one JSON-able view, see
+/// [`MarketMaker::snapshot`](crate::MarketMaker::snapshot).
+///
+/// Times are ages in seconds at the snapshot, `None` for what hasn't happened yet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarketMakerState {
+    pub asset: String,
+    pub position: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    /// Z-score, mean and stddev of the diff at the last signal evaluation.
+    pub z_score: Option<f64>,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    /// Diffs in the rolling window, out of `window_size` once warm.
+    pub window_len: usize,
+    pub window_size: usize,
+    pub last_trade_age_seconds: Option<f64>,
+    pub paused: bool,
+    /// Whether the order circuit breaker is holding orders back.
+    pub halted: bool,
+    pub mid_age_seconds: Option<f64>,
+    pub reference_price_age_seconds: Option<f64>,
+}
+
+/// What a restart needs to resume trading without rewarming: the rolling window,
+/// the EWMA estimates, and the position and profit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct SavedState {
+    pub(super) asset: String,
+    /// Unix time in milliseconds the state was saved at.
+    pub(super) saved_at: i64,
+    pub(super) ticks: usize,
+    /// Diffs of the rolling window, oldest first.
+    pub(super) diffs: Vec<f64>,
+    pub(super) ewma: Option<Ewma>,
+    pub(super) position: f64,
+    pub(super) avg_entry_px: f64,
+    pub(super) realized_pnl: f64,
+}
+
+impl SavedState {
+    /// Write the state as JSON, through a temporary file so a crash mid-write can't
+    /// leave a truncated one behind.
+    pub(super) fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// State saved for `asset` within `max_age` of `now`, in Unix milliseconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of why the file can't be used: it can't be read or
+    /// parsed, is for another asset, or is too old.
+    pub(super) fn load(
+        path: &Path,
+        asset: &str,
+        max_age: Duration,
+        now: i64,
+    ) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let state: Self = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        if state.asset != asset {
+            return Err(format!("it is for {}, not {asset}", state.asset));
+        }
+        let age = Duration::from_millis(u64::try_from(now - state.saved_at).unwrap_or(u64::MAX));
+        if age > max_age {
+            return Err(format!("it is {age:?} old, more than {max_age:?}"));
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_load_test() {
+        let path = std::env::temp_dir().join(format!("state_{}.json", std::process::id()));
+        let max_age = Duration::from_mins(10);
+        let state = SavedState {
+            asset: "BTC".to_string(),
+            saved_at: 1_000_000,
+            ticks: 3,
+            diffs: vec![0.001, -0.002, 0.0005],
+            ewma: None,
+            position: 0.002,
+            avg_entry_px: 100.5,
+            realized_pnl: 1.25,
+        };
+        state.save(&path).unwrap();
+
+        assert_eq!(
+            SavedState::load(&path, "BTC", max_age, 1_060_000),
+            Ok(state)
+        );
+        // Too old, or for another asset, and it starts fresh
+        assert!(SavedState::load(&path, "BTC", max_age, 1_000_000 + 601_000).is_err());
+        assert!(SavedState::load(&path, "ETH", max_age, 1_060_000).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(SavedState::load(&path, "BTC", max_age, 1_060_000).is_err());
+    }
+}
@@ -0,0 +1,654 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How the rolling window of diffs is bounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    /// The last `window_size` diffs, however long they took to arrive.
+    #[default]
+    Count,
+    /// Diffs from this long ago until now, however many arrived.
+    Time(Duration),
+}
+
+/// Prior estimate of the diff distribution, used to arm the strategy before the live
+/// window has filled. Values are in basis points.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowPrior {
+    pub mean_bps: f64,
+    pub stddev_bps: f64,
+    /// Weight of the prior relative to a full live window. It decays linearly to zero
+    /// as the window fills.
+    pub weight: f64,
+}
+
+impl WindowPrior {
+    /// Blend the prior with live statistics computed over `samples` of a window of
+    /// `window_size`. Returns `(mean, stddev)` as fractions, like the live values.
+    #[allow(clippy::cast_precision_loss)]
+    #[must_use]
+    pub fn blend(
+        &self,
+        live_mean: f64,
+        live_stddev: f64,
+        samples: usize,
+        window_size: usize,
+    ) -> (f64, f64) {
+        let fill = (samples as f64 / window_size as f64).min(1.0);
+        // The sample stddev is undefined for fewer than two samples
+        let live_weight = if samples < 2 { 0.0 } else { fill };
+        let prior_weight = self.weight * (1.0 - fill);
+        let total = live_weight + prior_weight;
+        if total <= 0.0 {
+            return (live_mean, live_stddev);
+        }
+
+        let prior_mean = super::bps_to_fraction(self.mean_bps);
+        let prior_variance = super::bps_to_fraction(self.stddev_bps).powi(2);
+        let mean = (prior_weight * prior_mean + live_weight * live_mean) / total;
+        let variance = (prior_weight * prior_variance + live_weight * live_stddev.powi(2)) / total;
+        (mean, variance.sqrt())
+    }
+}
+
+/// Volume weighted average price over the most recent `window` trades, used as a
+/// reference that a single small print can't move much.
+#[derive(Debug, Clone)]
+pub struct TradeVwap {
+    window: usize,
+    trades: VecDeque<(f64, f64)>,
+    notional: f64,
+    size: f64,
+}
+
+impl TradeVwap {
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            trades: VecDeque::with_capacity(window),
+            notional: 0.0,
+            size: 0.0,
+        }
+    }
+
+    /// Adds a trade and returns the updated VWAP, if any size is in the window.
+    pub fn push(&mut self, px: f64, sz: f64) -> Option<f64> {
+        if self.window > 0 {
+            if self.trades.len() == self.window {
+                if let Some((old_px, old_sz)) = self.trades.pop_front() {
+                    self.notional -= old_px * old_sz;
+                    self.size -= old_sz;
+                }
+            }
+            self.trades.push_back((px, sz));
+            self.notional += px * sz;
+            self.size += sz;
+        }
+        self.vwap()
+    }
+
+    #[must_use]
+    pub fn vwap(&self) -> Option<f64> {
+        (self.size > f64::EPSILON).then(|| self.notional / self.size)
+    }
+}
+
+/// Sample mean and standard deviation of `values`. The stddev is zero for fewer than
+/// two values.
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn mean_stddev(values: &VecDeque<f64>) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+/// How the mean and stddev of the diffs are estimated.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StatMode {
+    /// Equal weights over the rolling window.
+    #[default]
+    Window,
+    /// Exponentially weighted, each diff weighted `alpha` against the history. Trades
+    /// once `min_samples` diffs were seen rather than waiting for a full window.
+    Ewma { alpha: f64, min_samples: usize },
+    /// Weighted over the rolling window, each diff's weight halving every
+    /// `half_life` ticks of age. Keeps the window's bounded memory while emphasizing
+    /// recent diffs.
+    Decay { half_life: f64 },
+}
+
+/// Exponentially weighted mean and variance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct Ewma {
+    alpha: f64,
+    min_samples: usize,
+    mean: f64,
+    variance: f64,
+    samples: usize,
+}
+
+impl Ewma {
+    pub(super) const fn new(alpha: f64, min_samples: usize) -> Self {
+        Self {
+            alpha,
+            min_samples,
+            mean: 0.0,
+            variance: 0.0,
+            samples: 0,
+        }
+    }
+
+    pub(super) fn push(&mut self, value: f64) {
+        if self.samples == 0 {
+            self.mean = value;
+        } else {
+            let delta = value - self.mean;
+            self.mean += self.alpha * delta;
+            self.variance = (1.0 - self.alpha) * (self.alpha * delta).mul_add(delta, self.variance);
+        }
+        self.samples = self.samples.saturating_add(1);
+    }
+
+    pub(super) const fn is_warm(&self) -> bool {
+        self.samples >= self.min_samples
+    }
+
+    pub(super) const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    pub(super) fn stddev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+}
+
+/// Weighted mean and standard deviation of `values`, oldest first, with weights
+/// halving every `half_life` values of age. The stddev is zero for fewer than two
+/// values.
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn decayed_mean_stddev(values: &VecDeque<f64>, half_life: f64) -> (f64, f64) {
+    let weights: Vec<f64> = (0..values.len())
+        .rev()
+        .map(|age| 0.5f64.powf(age as f64 / half_life))
+        .collect();
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().zip(&weights).map(|(x, w)| x * w).sum::<f64>() / total;
+    // Reliability weights correction, which reduces to n - 1 for equal weights
+    let squares: f64 = weights.iter().map(|w| w * w).sum();
+    let denominator = total - squares / total;
+    if values.len() < 2 || denominator <= 0.0 {
+        return (mean, 0.0);
+    }
+    let variance = values
+        .iter()
+        .zip(&weights)
+        .map(|(x, w)| w * (x - mean).powi(2))
+        .sum::<f64>()
+        / denominator;
+    (mean, variance.sqrt())
+}
+
+/// Rolling window of values with Welford's running mean and variance, so both are
+/// O(1) per update instead of a pass over the window.
+#[derive(Debug, Clone, Default)]
+pub(super) struct RollingWindow {
+    values: VecDeque<f64>,
+    // When each value was pushed
+    times: VecDeque<Instant>,
+    mean: f64,
+    m2: f64,
+    // Updates since the accumulators were last recomputed from scratch
+    drift_updates: usize,
+}
+
+impl RollingWindow {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            times: VecDeque::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn push_back(&mut self, value: f64) {
+        self.push_back_at(value, Instant::now());
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub(super) fn push_back_at(&mut self, value: f64, at: Instant) {
+        self.values.push_back(value);
+        self.times.push_back(at);
+        let delta = value - self.mean;
+        self.mean += delta / self.values.len() as f64;
+        self.m2 += delta * (value - self.mean);
+        self.after_update();
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub(super) fn pop_front(&mut self) -> Option<f64> {
+        let value = self.values.pop_front()?;
+        self.times.pop_front();
+        if self.values.is_empty() {
+            self.mean = 0.0;
+            self.m2 = 0.0;
+        } else {
+            let delta = value - self.mean;
+            self.mean -= delta / self.values.len() as f64;
+            self.m2 = delta.mul_add(-(value - self.mean), self.m2).max(0.0);
+        }
+        self.after_update();
+        Some(value)
+    }
+
+    /// Recomputes the accumulators once per window length of updates, which keeps the
+    /// removal rounding error from building up at amortized O(1) cost.
+    fn after_update(&mut self) {
+        self.drift_updates += 1;
+        if self.drift_updates > self.values.len() {
+            let (mean, _) = mean_stddev(&self.values);
+            self.mean = mean;
+            self.m2 = self.values.iter().map(|x| (x - mean).powi(2)).sum();
+            self.drift_updates = 0;
+        }
+    }
+
+    /// Drops the values pushed before `cutoff`.
+    pub(super) fn evict_before(&mut self, cutoff: Instant) {
+        while self.times.front().is_some_and(|at| *at < cutoff) {
+            self.pop_front();
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub(super) const fn values(&self) -> &VecDeque<f64> {
+        &self.values
+    }
+
+    pub(super) const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Sample standard deviation, zero for fewer than two values.
+    #[allow(clippy::cast_precision_loss)]
+    pub(super) fn stddev(&self) -> f64 {
+        if self.values.len() < 2 {
+            return 0.0;
+        }
+        (self.m2 / (self.values.len() as f64 - 1.0)).sqrt()
+    }
+
+    /// Mean and stddev with recency weights, see [`decayed_mean_stddev`]. O(n) per
+    /// call.
+    pub(super) fn decayed_mean_stddev(&self, half_life: f64) -> (f64, f64) {
+        decayed_mean_stddev(&self.values, half_life)
+    }
+}
+
+/// Returns true if the long-timeframe z-score confirms the short-timeframe signal:
+/// beyond its own threshold and in the same direction.
+pub(super) fn timeframes_agree(short_z: f64, long_z: f64, long_z_threshold: f64) -> bool {
+    short_z.signum() == long_z.signum() && long_z.abs() > long_z_threshold
+}
+
+/// Performance over the most recent closing trades, see [`crate::MarketMaker::stats`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrategyStats {
+    /// Closing trades the other fields are computed over.
+    pub trades: usize,
+    /// Share of those trades with a positive realized `PnL`.
+    pub win_rate: Option<f64>,
+    pub avg_pnl: Option<f64>,
+    /// Mean over the standard deviation of the per-trade `PnL`.
+    pub sharpe: Option<f64>,
+}
+
+/// Rolling series of realized returns with risk-adjusted performance ratios.
+pub(super) struct ReturnSeries {
+    capacity: usize,
+    returns: VecDeque<f64>,
+}
+
+impl ReturnSeries {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            returns: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(super) fn push(&mut self, value: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.returns.len() == self.capacity {
+            self.returns.pop_front();
+        }
+        self.returns.push_back(value);
+    }
+
+    /// Mean return over its standard deviation, or `None` with fewer than two samples
+    /// or no variation.
+    pub(super) fn sharpe(&self) -> Option<f64> {
+        if self.returns.len() < 2 {
+            return None;
+        }
+        let (mean, stddev) = mean_stddev(&self.returns);
+        (stddev > f64::EPSILON).then(|| mean / stddev)
+    }
+
+    /// Trade count, win rate, average and Sharpe ratio, treating every return as the
+    /// result of one trade.
+    #[allow(clippy::cast_precision_loss)]
+    pub(super) fn strategy_stats(&self) -> StrategyStats {
+        let trades = self.returns.len();
+        let n = trades as f64;
+        let wins = self.returns.iter().filter(|r| **r > 0.0).count();
+        StrategyStats {
+            trades,
+            win_rate: (trades > 0).then(|| wins as f64 / n),
+            avg_pnl: (trades > 0).then(|| self.returns.iter().sum::<f64>() / n),
+            sharpe: self.sharpe(),
+        }
+    }
+
+    /// Mean return over the downside deviation (losses only), or `None` with fewer than
+    /// two samples or no losses.
+    #[allow(clippy::cast_precision_loss)]
+    pub(super) fn sortino(&self) -> Option<f64> {
+        if self.returns.len() < 2 {
+            return None;
+        }
+        let n = self.returns.len() as f64;
+        let mean = self.returns.iter().sum::<f64>() / n;
+        let downside = (self.returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n).sqrt();
+        (downside > f64::EPSILON).then(|| mean / downside)
+    }
+}
+
+/// Rolling correlation of the tick-to-tick log returns of the Hyperliquid and
+/// reference prices, which the diff only mean-reverts while it stays high.
+pub(super) struct ReturnCorrelation {
+    capacity: usize,
+    last: Option<(f64, f64)>,
+    returns: VecDeque<(f64, f64)>,
+}
+
+impl ReturnCorrelation {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            last: None,
+            returns: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(super) fn push(&mut self, hl_price: f64, reference_price: f64) {
+        if let Some((last_hl, last_reference)) = self.last {
+            if self.returns.len() == self.capacity {
+                self.returns.pop_front();
+            }
+            self.returns.push_back((
+                (hl_price / last_hl).ln(),
+                (reference_price / last_reference).ln(),
+            ));
+        }
+        self.last = Some((hl_price, reference_price));
+    }
+
+    /// Pearson correlation over the window, or `None` until it is full or while
+    /// either price stands still.
+    #[allow(clippy::cast_precision_loss)]
+    pub(super) fn correlation(&self) -> Option<f64> {
+        if self.returns.len() < self.capacity.max(2) {
+            return None;
+        }
+        let n = self.returns.len() as f64;
+        let (mean_hl, mean_reference) = self
+            .returns
+            .iter()
+            .fold((0.0, 0.0), |(hl, reference), (x, y)| {
+                (hl + x / n, reference + y / n)
+            });
+        let (mut covariance, mut var_hl, mut var_reference) = (0.0, 0.0, 0.0);
+        for (x, y) in &self.returns {
+            covariance += (x - mean_hl) * (y - mean_reference);
+            var_hl += (x - mean_hl).powi(2);
+            var_reference += (y - mean_reference).powi(2);
+        }
+        let scale = (var_hl * var_reference).sqrt();
+        (scale > f64::EPSILON.powi(2)).then(|| covariance / scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn rolling_window_matches_naive_test() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut window = RollingWindow::with_capacity(50);
+        for _ in 0..10_000 {
+            if window.len() == 50 {
+                window.pop_front();
+            }
+            window.push_back(rng.gen_range(-0.01..0.01) + 0.002);
+
+            let (mean, stddev) = mean_stddev(window.values());
+            assert!((window.mean() - mean).abs() < 1e-12);
+            assert!((window.stddev() - stddev).abs() < 1e-12);
+        }
+
+        while window.pop_front().is_some() {}
+        assert!(window.is_empty());
+        assert_eq!((window.mean(), window.stddev()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn return_correlation_test() {
+        let mut correlation = ReturnCorrelation::new(20);
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut price: f64 = 100.0;
+        for _ in 0..20 {
+            price *= 1.0 + rng.gen_range(-0.001..0.001);
+            correlation.push(price, price * 1.0002);
+        }
+        // Not full yet
+        assert_eq!(correlation.correlation(), None);
+        price *= 1.0005;
+        correlation.push(price, price * 1.0002);
+        assert!((correlation.correlation().unwrap() - 1.0).abs() < 1e-9);
+
+        // The reference decouples and moves on its own
+        let mut reference = price;
+        for _ in 0..20 {
+            price *= 1.0 + rng.gen_range(-0.001..0.001);
+            reference *= 1.0 + rng.gen_range(-0.001..0.001);
+            correlation.push(price, reference);
+        }
+        assert!(correlation.correlation().unwrap() < 0.5);
+
+        let mut flat = ReturnCorrelation::new(3);
+        for i in 0..5 {
+            flat.push(100.0 + f64::from(i), 100.0);
+        }
+        assert_eq!(flat.correlation(), None);
+    }
+
+    #[test]
+    fn ewma_converges_test() {
+        let mut ewma = Ewma::new(0.1, 3);
+        ewma.push(1.0);
+        ewma.push(-1.0);
+        assert!(!ewma.is_warm());
+        let stddev = ewma.stddev();
+        assert!(stddev > 0.0);
+
+        for _ in 0..500 {
+            ewma.push(0.5);
+        }
+        assert!(ewma.is_warm());
+        assert!((ewma.mean() - 0.5).abs() < 1e-9);
+        assert!(ewma.stddev() < 1e-9);
+    }
+
+    #[test]
+    fn evict_before_test() {
+        let start = Instant::now();
+        let mut window = RollingWindow::with_capacity(4);
+        for (i, value) in [1.0, 2.0, 3.0, 4.0].into_iter().enumerate() {
+            window.push_back_at(value, start + Duration::from_secs(i as u64));
+        }
+        window.evict_before(start + Duration::from_secs(2));
+        assert_eq!(window.values(), &[3.0, 4.0]);
+        assert!((window.mean() - 3.5).abs() < 1e-12);
+
+        window.evict_before(start + Duration::from_secs(10));
+        assert!(window.is_empty());
+    }
+
+    #[test]
+    fn prior_blend_test() {
+        let prior = WindowPrior {
+            mean_bps: 3.0,
+            stddev_bps: 1.5,
+            weight: 1.0,
+        };
+        let live_mean = 0.0005;
+        let live_stddev = 0.0001;
+
+        let (mean, stddev) = prior.blend(live_mean, live_stddev, 0, 100);
+        assert!((mean - 0.0003).abs() < 1e-12);
+        assert!((stddev - 0.00015).abs() < 1e-12);
+
+        let (mean, stddev) = prior.blend(live_mean, live_stddev, 50, 100);
+        assert!((mean - 0.0004).abs() < 1e-12);
+        // Average of the prior (1.5 bps)^2 and live (1 bp)^2 variances
+        let expected_variance: f64 = 1.625e-8;
+        assert!((stddev - expected_variance.sqrt()).abs() < 1e-12);
+
+        let (mean, stddev) = prior.blend(live_mean, live_stddev, 100, 100);
+        assert!((mean - live_mean).abs() < 1e-12);
+        assert!((stddev - live_stddev).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mean_stddev_test() {
+        let values: VecDeque<f64> = [1.0, 2.0, 3.0, 4.0].into_iter().collect();
+        let (mean, stddev) = mean_stddev(&values);
+        assert!((mean - 2.5).abs() < 1e-12);
+        assert!((stddev - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(mean_stddev(&VecDeque::new()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn decayed_mean_stddev_test() {
+        let newest_high: VecDeque<f64> = [0.0, 1.0].into_iter().collect();
+        let oldest_high: VecDeque<f64> = [1.0, 0.0].into_iter().collect();
+        // With a half-life of one tick the newest value weighs twice the oldest
+        let (mean, _) = decayed_mean_stddev(&newest_high, 1.0);
+        assert!((mean - 2.0 / 3.0).abs() < 1e-12);
+        let (mean, _) = decayed_mean_stddev(&oldest_high, 1.0);
+        assert!((mean - 1.0 / 3.0).abs() < 1e-12);
+
+        // A long half-life approaches equal weights
+        let values: VecDeque<f64> = [1.0, 2.0, 3.0, 4.0].into_iter().collect();
+        let (mean, stddev) = decayed_mean_stddev(&values, 1e12);
+        let (flat_mean, flat_stddev) = mean_stddev(&values);
+        assert!((mean - flat_mean).abs() < 1e-9);
+        assert!((stddev - flat_stddev).abs() < 1e-9);
+        assert_eq!(decayed_mean_stddev(&VecDeque::new(), 1.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn timeframes_agree_test() {
+        assert!(timeframes_agree(2.5, 1.5, 1.0));
+        assert!(timeframes_agree(-2.5, -1.5, 1.0));
+        // Long timeframe disagrees on direction
+        assert!(!timeframes_agree(2.5, -1.5, 1.0));
+        // Long timeframe agrees but isn't extreme enough
+        assert!(!timeframes_agree(2.5, 0.5, 1.0));
+    }
+
+    #[test]
+    fn trade_vwap_test() {
+        let mut vwap = TradeVwap::new(3);
+        assert_eq!(vwap.vwap(), None);
+        vwap.push(100.0, 1.0);
+        vwap.push(102.0, 3.0);
+        let value = vwap.push(101.0, 0.0).unwrap();
+        assert!((value - 101.5).abs() < 1e-9);
+
+        // The first trade leaves the window
+        let value = vwap.push(110.0, 1.0).unwrap();
+        assert!((value - 104.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn return_series_test() {
+        let mut series = ReturnSeries::new(4);
+        assert_eq!(series.sharpe(), None);
+        series.push(1.0);
+        assert_eq!(series.sharpe(), None);
+
+        // The oldest return is evicted
+        for value in [100.0, 2.0, -1.0, 3.0, 0.0] {
+            series.push(value);
+        }
+        // Mean 1, sample stddev sqrt(10/3)
+        let sharpe = series.sharpe().unwrap();
+        assert!((sharpe - 1.0 / (10.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        // Downside deviation sqrt(1/4)
+        let sortino = series.sortino().unwrap();
+        assert!((sortino - 2.0).abs() < 1e-12);
+
+        let mut no_losses = ReturnSeries::new(4);
+        no_losses.push(1.0);
+        no_losses.push(2.0);
+        assert_eq!(no_losses.sortino(), None);
+    }
+
+    #[test]
+    fn strategy_stats_test() {
+        let mut series = ReturnSeries::new(4);
+        assert_eq!(
+            series.strategy_stats(),
+            StrategyStats {
+                trades: 0,
+                win_rate: None,
+                avg_pnl: None,
+                sharpe: None,
+            }
+        );
+        for pnl in [5.0, 1.0, -1.0, 2.0, 3.0] {
+            series.push(pnl);
+        }
+        // The oldest trade fell out of the window
+        let stats = series.strategy_stats();
+        assert_eq!(stats.trades, 4);
+        assert!((stats.win_rate.unwrap() - 0.75).abs() < 1e-12);
+        assert!((stats.avg_pnl.unwrap() - 1.25).abs() < 1e-12);
+        assert!(stats.sharpe.unwrap() > 0.0);
+    }
+}
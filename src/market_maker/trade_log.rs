@@ -0,0 +1,146 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use tracing::error;
+
+use super::fills::RoundTrip;
+use crate::TradeInfo;
+
+const HEADER: &str = "timestamp,kind,asset,side,size,price,z,mean,stddev,funding_rate,result";
+
+/// Signal an order attempt was made on.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SignalSnapshot {
+    /// `None` for a band signal, which has no z-score.
+    pub(super) z: Option<f64>,
+    pub(super) mean: f64,
+    pub(super) stddev: f64,
+    pub(super) funding_rate: Option<f64>,
+}
+
+/// CSV record of every order attempt and fill, for analysing a run offline. Each
+/// row is flushed as it is written so a crash doesn't lose the tail.
+#[derive(Debug)]
+pub(super) struct TradeLog {
+    file: File,
+}
+
+impl TradeLog {
+    /// Appends to `path`, writing the header if the file is new or empty.
+    pub(super) fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{HEADER}")?;
+        }
+        Ok(Self { file })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn order(
+        &mut self,
+        time: i64,
+        asset: &str,
+        is_buy: bool,
+        sz: f64,
+        px: f64,
+        signal: Option<SignalSnapshot>,
+        result: &str,
+    ) {
+        let (z, mean, stddev, funding_rate) = signal.map_or_else(Default::default, |signal| {
+            (
+                signal.z.map_or_else(String::new, |z| z.to_string()),
+                signal.mean.to_string(),
+                signal.stddev.to_string(),
+                signal
+                    .funding_rate
+                    .map_or_else(String::new, |rate| rate.to_string()),
+            )
+        });
+        let side = if is_buy { "buy" } else { "sell" };
+        self.write_row(&format!(
+            "{time},order,{asset},{side},{sz},{px},{z},{mean},{stddev},{funding_rate},{result}"
+        ));
+    }
+
+    pub(super) fn fill(&mut self, fill: &TradeInfo) {
+        let side = if fill.side == "B" { "buy" } else { "sell" };
+        self.write_row(&format!(
+            "{},fill,{},{side},{},{},,,,,oid {}",
+            fill.time, fill.coin, fill.sz, fill.px, fill.oid
+        ));
+    }
+
+    /// A closed round trip, on the side it was entered on, with its diffs and `PnL`
+    /// split in the result column.
+    pub(super) fn round_trip(&mut self, time: u64, asset: &str, round_trip: &RoundTrip) {
+        let side = if round_trip.is_long { "buy" } else { "sell" };
+        self.write_row(&format!(
+            "{time},round_trip,{asset},{side},{},,,,,,entry_diff_bps {} exit_diff_bps {} edge_pnl {} drift_pnl {}",
+            round_trip.size,
+            round_trip.entry_diff_bps,
+            round_trip.exit_diff_bps,
+            round_trip.edge_pnl,
+            round_trip.drift_pnl
+        ));
+    }
+
+    fn write_row(&mut self, row: &str) {
+        if let Err(e) = writeln!(self.file, "{row}").and_then(|()| self.file.flush()) {
+            error!("Error writing trade log: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_maker::fills;
+
+    #[test]
+    fn trade_log_test() {
+        let path = std::env::temp_dir().join(format!("trade_log_{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let signal = SignalSnapshot {
+            z: Some(2.5),
+            mean: 0.1,
+            stddev: 0.2,
+            funding_rate: Some(0.0001),
+        };
+        TradeLog::open(&path).unwrap().order(
+            1,
+            "BTC",
+            true,
+            0.001,
+            100.5,
+            Some(signal),
+            "filled 0.001",
+        );
+        // Reopening appends without repeating the header
+        let mut log = TradeLog::open(&path).unwrap();
+        log.fill(&fills::fill("A", 0.001, 7, 2));
+        log.round_trip(
+            3,
+            "BTC",
+            &RoundTrip {
+                is_long: true,
+                size: 0.001,
+                entry_diff_bps: -12.5,
+                exit_diff_bps: 1.5,
+                edge_pnl: 0.0014,
+                drift_pnl: -0.0004,
+            },
+        );
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            contents.lines().collect::<Vec<_>>(),
+            [
+                HEADER,
+                "1,order,BTC,buy,0.001,100.5,2.5,0.1,0.2,0.0001,filled 0.001",
+                "2,fill,BTC,sell,0.001,100,,,,,oid 7",
+                "3,round_trip,BTC,buy,0.001,,,,,,entry_diff_bps -12.5 exit_diff_bps 1.5 edge_pnl 0.0014 drift_pnl -0.0004",
+            ]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use ethers::signers::LocalWallet;
+use std::path::Path;
+
+use super::MarketMakerError;
+
+/// Environment variable holding the hex private key.
+pub const PRIVATE_KEY_VAR: &str = "HL_PRIVATE_KEY";
+/// Environment variable holding the path of a file with the hex private key, used
+/// when [`PRIVATE_KEY_VAR`] isn't set.
+pub const PRIVATE_KEY_FILE_VAR: &str = "HL_PRIVATE_KEY_FILE";
+
+/// Wallet from the key in `HL_PRIVATE_KEY`, or else in the file `HL_PRIVATE_KEY_FILE`
+/// names.
+///
+/// # Errors
+///
+/// Returns [`MarketMakerError::Wallet`] if neither variable is set, the file can't be
+/// read or the key is malformed.
+#[allow(clippy::result_large_err)]
+pub fn load_wallet() -> Result<LocalWallet, MarketMakerError> {
+    if let Ok(key) = std::env::var(PRIVATE_KEY_VAR) {
+        return parse_private_key(&key);
+    }
+    let path = std::env::var(PRIVATE_KEY_FILE_VAR).map_err(|_| {
+        MarketMakerError::Wallet(format!("set {PRIVATE_KEY_VAR} or {PRIVATE_KEY_FILE_VAR}"))
+    })?;
+    load_wallet_file(Path::new(&path))
+}
+
+/// Wallet from a file holding the hex private key.
+///
+/// # Errors
+///
+/// Returns [`MarketMakerError::Wallet`] if the file can't be read or the key is
+/// malformed.
+#[allow(clippy::result_large_err)]
+pub fn load_wallet_file(path: &Path) -> Result<LocalWallet, MarketMakerError> {
+    let key = std::fs::read_to_string(path)
+        .map_err(|e| MarketMakerError::Wallet(format!("{}: {e}", path.display())))?;
+    parse_private_key(&key)
+}
+
+/// Wallet from a hex private key, with or without a `0x` prefix and surrounding
+/// whitespace. Errors never contain the key.
+///
+/// # Errors
+///
+/// Returns [`MarketMakerError::Wallet`] if the key isn't 32 hex bytes or not a valid
+/// secp256k1 key.
+#[allow(clippy::result_large_err)]
+pub fn parse_private_key(key: &str) -> Result<LocalWallet, MarketMakerError> {
+    let key = key.trim();
+    let key = key.strip_prefix("0x").unwrap_or(key);
+    if key.len() != 64 || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(MarketMakerError::Wallet(format!(
+            "private key must be 64 hex characters, got {}",
+            key.len()
+        )));
+    }
+    key.parse()
+        .map_err(|_| MarketMakerError::Wallet("private key is not a valid key".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::Signer;
+
+    const KEY: &str = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+
+    #[test]
+    fn parse_private_key_test() {
+        let wallet = parse_private_key(KEY).unwrap();
+        let prefixed = parse_private_key(&format!(" 0x{KEY}\n")).unwrap();
+        assert_eq!(wallet.address(), prefixed.address());
+
+        assert!(parse_private_key("").is_err());
+        let error = parse_private_key(&KEY.replace('e', "g"))
+            .unwrap_err()
+            .to_string();
+        assert!(!error.contains(&KEY[4..]));
+        // The zero scalar is hex but not a key
+        assert!(parse_private_key(&"0".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn load_wallet_file_test() {
+        let path = std::env::temp_dir().join(format!("wallet_{}.key", std::process::id()));
+        std::fs::write(&path, format!("{KEY}\n")).unwrap();
+        let wallet = load_wallet_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(wallet.is_ok());
+        assert!(load_wallet_file(&path).is_err());
+    }
+}
@@ -11,3 +11,27 @@ pub struct AssetMeta {
     pub name: String,
     pub sz_decimals: u32,
 }
+
+/// Spot pairs and the tokens they trade.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpotMeta {
+    pub universe: Vec<SpotAssetMeta>,
+    pub tokens: Vec<TokenInfo>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotAssetMeta {
+    /// Indices of the base and quote tokens.
+    pub tokens: [usize; 2],
+    pub name: String,
+    pub index: usize,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub name: String,
+    pub sz_decimals: u32,
+    pub index: usize,
+}
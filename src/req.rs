@@ -14,7 +14,7 @@ pub struct HttpClient {
     pub base_url: String,
 }
 
-async fn parse_response(response: Response) -> Result<String> {
+pub(crate) async fn parse_response(response: Response) -> Result<String> {
     let status_code = response.status().as_u16();
     let headers = response.headers().clone();
     let text = response
@@ -10,6 +10,7 @@ use ethers::{
 
 use crate::{prelude::*, proxy_digest::Sha256Proxy, signature::agent::l1, Error};
 
+#[allow(clippy::result_large_err)]
 pub(crate) fn sign_l1_action(
     wallet: &LocalWallet,
     connection_id: H256,
@@ -25,6 +26,7 @@ pub(crate) fn sign_l1_action(
     )
 }
 
+#[allow(clippy::result_large_err)]
 pub(crate) fn sign_typed_data<T: Eip712>(payload: &T, wallet: &LocalWallet) -> Result<Signature> {
     let encoded = payload
         .encode_eip712()
@@ -48,6 +50,7 @@ fn sign_hash(hash: H256, wallet: &LocalWallet) -> Signature {
 }
 
 #[cfg(test)]
+#[allow(clippy::result_large_err)]
 mod tests {
     use super::*;
     use crate::UsdSend;